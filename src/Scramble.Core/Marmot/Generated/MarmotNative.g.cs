@@ -19,19 +19,72 @@ namespace Scramble.Core.Marmot.Generated
 
 
         /// <summary>
-        ///  Get the last error message.
+        ///  Get the last error message of a call made on the calling thread.
         ///  Returns null if no error occurred.
         ///  The caller must free the returned string using `marmot_free_string`.
         /// </summary>
         [DllImport(__DllName, EntryPoint = "marmot_get_last_error", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         internal static extern byte* marmot_get_last_error();
 
+        /// <summary>
+        ///  Get the last error message of a specific client.
+        ///  Unlike `marmot_get_last_error`, this is not affected by operations on other
+        ///  clients. Failures to create a client are only reported by `marmot_get_last_error`.
+        ///  Returns null if the client's last operation succeeded, or if the client was
+        ///  not created by `marmot_create_client` or has been destroyed.
+        ///  The caller must free the returned string using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_client_get_last_error", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_client_get_last_error(MarmotClient* client);
+
+        /// <summary>
+        ///  Get the code of the last error.
+        ///
+        ///  # Returns
+        ///  0 if no error occurred, -1 for generic FFI errors (null pointers, invalid
+        ///  strings), otherwise the `MarmotError` code (e.g. 11 for a duplicate message).
+        ///  MDK failures report their kind: 12 storage, 2 group not found, 13 epoch,
+        ///  14 key package, 15 welcome, 16 message, and 3 for other MLS failures.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_last_error_code", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_last_error_code();
+
+        /// <summary>
+        ///  Get the last error of a call made on the calling thread as JSON, so that hosts
+        ///  can build error UIs without parsing messages.
+        ///
+        ///  # Returns
+        ///  `{"code": .., "message": "..", "operation": "add_member", "group_id": "<hex>"}`,
+        ///  where `operation` is the FFI function without its `marmot_` prefix and
+        ///  `group_id` is only present if the call targeted a group. Null if no error
+        ///  occurred. The caller must free the returned string using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_last_error_json", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_get_last_error_json();
+
+        /// <summary>
+        ///  Clear the last error of the calling thread, so that hosts can reset error state
+        ///  between units of work. Errors recorded on other threads or on clients are kept.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_clear_last_error", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern void marmot_clear_last_error();
+
+        /// <summary>
+        ///  Seed the crate's RNG for the calling thread, so that calls made on it are
+        ///  reproducible. MLS key material and group IDs come from MDK's crypto provider
+        ///  and cannot be seeded: group IDs still differ between runs.
+        ///  Only available with the `test-rng` feature; never enable it in release builds.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_seed_test_rng", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern void marmot_seed_test_rng(ulong seed);
+
         /// <summary>
         ///  Create a new Marmot client with the given Nostr identity.
         ///
         ///  # Arguments
         ///  * `private_key_hex` - The Nostr private key in hex format
-        ///  * `public_key_hex` - The Nostr public key in hex format
+        ///  * `public_key_hex` - The Nostr public key in hex format; must match the
+        ///    private key, or be empty to derive it
         ///  * `db_path` - Path to SQLite database file for persistent storage, or null for in-memory
         ///
         ///  # Returns
@@ -41,31 +94,255 @@ namespace Scramble.Core.Marmot.Generated
         [DllImport(__DllName, EntryPoint = "marmot_create_client", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         internal static extern MarmotClient* marmot_create_client(byte* private_key_hex, byte* public_key_hex, byte* db_path);
 
+        /// <summary>
+        ///  Create a new in-memory Marmot client that uses the given MLS ciphersuite.
+        ///
+        ///  # Arguments
+        ///  * `private_key_hex` - The Nostr private key in hex format
+        ///  * `public_key_hex` - The Nostr public key in hex format
+        ///  * `ciphersuite` - IANA ciphersuite value, e.g. 1 for
+        ///    MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519
+        ///
+        ///  # Returns
+        ///  A pointer to the client, or null on failure. An unsupported ciphersuite
+        ///  fails with an error listing the supported values.
+        ///  The caller must free the client using `marmot_destroy_client`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_create_client_with_ciphersuite", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern MarmotClient* marmot_create_client_with_ciphersuite(byte* private_key_hex, byte* public_key_hex, ushort ciphersuite);
+
+        /// <summary>
+        ///  Create a read-only "observer" client. It can join groups via welcomes,
+        ///  decrypt messages and read group info, but creating groups, adding or
+        ///  removing members and rekeying all fail.
+        ///
+        ///  # Arguments
+        ///  * `private_key_hex` - The Nostr private key in hex format
+        ///  * `public_key_hex` - The Nostr public key in hex format
+        ///
+        ///  # Returns
+        ///  A pointer to the client, or null on failure.
+        ///  The caller must free the client using `marmot_destroy_client`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_create_observer_client", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern MarmotClient* marmot_create_observer_client(byte* private_key_hex, byte* public_key_hex);
+
+        /// <summary>
+        ///  Create a new in-memory Marmot client from a raw secret key, without passing
+        ///  it as a hex string. The public key is derived from it.
+        ///
+        ///  # Arguments
+        ///  * `secret_key` - The 32-byte Nostr private key
+        ///
+        ///  # Returns
+        ///  A pointer to the client, or null on failure.
+        ///  The caller must free the client using `marmot_destroy_client`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_create_client_from_keypair", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern MarmotClient* marmot_create_client_from_keypair(byte* secret_key, int secret_key_length);
+
+        /// <summary>
+        ///  Create a new Marmot client from a JSON config, the extensible alternative
+        ///  to the other `marmot_create_client*` functions.
+        ///
+        ///  # Arguments
+        ///  * `private_key_hex` - The Nostr private key in hex format
+        ///  * `public_key_hex` - The Nostr public key in hex format; must match the
+        ///    private key, or be empty to derive it
+        ///  * `config_json` - JSON object `{ "storage": "memory"|"sqlite", "db_path",
+        ///    "relays": [...], "ciphersuite", "members_json_version": 1|2 }`, every key
+        ///    optional, or null for the defaults. Unknown keys are ignored with a warning.
+        ///
+        ///  # Returns
+        ///  A pointer to the client, or null on failure. SQLite storage is not
+        ///  available in this build.
+        ///  The caller must free the client using `marmot_destroy_client`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_create_client_ex", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern MarmotClient* marmot_create_client_ex(byte* private_key_hex, byte* public_key_hex, byte* config_json);
+
         /// <summary>
         ///  Destroy a Marmot client and free its resources.
+        ///  Destroying a client that was already destroyed is a no-op.
         /// </summary>
         [DllImport(__DllName, EntryPoint = "marmot_destroy_client", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         internal static extern void marmot_destroy_client(MarmotClient* client);
 
+        /// <summary>
+        ///  Destroy a Marmot client like `marmot_destroy_client`, first erasing its
+        ///  Nostr secret key so it does not linger in freed memory. Slower; for hosts
+        ///  with strict memory-hygiene requirements.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_wipe_client", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern void marmot_wipe_client(MarmotClient* client);
+
+        /// <summary>
+        ///  Check whether a client handle is still valid, for use by managed `SafeHandle` wrappers.
+        ///
+        ///  # Returns
+        ///  1 if the client was created by `marmot_create_client` and not yet destroyed, 0 otherwise.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_client_is_valid", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_client_is_valid(MarmotClient* client);
+
         /// <summary>
         ///  Generate a new KeyPackage for group invitations.
         ///
         ///  # Returns
         ///  A pointer to the KeyPackage data, or null on failure.
-        ///  The caller must free the buffer using `marmot_free_buffer`.
+        ///  The caller must free the buffer using `marmot_free_buffer_zeroize`, as it holds key material.
         /// </summary>
         [DllImport(__DllName, EntryPoint = "marmot_generate_key_package", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         internal static extern byte* marmot_generate_key_package(MarmotClient* client, int* data_length);
 
+        /// <summary>
+        ///  Generate a new KeyPackage for group invitations, as
+        ///  `marmot_generate_key_package`.
+        ///
+        ///  # Returns
+        ///  The KeyPackage data with its length, or a null buffer on failure.
+        ///  The caller must free the buffer using `marmot_free_owned_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_generate_key_package_buffer", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern MarmotBuffer marmot_generate_key_package_buffer(MarmotClient* client);
+
+        /// <summary>
+        ///  Generate a new KeyPackage valid for the given number of days.
+        ///
+        ///  # Returns
+        ///  A pointer to the KeyPackage data, or null on failure (including a zero lifetime).
+        ///  The caller must free the buffer using `marmot_free_buffer_zeroize`, as it holds key material.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_generate_key_package_with_lifetime", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_generate_key_package_with_lifetime(MarmotClient* client, uint valid_days, int* data_length);
+
+        /// <summary>
+        ///  List the key packages generated by this client whose private keys are
+        ///  still stored, to audit outstanding key material after rotation.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        ///  On success `key_packages_json` receives a JSON array of `{ "key_package_ref",
+        ///  "created_at" }` objects, oldest first, where `key_package_ref` is the hex MLS
+        ///  key package reference. The caller must free it using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_list_stored_key_packages", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_list_stored_key_packages(MarmotClient* client, byte** key_packages_json);
+
+        /// <summary>
+        ///  Delete a stored key package's private keys, e.g. once it has been used.
+        ///
+        ///  # Arguments
+        ///  * `key_package_ref` - Hex key package reference, as listed by
+        ///    `marmot_list_stored_key_packages`
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure (including an unknown reference).
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_delete_key_package", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_delete_key_package(MarmotClient* client, byte* key_package_ref);
+
+        /// <summary>
+        ///  Set the maximum number of relays listed in generated key packages (5 by
+        ///  default). When the client has more relays, only the first `max` are used.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure (including a zero `max`).
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_set_max_key_package_relays", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_set_max_key_package_relays(MarmotClient* client, uint max);
+
         /// <summary>
         ///  Create a new MLS group.
         ///
+        ///  # Arguments
+        ///  * `config_json` - Receives the group's resolved configuration as a JSON
+        ///    object `{ "name", "description", "relays", "admins" }`, saving a call to
+        ///    read it back; free using `marmot_free_string`. May be null to skip it.
+        ///
         ///  # Returns
-        ///  A pointer to the group ID, or null on failure.
+        ///  A pointer to the group ID, or null on failure (including a null
+        ///  `group_id_length` or `epoch`, and a requested configuration that could not
+        ///  be read back). `epoch` receives the group's actual epoch.
         ///  The caller must free the buffer using `marmot_free_buffer`.
         /// </summary>
         [DllImport(__DllName, EntryPoint = "marmot_create_group", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern byte* marmot_create_group(MarmotClient* client, byte* group_name, int* group_id_length, ulong* epoch);
+        internal static extern byte* marmot_create_group(MarmotClient* client, byte* group_name, int* group_id_length, ulong* epoch, byte** config_json);
+
+        /// <summary>
+        ///  Create a new MLS group with additional admins.
+        ///
+        ///  # Arguments
+        ///  * `admins_json` - JSON array of hex public keys. Duplicates are dropped and
+        ///    the creator is always an admin; an invalid key fails with the invalid key
+        ///    code and names the entry.
+        ///
+        ///  # Returns
+        ///  A pointer to the group ID, or null on failure. `epoch` receives the group's
+        ///  actual epoch. The caller must free the buffer using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_create_group_with_admins", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_create_group_with_admins(MarmotClient* client, byte* group_name, byte* admins_json, int* group_id_length, ulong* epoch);
+
+        /// <summary>
+        ///  Create a new MLS group, as `marmot_create_group`.
+        ///
+        ///  # Returns
+        ///  The group ID with its length, or a null buffer on failure (including a
+        ///  null `epoch`). `epoch` receives the group's actual epoch.
+        ///  The caller must free the buffer using `marmot_free_owned_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_create_group_buffer", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern MarmotBuffer marmot_create_group_buffer(MarmotClient* client, byte* group_name, ulong* epoch);
+
+        /// <summary>
+        ///  Create a new MLS group with its founding members in a single commit.
+        ///
+        ///  # Arguments
+        ///  * `key_package_events_json` - JSON array of the members' KeyPackage events
+        ///
+        ///  # Returns
+        ///  A pointer to the group ID, or null on failure.
+        ///  On success `welcomes_json` receives a JSON array with one welcome rumor per
+        ///  founding member, which the caller must free using `marmot_free_string`.
+        ///  The caller must free the group ID using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_create_group_with_members", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_create_group_with_members(MarmotClient* client, byte* group_name, byte* key_package_events_json, int key_package_events_length, int* group_id_length, ulong* epoch, byte** welcomes_json);
+
+        /// <summary>
+        ///  Re-initialize a group after a suspected compromise, migrating its members
+        ///  to a fresh group. The old group can no longer be used to send messages
+        ///  from this client. That is held in memory only: it is not part of exported
+        ///  group state, so a client restored from a backup can send to the old group.
+        ///
+        ///  # Arguments
+        ///  * `key_package_events_json` - JSON array of fresh KeyPackage events, one per other member
+        ///
+        ///  # Returns
+        ///  A pointer to the new group ID, or null on failure.
+        ///  On success `welcomes_json` receives a JSON array of welcome rumors, which the
+        ///  caller must free using `marmot_free_string`.
+        ///  The caller must free the group ID using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_reinit_group", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_reinit_group(MarmotClient* client, byte* group_id, int group_id_length, byte* key_package_events_json, int key_package_events_length, int* new_group_id_length, byte** welcomes_json);
+
+        /// <summary>
+        ///  Create a sub-conversation of a group: a new group with the parent's members
+        ///  and relays and its own message stream. Every other member is re-invited.
+        ///
+        ///  # Arguments
+        ///  * `key_package_events_json` - JSON array of KeyPackage events, one per other member
+        ///
+        ///  # Returns
+        ///  A pointer to the subgroup ID, or null on failure.
+        ///  On success `welcomes_json` receives a JSON array of welcome rumors, which the
+        ///  caller must free using `marmot_free_string`.
+        ///  The caller must free the group ID using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_create_subgroup", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_create_subgroup(MarmotClient* client, byte* parent_group_id, int parent_group_id_length, byte* group_name, byte* key_package_events_json, int key_package_events_length, int* subgroup_id_length, byte** welcomes_json);
 
         /// <summary>
         ///  Add a member to a group using their KeyPackage.
@@ -77,6 +354,37 @@ namespace Scramble.Core.Marmot.Generated
         [DllImport(__DllName, EntryPoint = "marmot_add_member", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         internal static extern byte* marmot_add_member(MarmotClient* client, byte* group_id, int group_id_length, byte* key_package_data, int key_package_length, int* welcome_length);
 
+        /// <summary>
+        ///  Check a KeyPackage event before adding it to a group, without committing anything.
+        ///
+        ///  # Arguments
+        ///  * `group_id` - The group the key package would be added to
+        ///  * `key_package_event_json` - JSON-serialized KeyPackage event
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure. Expired packages fail with the
+        ///  key-package-expired code (17) and packages for a ciphersuite other than
+        ///  the group's with the unsupported-ciphersuite code (18).
+        ///  On success `info_json` receives `{ "public_key", "ciphersuite", "not_before",
+        ///  "not_after" }`, which the caller must free using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_validate_key_package", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_validate_key_package(MarmotClient* client, byte* group_id, int group_id_length, byte* key_package_event_json, int key_package_length, byte** info_json);
+
+        /// <summary>
+        ///  Add a member to a group from a bare KeyPackage, without a wrapping Nostr event.
+        ///
+        ///  # Arguments
+        ///  * `content_b64` - The base64 KeyPackage (`content` from `marmot_generate_key_package`)
+        ///  * `tags_json` - JSON array of tags (`tags` from `marmot_generate_key_package`)
+        ///
+        ///  # Returns
+        ///  A pointer to the Welcome message data, or null on failure.
+        ///  The caller must free the buffer using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_add_member_from_key_package", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_add_member_from_key_package(MarmotClient* client, byte* group_id, int group_id_length, byte* content_b64, byte* tags_json, int* welcome_length);
+
         /// <summary>
         ///  Process a Welcome message to join a group.
         ///
@@ -86,6 +394,79 @@ namespace Scramble.Core.Marmot.Generated
         [DllImport(__DllName, EntryPoint = "marmot_process_welcome", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         internal static extern byte* marmot_process_welcome(MarmotClient* client, byte* welcome_data, int welcome_length, int* group_id_length, ulong* epoch, byte** group_name, byte** members_json);
 
+        /// <summary>
+        ///  NIP-59 gift wrap a welcome rumor from `marmot_add_member` for its recipient,
+        ///  ready to publish as a kind 1059 event.
+        ///
+        ///  # Arguments
+        ///  * `welcome_rumor_json` - One welcome rumor produced by this client
+        ///  * `recipient_public_key_hex` - The invitee's Nostr public key
+        ///  * `randomize_timestamp` - Non-zero (recommended) to shift the seal and wrapper
+        ///    timestamps up to 48 hours into the past, hiding when the invite was sent
+        ///
+        ///  # Returns
+        ///  A pointer to the gift wrap event JSON, or null on failure.
+        ///  The caller must free the string using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_wrap_welcome", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_wrap_welcome(MarmotClient* client, byte* welcome_rumor_json, byte* recipient_public_key_hex, int randomize_timestamp);
+
+        /// <summary>
+        ///  Process a Welcome delivered as a signed NIP-59 gift wrap (kind 1059) and
+        ///  join its group. Unlike `marmot_process_welcome`, the host passes the gift
+        ///  wrap event exactly as received from a relay.
+        ///
+        ///  # Returns
+        ///  A pointer to the group ID, or null on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_process_welcome_event", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_process_welcome_event(MarmotClient* client, byte* gift_wrap_event, int gift_wrap_length, int* group_id_length, ulong* epoch, byte** group_name, byte** members_json);
+
+        /// <summary>
+        ///  Process a Welcome message without joining the group, so the user can review the invite.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        ///  On success `preview_json` receives a JSON object with `welcome_id`, `group_id`,
+        ///  `group_name`, `inviter` and `members`, which the caller must free using
+        ///  `marmot_free_string`. Pass `welcome_id` to `marmot_accept_welcome` or
+        ///  `marmot_decline_welcome`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_preview_welcome", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_preview_welcome(MarmotClient* client, byte* welcome_data, int welcome_length, byte** preview_json);
+
+        /// <summary>
+        ///  Accept a welcome returned by `marmot_preview_welcome` and join its group.
+        ///
+        ///  # Returns
+        ///  A pointer to the group ID, or null on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_accept_welcome", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_accept_welcome(MarmotClient* client, byte* welcome_id, int* group_id_length, ulong* epoch, byte** group_name, byte** members_json);
+
+        /// <summary>
+        ///  Decline a welcome returned by `marmot_preview_welcome`, without joining.
+        ///  A declined welcome no longer appears in `marmot_pending_welcomes`.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure. An unknown welcome ID fails with the
+        ///  welcome-not-found error code.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_decline_welcome", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_decline_welcome(MarmotClient* client, byte* welcome_id);
+
+        /// <summary>
+        ///  List welcomes that have been processed but neither accepted nor declined.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        ///  On success `welcomes_json` receives a JSON array of `{ "welcome_id",
+        ///  "group_id", "group_name", "inviter" }`, which the caller must free using
+        ///  `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_pending_welcomes", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_pending_welcomes(MarmotClient* client, byte** welcomes_json);
+
         /// <summary>
         ///  Encrypt a message for a group.
         ///
@@ -96,91 +477,969 @@ namespace Scramble.Core.Marmot.Generated
         internal static extern byte* marmot_encrypt_message(MarmotClient* client, byte* group_id, int group_id_length, byte* plaintext, int* ciphertext_length);
 
         /// <summary>
-        ///  Decrypt a message from a group.
+        ///  Encrypt a message for a group, as `marmot_encrypt_message`, also reporting
+        ///  the event's ID.
+        ///
+        ///  # Arguments
+        ///  * `event_id` - Receives the event ID as 64-char hex, the ID relays compute
+        ///    and echo in their OK response; free using `marmot_free_string`.
         ///
         ///  # Returns
-        ///  A pointer to the plaintext string, or null on failure.
+        ///  A pointer to the ciphertext, or null on failure.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "marmot_decrypt_message", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern byte* marmot_decrypt_message(MarmotClient* client, byte* group_id, int group_id_length, byte* ciphertext, int ciphertext_length, byte** sender_public_key, ulong* epoch);
+        [DllImport(__DllName, EntryPoint = "marmot_encrypt_message_ex", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_encrypt_message_ex(MarmotClient* client, byte* group_id, int group_id_length, byte* plaintext, int* ciphertext_length, byte** event_id);
 
         /// <summary>
-        ///  Process a commit message.
+        ///  Encrypt a message for a group, as `marmot_encrypt_message`.
+        ///
+        ///  # Returns
+        ///  The ciphertext with its length, or a null buffer on failure.
+        ///  The caller must free the buffer using `marmot_free_owned_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_encrypt_message_buffer", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern MarmotBuffer marmot_encrypt_message_buffer(MarmotClient* client, byte* group_id, int group_id_length, byte* plaintext);
+
+        /// <summary>
+        ///  Encrypt a message for a group, failing if the group is not at the epoch
+        ///  the host expects (for example because a commit has not been processed).
+        ///
+        ///  # Arguments
+        ///  * `expected_epoch` - Epoch the host believes the group is at
+        ///
+        ///  # Returns
+        ///  A pointer to the ciphertext, or null on failure.
+        ///  An epoch mismatch fails with an invalid state error before encrypting.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_encrypt_message_at_epoch", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_encrypt_message_at_epoch(MarmotClient* client, byte* group_id, int group_id_length, byte* plaintext, ulong expected_epoch, int* ciphertext_length);
+
+        /// <summary>
+        ///  Set the rumor kind used for outgoing messages when no kind is given
+        ///  (9, a chat message, by default).
         ///
         ///  # Returns
         ///  0 on success, non-zero on failure.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "marmot_process_commit", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern int marmot_process_commit(MarmotClient* client, byte* group_id, int group_id_length, byte* commit_data, int commit_length);
+        [DllImport(__DllName, EntryPoint = "marmot_set_default_message_kind", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_set_default_message_kind(MarmotClient* client, ushort kind);
 
         /// <summary>
-        ///  Update keys for forward secrecy.
+        ///  Set the maximum size of outgoing message content, in bytes (65536 by
+        ///  default). Larger messages fail with "message too large" before encryption.
+        ///  Binary payloads count with their base64 encoding.
         ///
         ///  # Returns
-        ///  A pointer to the commit data, or null on failure.
+        ///  0 on success, non-zero on failure (including a zero limit).
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "marmot_update_keys", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern byte* marmot_update_keys(MarmotClient* client, byte* group_id, int group_id_length, int* commit_length);
+        [DllImport(__DllName, EntryPoint = "marmot_set_max_message_bytes", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_set_max_message_bytes(MarmotClient* client, uint max_bytes);
 
         /// <summary>
-        ///  Remove a member from a group.
+        ///  Set how many times a failed storage operation (e.g. a locked database) is
+        ///  retried, with a short backoff, before its error is reported. Defaults to 3;
+        ///  zero disables retries. Only operations that are safe to repeat, deleting a
+        ///  key package and resetting a group, are retried; MLS protocol errors never are.
         ///
         ///  # Returns
-        ///  A pointer to the commit data, or null on failure.
+        ///  0 on success, non-zero if the client is null.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "marmot_remove_member", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern byte* marmot_remove_member(MarmotClient* client, byte* group_id, int group_id_length, byte* member_public_key, int* commit_length);
+        [DllImport(__DllName, EntryPoint = "marmot_set_storage_retry_count", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_set_storage_retry_count(MarmotClient* client, uint count);
 
         /// <summary>
-        ///  Get information about a group.
+        ///  Choose the shape of every `members_json` this client returns (group info,
+        ///  welcomes, member pages and past rosters):
+        ///  * 1 (default) - a flat JSON array of hex public keys
+        ///  * 2 - `{ "v": 2, "members": [{ "pubkey": "<hex>", "admin": bool }, ...] }`,
+        ///    marking the group's current admins
         ///
         ///  # Returns
-        ///  0 on success, non-zero if group not found.
+        ///  0 on success, non-zero on failure (including an unknown version).
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "marmot_get_group_info", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern int marmot_get_group_info(MarmotClient* client, byte* group_id, int group_id_length, byte** group_name, ulong* epoch, byte** members_json);
+        [DllImport(__DllName, EntryPoint = "marmot_set_members_json_version", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_set_members_json_version(MarmotClient* client, uint version);
 
         /// <summary>
-        ///  Export group state for persistence.
+        ///  Encrypt a reply to an earlier message in a group.
+        ///
+        ///  # Arguments
+        ///  * `parent_event_id` - ID of the message being replied to, as 64-char hex
         ///
         ///  # Returns
-        ///  A pointer to the state data, or null on failure.
+        ///  A pointer to the ciphertext, or null on failure.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "marmot_export_group_state", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern byte* marmot_export_group_state(MarmotClient* client, byte* group_id, int group_id_length, int* state_length);
+        [DllImport(__DllName, EntryPoint = "marmot_encrypt_reply", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_encrypt_reply(MarmotClient* client, byte* group_id, int group_id_length, byte* plaintext, byte* parent_event_id, int* ciphertext_length);
 
         /// <summary>
-        ///  Import group state from persistence.
+        ///  Decrypt a message from a group.
+        ///
+        ///  # Arguments
+        ///  * `timestamp` - Receives the sender's `created_at` from the message rumor
+        ///    (seconds since the Unix epoch), for ordering backfilled history
         ///
         ///  # Returns
-        ///  0 on success, non-zero on failure.
+        ///  A pointer to the plaintext string, or null on failure. A message that
+        ///  belongs to a different group than `group_id` fails with "group id mismatch".
+        ///  Replayed events that were already processed fail with the duplicate
+        ///  message error code, which hosts can safely ignore. Content containing a NUL
+        ///  byte fails with an invalid state error; pass the same event to
+        ///  `marmot_decrypt_message_raw` to get it. Only the 32 most recent such
+        ///  messages are held; older ones are lost.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "marmot_import_group_state", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern int marmot_import_group_state(MarmotClient* client, byte* group_id, int group_id_length, byte* state, int state_length);
+        [DllImport(__DllName, EntryPoint = "marmot_decrypt_message", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_decrypt_message(MarmotClient* client, byte* group_id, int group_id_length, byte* ciphertext, int ciphertext_length, byte** sender_public_key, ulong* epoch, ulong* timestamp);
 
         /// <summary>
-        ///  Free a buffer allocated by this library.
+        ///  Decrypt a message, returning the decrypted rumor as its original JSON
+        ///  (with pubkey, kind, tags, content and created_at), for hosts that want
+        ///  more than `marmot_decrypt_message` reports.
+        ///
+        ///  # Returns
+        ///  A pointer to the rumor JSON, or null on failure (including a commit or
+        ///  proposal). The caller must free the string using `marmot_free_string`.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "marmot_free_buffer", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern void marmot_free_buffer(byte* buffer);
+        [DllImport(__DllName, EntryPoint = "marmot_decrypt_to_rumor_json", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_decrypt_to_rumor_json(MarmotClient* client, byte* group_id, int group_id_length, byte* ciphertext, int ciphertext_length);
 
         /// <summary>
-        ///  Free a string allocated by this library.
+        ///  Decrypt a page of message events in one call.
+        ///
+        ///  # Arguments
+        ///  * `events_json` - JSON array of Nostr events
+        ///  * `results_json` - Receives a JSON array with one `{ "ok", "sender",
+        ///    "content", "epoch", "error" }` object per event, in input order.
+        ///    Free using `marmot_free_string`.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero if the batch itself is invalid. Events that fail
+        ///  to decrypt are reported in `results_json` and do not stop the rest.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "marmot_free_string", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        internal static extern void marmot_free_string(byte* s);
+        [DllImport(__DllName, EntryPoint = "marmot_decrypt_messages", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_decrypt_messages(MarmotClient* client, byte* group_id, int group_id_length, byte* events_json, int events_length, byte** results_json);
 
+        /// <summary>
+        ///  Decrypt a message from a group, returning the content as raw bytes.
+        ///  Unlike `marmot_decrypt_message`, content containing NUL bytes is preserved,
+        ///  including for an event `marmot_decrypt_message` already rejected for it.
+        ///
+        ///  # Returns
+        ///  A pointer to the plaintext bytes, or null on failure.
+        ///  The caller must free the buffer using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_decrypt_message_raw", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_decrypt_message_raw(MarmotClient* client, byte* group_id, int group_id_length, byte* ciphertext, int ciphertext_length, byte** sender_public_key, ulong* epoch, int* plaintext_length);
 
-    }
+        /// <summary>
+        ///  Encrypt a binary payload for a group. Unlike `marmot_encrypt_message`,
+        ///  the data need not be UTF-8 and may contain NUL bytes.
+        ///
+        ///  # Returns
+        ///  A pointer to the ciphertext, or null on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_encrypt_binary", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_encrypt_binary(MarmotClient* client, byte* group_id, int group_id_length, byte* data, int data_length, int* ciphertext_length);
 
-    /// <summary>
-    ///  The main Marmot client that wraps MDK for FFI access.
-    /// </summary>
+        /// <summary>
+        ///  Decrypt a message sent with `marmot_encrypt_binary`.
+        ///
+        ///  # Returns
+        ///  A pointer to the payload bytes, or null on failure (including a message
+        ///  that does not carry binary content).
+        ///  The caller must free the buffer using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_decrypt_binary", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_decrypt_binary(MarmotClient* client, byte* group_id, int group_id_length, byte* ciphertext, int ciphertext_length, byte** sender_public_key, ulong* epoch, int* data_length);
+
+        /// <summary>
+        ///  Encrypt a message whose content is additionally NIP-44 sealed to the group.
+        ///
+        ///  # Returns
+        ///  A pointer to the ciphertext, or null on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_encrypt_message_sealed", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_encrypt_message_sealed(MarmotClient* client, byte* group_id, int group_id_length, byte* plaintext, int* ciphertext_length);
+
+        /// <summary>
+        ///  Decrypt a message from a group, unsealing content sent with
+        ///  `marmot_encrypt_message_sealed`. Plain messages are returned unchanged.
+        ///
+        ///  # Arguments
+        ///  * `sealed` - Receives 1 if the content was sealed, 0 otherwise
+        ///
+        ///  # Returns
+        ///  A pointer to the plaintext string, or null on failure. Content sealed in an
+        ///  epoch whose exporter secret MDK no longer keeps fails with the epoch-too-old
+        ///  code (20).
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_decrypt_message_sealed", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_decrypt_message_sealed(MarmotClient* client, byte* group_id, int group_id_length, byte* ciphertext, int ciphertext_length, byte** sender_public_key, ulong* epoch, int* sealed);
+
+        /// <summary>
+        ///  Set the callback invoked for each application message handled by
+        ///  `marmot_process_events`. Pass null to clear it.
+        ///  The callback receives (group_id, group_id_length, sender_public_key, content,
+        ///  kind, epoch); its pointers are only valid for the duration of the call.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_set_message_callback", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_set_message_callback(MarmotClient* client, delegate* unmanaged[Cdecl]<byte*, int, byte*, byte*, ushort, ulong, void> callback);
+
+        /// <summary>
+        ///  Set a proxy that receives the JSON of every event the client would publish
+        ///  (encrypted messages, commits and unwrapped welcome rumors), so tests can
+        ///  pass them between in-process clients without relays. Pass null to clear it.
+        ///  Only available with the `test-transport` feature; never enable it in release builds.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_set_nostr_proxy", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_set_nostr_proxy(MarmotClient* client, delegate* unmanaged[Cdecl]<byte*, void> callback);
+
+        /// <summary>
+        ///  Feed back an event captured by the proxy set with `marmot_set_nostr_proxy`,
+        ///  as if it came from a relay. Welcome rumors are accepted; any other event is
+        ///  handled like `marmot_process_message`.
+        ///  Only available with the `test-transport` feature; never enable it in release builds.
+        ///
+        ///  # Arguments
+        ///  * `result_json` - Receives the `marmot_process_message` result, or a `joined`
+        ///    object (with `group_id` and `epoch`) for a welcome rumor.
+        ///    Free using `marmot_free_string`.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_replay_published_event", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_replay_published_event(MarmotClient* client, byte* event_json, int event_length, byte** result_json);
+
+        /// <summary>
+        ///  Process a batch of incoming message events, dispatching application
+        ///  messages to the callback set by `marmot_set_message_callback`.
+        ///  Commits in the batch are applied; replayed events are skipped. An event
+        ///  that fails does not stop the rest of the batch.
+        ///
+        ///  # Arguments
+        ///  * `events_json` - JSON array of Nostr events
+        ///  * `errors_json` - Receives a JSON array of `{ "index", "event_id", "code",
+        ///    "message" }` objects for the events that failed, in batch order.
+        ///    Must be freed using `marmot_free_string`.
+        ///
+        ///  # Returns
+        ///  The number of application messages dispatched, or -1 if the batch could
+        ///  not be read.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_process_events", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_process_events(MarmotClient* client, byte* events_json, int events_length, byte** errors_json);
+
+        /// <summary>
+        ///  Process any relay-delivered group event, without the caller having to know
+        ///  whether it carries a message, a commit or a proposal.
+        ///
+        ///  # Arguments
+        ///  * `result_json` - Receives a JSON object tagged by `type`: `message` (with
+        ///    `group_id`, `sender`, `content`, `epoch` and `kind`), `commit` (with
+        ///    `group_id` and the new `epoch`), `proposal`, or `welcome_unsupported` for
+        ///    welcomes, which go through `marmot_process_welcome_event`.
+        ///    Free using `marmot_free_string`.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_process_message", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_process_message(MarmotClient* client, byte* event_json, int event_length, byte** result_json);
+
+        /// <summary>
+        ///  Process a commit message.
+        ///
+        ///  # Returns
+        ///  0 on success, including for a commit that was already applied (e.g.
+        ///  redelivered by a relay), non-zero on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_process_commit", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_process_commit(MarmotClient* client, byte* group_id, int group_id_length, byte* commit_data, int commit_length);
+
+        /// <summary>
+        ///  Process a commit message, reporting its effect on the group.
+        ///
+        ///  # Arguments
+        ///  * `new_epoch` - Receives the group's epoch after the commit
+        ///  * `member_delta` - Receives the net change in member count (e.g. -1 for a removal)
+        ///  * `effect_json` - Receives a JSON object with `kind` (commit, proposal,
+        ///    external_join, unprocessable or other), `epoch`, `member_delta`, and the
+        ///    `added` and `removed` member public keys. Free using `marmot_free_string`.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure. An unprocessable commit is reported
+        ///  through `effect_json` rather than as a failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_process_commit_ex", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_process_commit_ex(MarmotClient* client, byte* group_id, int group_id_length, byte* commit_data, int commit_length, ulong* new_epoch, int* member_delta, byte** effect_json);
+
+        /// <summary>
+        ///  Apply commits missed while offline. The commits may be supplied in any order;
+        ///  they are applied in epoch order and those already applied are skipped.
+        ///
+        ///  # Arguments
+        ///  * `events_json` - JSON array of commit events
+        ///  * `failed_json` - Receives a JSON array of the IDs of the events that could
+        ///    not be applied. Must be freed using `marmot_free_string`.
+        ///
+        ///  # Returns
+        ///  The number of commits that advanced the group state, or -1 on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_reprocess_commits", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_reprocess_commits(MarmotClient* client, byte* group_id, int group_id_length, byte* events_json, int events_length, byte** failed_json);
+
+        /// <summary>
+        ///  Process a commit message, reporting only the members it added and removed,
+        ///  so the host can update its member list without re-reading the roster.
+        ///
+        ///  # Arguments
+        ///  * `added_json` - Receives a JSON array of the added members' public keys
+        ///  * `removed_json` - Receives a JSON array of the removed members' public keys
+        ///
+        ///  Both strings must be freed using `marmot_free_string`.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_process_commit_diff", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_process_commit_diff(MarmotClient* client, byte* group_id, int group_id_length, byte* commit_data, int commit_length, byte** added_json, byte** removed_json);
+
+        /// <summary>
+        ///  Update keys for forward secrecy.
+        ///
+        ///  # Returns
+        ///  A pointer to the commit data, or null on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_update_keys", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_update_keys(MarmotClient* client, byte* group_id, int group_id_length, int* commit_length);
+
+        /// <summary>
+        ///  Rotate this client's keys and encrypt a message under the new epoch.
+        ///
+        ///  # Arguments
+        ///  * `plaintext` - Message to deliver alongside the rotation
+        ///
+        ///  # Returns
+        ///  A pointer to a JSON object `{ "commit": {...}, "message": {...},
+        ///  "message_error": null }`, or null on failure. Members must process the
+        ///  commit before the message. Once the commit is merged it is always returned;
+        ///  if the message then fails, `message` is null and `message_error` says why.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_self_update_with_message", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_self_update_with_message(MarmotClient* client, byte* group_id, int group_id_length, byte* plaintext, int* result_length);
+
+        /// <summary>
+        ///  Set a group's rekey policy, checked with `marmot_should_rekey`.
+        ///
+        ///  # Arguments
+        ///  * `max_messages` - Messages sent since the last key update, 0 for no limit
+        ///  * `max_seconds` - Seconds since the last key update, 0 for no limit
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_set_rekey_policy", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_set_rekey_policy(MarmotClient* client, byte* group_id, int group_id_length, uint max_messages, ulong max_seconds);
+
+        /// <summary>
+        ///  Check whether a group's rekey policy has fired, meaning the host should
+        ///  call `marmot_update_keys`.
+        ///
+        ///  # Returns
+        ///  1 if a rekey is due, 0 if not (or no policy is set), -1 on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_should_rekey", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_should_rekey(MarmotClient* client, byte* group_id, int group_id_length);
+
+        /// <summary>
+        ///  Remove a member from a group.
+        ///
+        ///  # Returns
+        ///  A pointer to the commit data, or null on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_remove_member", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_remove_member(MarmotClient* client, byte* group_id, int group_id_length, byte* member_public_key, int* commit_length);
+
+        /// <summary>
+        ///  Propose removing the client's own member from a group.
+        ///  A member can't commit its own removal, so another member must commit the
+        ///  returned proposal; use `marmot_remove_member` to remove someone else, which
+        ///  commits immediately.
+        ///
+        ///  # Returns
+        ///  A pointer to the proposal event data, or null on failure.
+        ///  The caller must free the buffer using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_propose_self_removal", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_propose_self_removal(MarmotClient* client, byte* group_id, int group_id_length, int* proposal_length);
+
+        /// <summary>
+        ///  Get the ID of the most recent commit event produced by this client
+        ///  (by adding or removing members, updating keys or changing group data).
+        ///
+        ///  # Returns
+        ///  The event ID in hex, or null if the client has not produced a commit yet.
+        ///  The caller must free the returned string using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_last_commit_event_id", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_last_commit_event_id(MarmotClient* client);
+
+        /// <summary>
+        ///  Check whether a group exists. Cheaper than `marmot_get_group_info`.
+        ///
+        ///  # Returns
+        ///  1 if the group exists, 0 if it does not, -1 on failure (e.g. a storage error).
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_group_exists", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_group_exists(MarmotClient* client, byte* group_id, int group_id_length);
+
+        /// <summary>
+        ///  Check whether a group has a commit that was created but not yet merged.
+        ///  MLS forbids creating another commit for the group until it is resolved.
+        ///
+        ///  # Returns
+        ///  1 if a commit is pending, 0 if not, -1 on failure (e.g. the group was not found).
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_has_pending_commit", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_has_pending_commit(MarmotClient* client, byte* group_id, int group_id_length);
+
+        /// <summary>
+        ///  Cache a member's display metadata (from their kind 0 profile) on the client.
+        ///
+        ///  # Arguments
+        ///  * `member_public_key` - The member's public key in hex format
+        ///  * `name` - Display name
+        ///  * `picture_url` - Profile picture URL, or null for none
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_set_member_metadata", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_set_member_metadata(MarmotClient* client, byte* member_public_key, byte* name, byte* picture_url);
+
+        /// <summary>
+        ///  Get a member's cached display metadata.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        ///  On success `metadata_json` receives `{ "name", "picture_url" }`, or null if
+        ///  no metadata was set. Free using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_member_metadata", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_member_metadata(MarmotClient* client, byte* member_public_key, byte** metadata_json);
+
+        /// <summary>
+        ///  Get diagnostic counts for a client. Read-only.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        ///  On success `stats_json` receives `{ "group_count", "total_members",
+        ///  "pending_commits" }`, which the caller must free using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_client_stats", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_client_stats(MarmotClient* client, byte** stats_json);
+
+        /// <summary>
+        ///  Get the current epoch of a group. Cheaper than `marmot_get_group_info`.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero if group not found.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_epoch", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_epoch(MarmotClient* client, byte* group_id, int group_id_length, ulong* epoch);
+
+        /// <summary>
+        ///  Get the MLS ciphersuite of a group, as its IANA value.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero if group not found.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_group_ciphersuite", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_group_ciphersuite(MarmotClient* client, byte* group_id, int group_id_length, ushort* ciphersuite);
+
+        /// <summary>
+        ///  Preview adding a member without creating a commit or changing the group.
+        ///
+        ///  # Arguments
+        ///  * `key_package_event` - JSON-serialized key package event, as for `marmot_add_member`
+        ///  * `preview_json` - Receives `{ "new_member", "epoch", "members" }` describing
+        ///    the group after the add. Free using `marmot_free_string`.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure (including an invalid key package or an
+        ///  existing member).
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_dry_run_add_member", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_dry_run_add_member(MarmotClient* client, byte* group_id, int group_id_length, byte* key_package_event, int key_package_length, byte** preview_json);
+
+        /// <summary>
+        ///  Remove several members from a group in a single commit.
+        ///
+        ///  # Arguments
+        ///  * `member_public_keys_json` - JSON array of hex public keys; if any is invalid
+        ///    or not a member, nothing is removed
+        ///
+        ///  # Returns
+        ///  A pointer to the commit data, or null on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_remove_members", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_remove_members(MarmotClient* client, byte* group_id, int group_id_length, byte* member_public_keys_json, int* commit_length);
+
+        /// <summary>
+        ///  Get who created a group and when.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        ///  On success `info_json` receives `{ "creator", "created_at" }`, as the creator
+        ///  recorded them in the group, so every member sees the same values. Both are
+        ///  null for groups created by clients that do not record them.
+        ///  Free using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_group_creation_info", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_group_creation_info(MarmotClient* client, byte* group_id, int group_id_length, byte** info_json);
+
+        /// <summary>
+        ///  Get information about a group. See `marmot_get_group_creation_info` for
+        ///  its creator and creation time.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero if group not found.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_group_info", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_group_info(MarmotClient* client, byte* group_id, int group_id_length, byte** group_name, ulong* epoch, byte** members_json);
+
+        /// <summary>
+        ///  Get one page of a group's members, for groups too large to list at once.
+        ///  Members are sorted by public key, so pages are consistent between calls.
+        ///
+        ///  # Arguments
+        ///  * `offset` - Number of members to skip
+        ///  * `limit` - Maximum number of members to return, at least one
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        ///  On success `members_json` receives a JSON array of hex public keys, which
+        ///  the caller must free using `marmot_free_string`, and `total` receives the
+        ///  group's member count.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_members_page", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_members_page(MarmotClient* client, byte* group_id, int group_id_length, uint offset, uint limit, byte** members_json, uint* total);
+
+        /// <summary>
+        ///  Get a group's members as they were at a given epoch.
+        ///  Only epochs this client has observed are available; MDK does not retain
+        ///  historical group states, so other epochs fail rather than reporting the
+        ///  current roster.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        ///  On success `members_json` receives a JSON array of hex public keys, which
+        ///  the caller must free using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_members_at_epoch", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_members_at_epoch(MarmotClient* client, byte* group_id, int group_id_length, ulong epoch, byte** members_json);
+
+        /// <summary>
+        ///  Get the epoch transitions this client has applied to a group, for auditing.
+        ///  The history starts when this client created or joined the group.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        ///  On success `history_json` receives a JSON array of `{ "epoch", "timestamp",
+        ///  "kind", "actor" }` objects, oldest first, where `kind` is add, remove,
+        ///  add_remove or update and `actor` is the committer, or null if unknown.
+        ///  The caller must free it using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_epoch_history", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_epoch_history(MarmotClient* client, byte* group_id, int group_id_length, byte** history_json);
+
+        /// <summary>
+        ///  Get how many commits, including self-updates, this client has applied to
+        ///  a group since creating or joining it. The count is kept in the state
+        ///  exported by `marmot_export_group_state` and `marmot_export_all_groups`.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_rotation_count", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_rotation_count(MarmotClient* client, byte* group_id, int group_id_length, ulong* count);
+
+        /// <summary>
+        ///  Get the relays configured for a group.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        ///  On success `relays_json` receives a JSON array of relay URLs, which the
+        ///  caller must free using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_group_relays", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_group_relays(MarmotClient* client, byte* group_id, int group_id_length, byte** relays_json);
+
+        /// <summary>
+        ///  Get the admins of a group, who may moderate it.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        ///  On success `admins_json` receives a JSON array of hex public keys, which
+        ///  the caller must free using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_group_admins", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_group_admins(MarmotClient* client, byte* group_id, int group_id_length, byte** admins_json);
+
+        /// <summary>
+        ///  Replace the relays configured for a group.
+        ///
+        ///  # Arguments
+        ///  * `relays_json` - JSON array of relay URLs, must not be empty
+        ///
+        ///  # Returns
+        ///  A pointer to the commit data, or null on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_set_group_relays", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_set_group_relays(MarmotClient* client, byte* group_id, int group_id_length, byte* relays_json, int* commit_length);
+
+        /// <summary>
+        ///  Rename a group. Only the name in the group data is changed.
+        ///
+        ///  # Arguments
+        ///  * `new_name` - The new name, must not be empty
+        ///
+        ///  # Returns
+        ///  A pointer to the commit data, or null on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_rename_group", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_rename_group(MarmotClient* client, byte* group_id, int group_id_length, byte* new_name, int* commit_length);
+
+        /// <summary>
+        ///  Set a group's description. Only the description in the group data is changed.
+        ///
+        ///  # Arguments
+        ///  * `description` - The new description; empty to clear it
+        ///
+        ///  # Returns
+        ///  A pointer to the commit data, or null on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_set_group_description", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_set_group_description(MarmotClient* client, byte* group_id, int group_id_length, byte* description, int* commit_length);
+
+        /// <summary>
+        ///  Set a group's image. The image is encrypted with a fresh key and nonce,
+        ///  which are stored in the group data via a commit.
+        ///
+        ///  # Arguments
+        ///  * `image` - The plaintext image bytes
+        ///  * `commit_json` - Receives the commit event JSON to publish; free using `marmot_free_string`
+        ///
+        ///  # Returns
+        ///  A pointer to the encrypted image blob for the host to upload, or null on failure.
+        ///  The caller must free the buffer using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_set_group_image", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_set_group_image(MarmotClient* client, byte* group_id, int group_id_length, byte* image, int image_length, int* encrypted_length, byte** commit_json);
+
+        /// <summary>
+        ///  Get the parameters needed to decrypt a group's image.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        ///  On success `image_key_json` receives `{ "hash", "key", "nonce" }` as hex
+        ///  strings, or null if the group has no image. Free using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_group_image_key", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_group_image_key(MarmotClient* client, byte* group_id, int group_id_length, byte** image_key_json);
+
+        /// <summary>
+        ///  Decrypt a downloaded group image blob. Needs no client.
+        ///
+        ///  # Arguments
+        ///  * `image_key_json` - The JSON from `marmot_get_group_image_key`
+        ///
+        ///  # Returns
+        ///  A pointer to the plaintext image, or null on failure (including a blob
+        ///  that does not match the stored hash).
+        ///  The caller must free the buffer using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_decrypt_group_image", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_decrypt_group_image(byte* encrypted, int encrypted_length, byte* image_key_json, int* image_length);
+
+        /// <summary>
+        ///  Get the MLS epoch authenticator for a group, for out-of-band verification
+        ///  that two members share the same epoch.
+        ///
+        ///  # Returns
+        ///  A pointer to the authenticator bytes, or null on failure.
+        ///  The caller must free the buffer using `marmot_free_buffer_zeroize`, as it holds key material.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_epoch_authenticator", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_epoch_authenticator(MarmotClient* client, byte* group_id, int group_id_length, int* authenticator_length);
+
+        /// <summary>
+        ///  Export a group's ratchet tree for debugging and interop testing.
+        ///  The output is the standard MLS (RFC 9420) TLS encoding of the ratchet tree.
+        ///  This does not modify any group state.
+        ///
+        ///  # Returns
+        ///  A pointer to the ratchet tree data, or null on failure.
+        ///  The caller must free the buffer using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_export_ratchet_tree", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_export_ratchet_tree(MarmotClient* client, byte* group_id, int group_id_length, int* tree_length);
+
+        /// <summary>
+        ///  Export a group's MLS GroupInfo, with its ratchet tree, as a TLS-encoded
+        ///  MLSMessage (RFC 9420). Unlike `marmot_export_group_state`, which is a JSON
+        ///  snapshot only this library understands, this is the standard format used
+        ///  for external commits and interop. It holds no secrets.
+        ///
+        ///  # Returns
+        ///  A pointer to the GroupInfo message, or null on failure.
+        ///  The caller must free the buffer using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_export_group_info", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_export_group_info(MarmotClient* client, byte* group_id, int group_id_length, int* group_info_length);
+
+        /// <summary>
+        ///  Set a custom extension in a group's MLS GroupContext, for app data such as a
+        ///  topic or pinned message ID.
+        ///
+        ///  Only the private-use types 0xFF00-0xFF07 may be set; any other type fails
+        ///  with an invalid state error. The change is a GroupContextExtensions commit,
+        ///  which fails if a member's client does not support the type.
+        ///
+        ///  # Returns
+        ///  A pointer to the JSON-serialized commit event to publish, or null on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_set_group_extension", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_set_group_extension(MarmotClient* client, byte* group_id, int group_id_length, ushort extension_type, byte* data, int data_length, int* commit_length);
+
+        /// <summary>
+        ///  Read a custom extension from a group's MLS GroupContext.
+        ///
+        ///  # Returns
+        ///  1 if the extension is present, 0 if it is not, -1 on failure.
+        ///  If present, `out_data` receives the extension data and `out_length` its
+        ///  length; the caller must free the data using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_get_group_extension", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_get_group_extension(MarmotClient* client, byte* group_id, int group_id_length, ushort extension_type, byte** out_data, int* out_length);
+
+        /// <summary>
+        ///  Export a group's complete state, including its secrets, for
+        ///  `marmot_import_group_state`. The exporting client must not send in the
+        ///  group afterwards.
+        ///
+        ///  # Returns
+        ///  A pointer to the state data, or null on failure.
+        ///  The caller must free the buffer using `marmot_free_buffer_zeroize`, as it holds key material.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_export_group_state", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_export_group_state(MarmotClient* client, byte* group_id, int group_id_length, int* state_length);
+
+        /// <summary>
+        ///  Import group state produced by `marmot_export_group_state` on a client of
+        ///  the same identity.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure (including when the group already exists
+        ///  or the state belongs to another identity).
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_import_group_state", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_import_group_state(MarmotClient* client, byte* group_id, int group_id_length, byte* state, int state_length);
+
+        /// <summary>
+        ///  Drop the local state of a group whose epoch has diverged beyond catching
+        ///  up, so it can be re-joined from a fresh welcome. Other groups are unaffected.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_reset_group", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_reset_group(MarmotClient* client, byte* group_id, int group_id_length);
+
+        /// <summary>
+        ///  Export every active group's complete state, including its secrets, as a
+        ///  single versioned backup blob.
+        ///
+        ///  # Returns
+        ///  A pointer to the backup data, or null on failure.
+        ///  The caller must free the buffer using `marmot_free_buffer_zeroize`, as it holds key material.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_export_all_groups", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_export_all_groups(MarmotClient* client, int* backup_length);
+
+        /// <summary>
+        ///  Restore the groups in a backup from `marmot_export_all_groups` made by a
+        ///  client of the same identity. Groups that already exist are skipped.
+        ///
+        ///  # Returns
+        ///  The number of groups restored, or -1 on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_import_all_groups", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_import_all_groups(MarmotClient* client, byte* backup, int backup_length);
+
+        /// <summary>
+        ///  Export the client's Nostr identity and groups as a backup encrypted with
+        ///  a passphrase, for moving them to another device.
+        ///
+        ///  # Returns
+        ///  A pointer to the backup data, or null on failure.
+        ///  The caller must free the buffer using `marmot_free_buffer_zeroize`, as it holds key material.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_export_identity_backup", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_export_identity_backup(MarmotClient* client, byte* passphrase, int* backup_length);
+
+        /// <summary>
+        ///  Create a client from a backup made with `marmot_export_identity_backup`.
+        ///
+        ///  # Returns
+        ///  A pointer to the client, or null on failure. A wrong passphrase fails with
+        ///  the crypto error code.
+        ///  The caller must free the client using `marmot_destroy_client`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_import_identity_backup", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern MarmotClient* marmot_import_identity_backup(byte* backup, int backup_length, byte* passphrase);
+
+        /// <summary>
+        ///  Sign an app-level Nostr event (e.g. profile metadata or a reaction outside
+        ///  any group) with the client's key.
+        ///
+        ///  # Arguments
+        ///  * `unsigned_event_json` - JSON unsigned event whose pubkey is the client's
+        ///
+        ///  # Returns
+        ///  A pointer to the signed event JSON, or null on failure.
+        ///  The caller must free the string using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_sign_event", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_sign_event(MarmotClient* client, byte* unsigned_event_json);
+
+        /// <summary>
+        ///  Verify a Nostr event's id and signature. Needs no client.
+        ///
+        ///  # Returns
+        ///  1 if the event is valid, 0 if its id or signature is invalid, -1 if it
+        ///  cannot be parsed.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_verify_event", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_verify_event(byte* event_json);
+
+        /// <summary>
+        ///  List the MLS ciphersuites this build supports, for choosing a value to pass
+        ///  to `marmot_create_client_with_ciphersuite`. Needs no client.
+        ///
+        ///  # Arguments
+        ///  * `out_json` - Receives a JSON array of `{ "code", "name" }` objects, where
+        ///    `code` is the IANA ciphersuite value. Free using `marmot_free_string`.
+        ///
+        ///  # Returns
+        ///  0 on success, non-zero on failure.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_supported_ciphersuites", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_supported_ciphersuites(byte** out_json);
+
+        /// <summary>
+        ///  Self-test the library before going live: runs a full local round trip
+        ///  (group creation, add member, welcome, encrypt and decrypt) between two
+        ///  throwaway clients with ephemeral keys. Needs no client.
+        ///
+        ///  # Returns
+        ///  0 if every step succeeded, non-zero on failure, with the failing step
+        ///  described by `marmot_get_last_error`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_health_check", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern int marmot_health_check();
+
+        /// <summary>
+        ///  Encode a group ID as lowercase hex, the representation used in the
+        ///  library's JSON outputs. Needs no client.
+        ///
+        ///  # Returns
+        ///  A hex string, or null on failure.
+        ///  The caller must free the string using `marmot_free_string`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_group_id_to_hex", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_group_id_to_hex(byte* group_id, int group_id_length);
+
+        /// <summary>
+        ///  Decode a hex group ID, as produced by `marmot_group_id_to_hex`, into bytes.
+        ///  Needs no client.
+        ///
+        ///  # Returns
+        ///  A pointer to the group ID, or null on failure.
+        ///  The caller must free the buffer using `marmot_free_buffer`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_group_id_from_hex", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern byte* marmot_group_id_from_hex(byte* group_id_hex, int* group_id_length);
+
+        /// <summary>
+        ///  Free a buffer allocated by this library.
+        ///
+        ///  # Arguments
+        ///  * `length` - The length reported when the buffer was returned
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_free_buffer", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern void marmot_free_buffer(byte* buffer, int length);
+
+        /// <summary>
+        ///  Overwrite a buffer allocated by this library with zeros, then free it.
+        ///  Use this instead of `marmot_free_buffer` for outputs that may hold key
+        ///  material: key packages, exported group state and backups, and the epoch
+        ///  authenticator.
+        ///
+        ///  # Arguments
+        ///  * `length` - The length reported when the buffer was returned
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_free_buffer_zeroize", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern void marmot_free_buffer_zeroize(byte* buffer, int length);
+
+        /// <summary>
+        ///  Free a buffer returned by value by this library.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_free_owned_buffer", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern void marmot_free_owned_buffer(MarmotBuffer buffer);
+
+        /// <summary>
+        ///  Free a string allocated by this library.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "marmot_free_string", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        internal static extern void marmot_free_string(byte* s);
+
+
+    }
+
+    /// <summary>
+    ///  The main Marmot client that wraps MDK for FFI access.
+    ///  Opaque to managed code, which only ever holds a pointer to it.
+    /// </summary>
     [StructLayout(LayoutKind.Sequential)]
     internal unsafe partial struct MarmotClient
     {
     }
 
+    /// <summary>
+    ///  A buffer returned by value, so the pointer and its length arrive together.
+    ///  Free it with `marmot_free_owned_buffer`.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    internal unsafe partial struct MarmotBuffer
+    {
+        /// <summary>
+        ///  The buffer data, or null on failure
+        /// </summary>
+        public byte* data;
+        /// <summary>
+        ///  The number of bytes at `data`
+        /// </summary>
+        public int length;
+    }
+
 
 
 }
@@ -0,0 +1,28 @@
+using System.Runtime.InteropServices;
+
+namespace Scramble.Core.Marmot;
+
+/// <summary>
+/// Owns a native Marmot client pointer and destroys it when released.
+/// Interop calls take the handle rather than the raw pointer, so the client is
+/// not destroyed while a call is still using it.
+/// </summary>
+internal sealed class MarmotClientHandle : SafeHandle
+{
+    public MarmotClientHandle() : base(IntPtr.Zero, ownsHandle: true)
+    {
+    }
+
+    public MarmotClientHandle(IntPtr client) : base(IntPtr.Zero, ownsHandle: true)
+    {
+        SetHandle(client);
+    }
+
+    public override bool IsInvalid => handle == IntPtr.Zero;
+
+    protected override bool ReleaseHandle()
+    {
+        MarmotInterop.DestroyClient(handle);
+        return true;
+    }
+}
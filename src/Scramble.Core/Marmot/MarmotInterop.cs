@@ -17,7 +17,7 @@ internal static partial class MarmotInterop
 #endif
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_create_client")]
-    internal static partial IntPtr CreateClient(
+    internal static partial MarmotClientHandle CreateClient(
         [MarshalAs(UnmanagedType.LPUTF8Str)] string privateKeyHex,
         [MarshalAs(UnmanagedType.LPUTF8Str)] string publicKeyHex,
         [MarshalAs(UnmanagedType.LPUTF8Str)] string? dbPath);
@@ -25,21 +25,25 @@ internal static partial class MarmotInterop
     [LibraryImport(LibraryName, EntryPoint = "marmot_destroy_client")]
     internal static partial void DestroyClient(IntPtr client);
 
+    [LibraryImport(LibraryName, EntryPoint = "marmot_client_is_valid")]
+    internal static partial int ClientIsValid(IntPtr client);
+
     [LibraryImport(LibraryName, EntryPoint = "marmot_generate_key_package")]
     internal static partial IntPtr GenerateKeyPackage(
-        IntPtr client,
+        MarmotClientHandle client,
         out int dataLength);
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_create_group")]
     internal static partial IntPtr CreateGroup(
-        IntPtr client,
+        MarmotClientHandle client,
         [MarshalAs(UnmanagedType.LPUTF8Str)] string groupName,
         out int groupIdLength,
-        out ulong epoch);
+        out ulong epoch,
+        out IntPtr configJson);
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_add_member")]
     internal static partial IntPtr AddMember(
-        IntPtr client,
+        MarmotClientHandle client,
         IntPtr groupId,
         int groupIdLength,
         IntPtr keyPackageData,
@@ -48,7 +52,7 @@ internal static partial class MarmotInterop
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_process_welcome")]
     internal static partial IntPtr ProcessWelcome(
-        IntPtr client,
+        MarmotClientHandle client,
         IntPtr welcomeData,
         int welcomeLength,
         out int groupIdLength,
@@ -58,7 +62,7 @@ internal static partial class MarmotInterop
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_encrypt_message")]
     internal static partial IntPtr EncryptMessage(
-        IntPtr client,
+        MarmotClientHandle client,
         IntPtr groupId,
         int groupIdLength,
         [MarshalAs(UnmanagedType.LPUTF8Str)] string plaintext,
@@ -66,17 +70,18 @@ internal static partial class MarmotInterop
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_decrypt_message")]
     internal static partial IntPtr DecryptMessage(
-        IntPtr client,
+        MarmotClientHandle client,
         IntPtr groupId,
         int groupIdLength,
         IntPtr ciphertext,
         int ciphertextLength,
         out IntPtr senderPublicKey,
-        out ulong epoch);
+        out ulong epoch,
+        out ulong timestamp);
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_process_commit")]
     internal static partial int ProcessCommit(
-        IntPtr client,
+        MarmotClientHandle client,
         IntPtr groupId,
         int groupIdLength,
         IntPtr commitData,
@@ -84,14 +89,14 @@ internal static partial class MarmotInterop
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_update_keys")]
     internal static partial IntPtr UpdateKeys(
-        IntPtr client,
+        MarmotClientHandle client,
         IntPtr groupId,
         int groupIdLength,
         out int commitLength);
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_remove_member")]
     internal static partial IntPtr RemoveMember(
-        IntPtr client,
+        MarmotClientHandle client,
         IntPtr groupId,
         int groupIdLength,
         [MarshalAs(UnmanagedType.LPUTF8Str)] string memberPublicKey,
@@ -99,7 +104,7 @@ internal static partial class MarmotInterop
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_get_group_info")]
     internal static partial int GetGroupInfo(
-        IntPtr client,
+        MarmotClientHandle client,
         IntPtr groupId,
         int groupIdLength,
         out IntPtr groupName,
@@ -108,21 +113,24 @@ internal static partial class MarmotInterop
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_export_group_state")]
     internal static partial IntPtr ExportGroupState(
-        IntPtr client,
+        MarmotClientHandle client,
         IntPtr groupId,
         int groupIdLength,
         out int stateLength);
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_import_group_state")]
     internal static partial int ImportGroupState(
-        IntPtr client,
+        MarmotClientHandle client,
         IntPtr groupId,
         int groupIdLength,
         IntPtr state,
         int stateLength);
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_free_buffer")]
-    internal static partial void FreeBuffer(IntPtr buffer);
+    internal static partial void FreeBuffer(IntPtr buffer, int length);
+
+    [LibraryImport(LibraryName, EntryPoint = "marmot_free_buffer_zeroize")]
+    internal static partial void FreeBufferZeroize(IntPtr buffer, int length);
 
     [LibraryImport(LibraryName, EntryPoint = "marmot_free_string")]
     internal static partial void FreeString(IntPtr str);
@@ -13,7 +13,7 @@ namespace Scramble.Core.Marmot;
 public class MarmotWrapper : IDisposable
 {
     private readonly ILogger<MarmotWrapper> _logger;
-    private IntPtr _client;
+    private MarmotClientHandle _client = new();
     private bool _disposed;
     private bool _initialized;
 
@@ -22,7 +22,7 @@ public class MarmotWrapper : IDisposable
     /// <summary>
     /// Whether the native MLS client is loaded and ready.
     /// </summary>
-    public bool IsUsingNativeClient => _client != IntPtr.Zero;
+    public bool IsUsingNativeClient => !_client.IsInvalid && !_client.IsClosed;
 
     /// <summary>
     /// Maximum buffer size accepted from native code (100 MB).
@@ -60,7 +60,7 @@ public class MarmotWrapper : IDisposable
 
             _logger.LogDebug("Attempting to load native Marmot library");
             _client = MarmotInterop.CreateClient(privateKeyHex, publicKeyHex, dbPath);
-            if (_client == IntPtr.Zero)
+            if (_client.IsInvalid)
             {
                 var error = GetLastError() ?? "Failed to create Marmot client";
                 _logger.LogError("Failed to create native Marmot client: {Error}", error);
@@ -163,7 +163,7 @@ public class MarmotWrapper : IDisposable
             }
             finally
             {
-                MarmotInterop.FreeBuffer(ptr);
+                MarmotInterop.FreeBufferZeroize(ptr, length);
             }
         });
     }
@@ -174,11 +174,12 @@ public class MarmotWrapper : IDisposable
 
         return await Task.Run(() =>
         {
-            var ptr = MarmotInterop.CreateGroup(_client, groupName, out int groupIdLength, out ulong epoch);
+            var ptr = MarmotInterop.CreateGroup(_client, groupName, out int groupIdLength, out ulong epoch, out IntPtr configJson);
             if (ptr == IntPtr.Zero)
             {
                 throw new MarmotException(GetLastError() ?? "Failed to create group");
             }
+            MarmotInterop.FreeString(configJson);
 
             try
             {
@@ -189,7 +190,7 @@ public class MarmotWrapper : IDisposable
             }
             finally
             {
-                MarmotInterop.FreeBuffer(ptr);
+                MarmotInterop.FreeBuffer(ptr, groupIdLength);
             }
         });
     }
@@ -269,7 +270,7 @@ public class MarmotWrapper : IDisposable
                 }
                 finally
                 {
-                    MarmotInterop.FreeBuffer(ptr);
+                    MarmotInterop.FreeBuffer(ptr, responseLength);
                 }
             }
             finally
@@ -363,7 +364,7 @@ public class MarmotWrapper : IDisposable
                 }
                 finally
                 {
-                    MarmotInterop.FreeBuffer(ptr);
+                    MarmotInterop.FreeBuffer(ptr, groupIdLength);
                     MarmotInterop.FreeString(groupNamePtr);
                     MarmotInterop.FreeString(membersJsonPtr);
                 }
@@ -406,7 +407,7 @@ public class MarmotWrapper : IDisposable
                 }
                 finally
                 {
-                    MarmotInterop.FreeBuffer(ptr);
+                    MarmotInterop.FreeBuffer(ptr, ciphertextLength);
                 }
             }
             finally
@@ -434,7 +435,8 @@ public class MarmotWrapper : IDisposable
                     ciphertextHandle.AddrOfPinnedObject(),
                     ciphertext.Length,
                     out IntPtr senderPublicKeyPtr,
-                    out ulong epoch);
+                    out ulong epoch,
+                    out _);
 
                 if (ptr == IntPtr.Zero)
                 {
@@ -523,7 +525,7 @@ public class MarmotWrapper : IDisposable
                 }
                 finally
                 {
-                    MarmotInterop.FreeBuffer(ptr);
+                    MarmotInterop.FreeBuffer(ptr, commitLength);
                 }
             }
             finally
@@ -564,7 +566,7 @@ public class MarmotWrapper : IDisposable
                 }
                 finally
                 {
-                    MarmotInterop.FreeBuffer(ptr);
+                    MarmotInterop.FreeBuffer(ptr, commitLength);
                 }
             }
             finally
@@ -648,7 +650,7 @@ public class MarmotWrapper : IDisposable
                 }
                 finally
                 {
-                    MarmotInterop.FreeBuffer(ptr);
+                    MarmotInterop.FreeBufferZeroize(ptr, stateLength);
                 }
             }
             finally
@@ -720,17 +722,14 @@ public class MarmotWrapper : IDisposable
     {
         if (_disposed) return;
 
-        if (_client != IntPtr.Zero)
+        try
         {
-            try
-            {
-                MarmotInterop.DestroyClient(_client);
-            }
-            catch
-            {
-                // Ignore errors during cleanup
-            }
-            _client = IntPtr.Zero;
+            // Destroys the native client once no in-flight call still holds it
+            _client.Dispose();
+        }
+        catch
+        {
+            // Ignore errors during cleanup
         }
 
         _disposed = true;
@@ -16,7 +16,6 @@ fn main() {
     csbindgen::Builder::default()
         .input_extern_file("src/lib.rs")
         .input_extern_file("src/client.rs")
-        .input_extern_file("src/group.rs")
         .input_extern_file("src/error.rs")
         .csharp_dll_name("scramble_native")
         .csharp_namespace("Scramble.Core.Marmot.Generated")
@@ -27,6 +26,5 @@ fn main() {
 
     println!("cargo:rerun-if-changed=src/lib.rs");
     println!("cargo:rerun-if-changed=src/client.rs");
-    println!("cargo:rerun-if-changed=src/group.rs");
     println!("cargo:rerun-if-changed=src/error.rs");
 }
@@ -2,43 +2,604 @@
 //! Uses in-memory storage (ephemeral). Persistent storage requires mdk-sqlite-storage
 //! which needs OpenSSL/SQLCipher — not yet available on the Windows build toolchain.
 
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ffi::{c_char, c_int, CString};
 use std::sync::Arc;
 
 use mdk_core::{MDK, MdkConfig};
 use mdk_memory_storage::MdkMemoryStorage;
+use mdk_storage_traits::groups::GroupStorage;
 use nostr::{Event, EventId, Keys, PublicKey, RelayUrl, UnsignedEvent};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 
-use crate::error::MarmotError;
+use crate::error::{MarmotError, MdkErrorKind};
+use crate::group_state;
+
+/// MDK's storage backend. Tests use a wrapper that can be made to fail.
+#[cfg(not(test))]
+pub(crate) type Storage = MdkMemoryStorage;
+#[cfg(test)]
+pub(crate) type Storage = crate::test_storage::FaultyStorage;
+
+/// What kind of event `process_commit` applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitKind {
+    Commit,
+    Proposal,
+    ExternalJoin,
+    Unprocessable,
+    /// The commit was already applied; redelivered commits change nothing
+    AlreadyApplied,
+    Other,
+}
+
+/// The effect of a processed commit on the group.
+#[derive(Debug, serde::Serialize)]
+pub struct CommitEffect {
+    pub kind: CommitKind,
+    /// The group's epoch after processing
+    pub epoch: u64,
+    /// Net change in member count
+    pub member_delta: i32,
+    /// Public keys (hex) of members added by the commit
+    pub added: Vec<String>,
+    /// Public keys (hex) of members removed by the commit
+    pub removed: Vec<String>,
+}
+
+/// The outcome of `process_events`.
+#[derive(Debug, serde::Serialize)]
+pub struct EventsReport {
+    /// Number of application messages dispatched to the message callback
+    pub dispatched: usize,
+    /// The events that could not be processed, in batch order
+    pub errors: Vec<EventError>,
+}
+
+/// An event `process_events` could not process.
+#[derive(Debug, serde::Serialize)]
+pub struct EventError {
+    /// Position of the event in the batch
+    pub index: usize,
+    /// ID (hex) of the event, if it has one
+    pub event_id: Option<String>,
+    /// `MarmotError` code
+    pub code: i32,
+    pub message: String,
+}
+
+/// The outcome of `reprocess_commits`.
+#[derive(Debug, serde::Serialize)]
+pub struct ReprocessReport {
+    /// Number of commits that advanced the group state
+    pub applied: usize,
+    /// IDs (hex) of the events that could not be applied
+    pub failed: Vec<String>,
+}
+
+/// An incoming relay event, classified by `process_message`.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProcessedEvent {
+    /// An application message
+    Message {
+        group_id: String,
+        sender: String,
+        content: String,
+        epoch: u64,
+        /// Rumor kind of the message
+        kind: u16,
+    },
+    /// A commit, already merged into the group
+    Commit {
+        group_id: String,
+        /// The group's epoch after the commit
+        epoch: u64,
+    },
+    /// A proposal, held until a commit includes it
+    Proposal,
+    /// A welcome or gift wrap; these go through `process_welcome_event`
+    WelcomeUnsupported,
+    /// A welcome rumor replayed from the test transport, already accepted
+    Joined {
+        group_id: String,
+        /// The group's epoch on joining
+        epoch: u64,
+    },
+}
+
+/// The outcome of decrypting one event of a `decrypt_messages` batch.
+#[derive(Debug, Default, serde::Serialize)]
+pub struct DecryptResult {
+    pub ok: bool,
+    pub sender: Option<String>,
+    pub content: Option<String>,
+    pub epoch: Option<u64>,
+    /// Why the event could not be decrypted; None on success
+    pub error: Option<String>,
+}
+
+/// The effect `add_member` would have, as computed by `dry_run_add_member`.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+pub struct AddMemberPreview {
+    /// Public key (hex) of the member who would be added
+    pub new_member: String,
+    /// The group's epoch after the add
+    pub epoch: u64,
+    /// Public keys (hex) of the members after the add, sorted
+    pub members: Vec<String>,
+}
+
+/// A processed but not yet accepted welcome.
+#[derive(Debug, serde::Serialize)]
+pub struct WelcomePreview {
+    /// Handle for `accept_welcome` / `decline_welcome` (the welcome rumor's event ID)
+    pub welcome_id: String,
+    pub group_id: String,
+    pub group_name: String,
+    /// Public key (hex) of the member who sent the invite
+    pub inviter: String,
+    /// Public keys (hex) of the group's members
+    pub members: Vec<String>,
+}
+
+/// A welcome that has been processed but neither accepted nor declined.
+#[derive(Debug, serde::Serialize)]
+pub struct WelcomeSummary {
+    /// Handle for `accept_welcome` / `decline_welcome` (the welcome rumor's event ID)
+    pub welcome_id: String,
+    pub group_id: String,
+    pub group_name: String,
+    /// Public key (hex) of the member who sent the invite
+    pub inviter: String,
+}
+
+/// What caused an epoch transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EpochChangeKind {
+    /// Members were added
+    Add,
+    /// Members were removed
+    Remove,
+    /// Members were added and others removed in the same commit
+    AddRemove,
+    /// The roster is unchanged: a key update or a group data change
+    Update,
+}
+
+/// An entry in a group's epoch history.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EpochRecord {
+    /// The epoch the group entered
+    pub epoch: u64,
+    /// When this client applied the commit, in seconds since the Unix epoch
+    pub timestamp: u64,
+    pub kind: EpochChangeKind,
+    /// Public key (hex) of the committer, if it could be determined
+    pub actor: Option<String>,
+}
+
+/// Counts describing a client's groups, for diagnostics.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ClientStats {
+    pub group_count: usize,
+    /// Sum of the member counts of all groups
+    pub total_members: usize,
+    /// Groups with a commit that has been created but not merged
+    pub pending_commits: usize,
+}
+
+/// Display metadata for a member, from their kind 0 profile, cached by the host.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct MemberMetadata {
+    pub name: String,
+    pub picture_url: Option<String>,
+}
+
+/// A group's rekey policy and the usage counted since its last self-update.
+#[derive(Debug)]
+struct RekeyState {
+    /// Messages after which to rekey, or 0 for no limit
+    max_messages: u32,
+    /// Seconds after which to rekey, or 0 for no limit
+    max_seconds: u64,
+    messages_sent: u32,
+    last_update: std::time::Instant,
+}
+
+/// Who created a group and when, as recorded in the group's
+/// `CREATION_EXTENSION_TYPE` extension. Both are None for groups created by
+/// clients that do not record them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct GroupCreationInfo {
+    /// Hex public key of the creator
+    pub creator: Option<String>,
+    /// Creation time in seconds since the Unix epoch
+    pub created_at: Option<u64>,
+}
+
+/// A group's configuration as stored in its Nostr group data.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+pub struct GroupConfig {
+    pub name: String,
+    pub description: String,
+    pub relays: Vec<String>,
+    /// Hex public keys of the group's admins
+    pub admins: Vec<String>,
+}
+
+/// Shape of the members JSON returned across the FFI boundary, so hosts can
+/// migrate to role-aware members on their own schedule.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MembersJsonVersion {
+    /// A flat JSON array of hex public keys
+    #[default]
+    V1,
+    /// `{ "v": 2, "members": [{ "pubkey": "<hex>", "admin": bool }, ...] }`
+    V2,
+}
+
+/// A member in the version 2 members JSON.
+#[derive(Debug, serde::Serialize)]
+struct MemberEntry<'a> {
+    pubkey: &'a str,
+    admin: bool,
+}
+
+/// The version 2 members JSON envelope.
+#[derive(Debug, serde::Serialize)]
+struct MembersEnvelope<'a> {
+    v: u32,
+    members: Vec<MemberEntry<'a>>,
+}
+
+/// Storage backend selected in a `ClientConfig`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageKind {
+    #[default]
+    Memory,
+    Sqlite,
+}
+
+/// Options for `MarmotClient::new_with_config`, parsed from JSON.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct ClientConfig {
+    #[serde(default)]
+    pub storage: StorageKind,
+    /// Database file, required for SQLite storage
+    pub db_path: Option<String>,
+    /// Relays listed in key packages and new groups, replacing the defaults
+    pub relays: Option<Vec<String>>,
+    /// IANA ciphersuite value, as accepted by `new_with_ciphersuite`
+    pub ciphersuite: Option<u16>,
+    /// Members JSON version, as accepted by `set_members_json_version`
+    pub members_json_version: Option<u32>,
+}
+
+impl ClientConfig {
+    const KEYS: [&'static str; 5] = ["storage", "db_path", "relays", "ciphersuite", "members_json_version"];
+
+    /// Parse a config object. Unknown keys are ignored with a warning, so hosts
+    /// can pass options newer than this build understands.
+    pub fn from_json(json: &str) -> Result<Self, MarmotError> {
+        let value: serde_json::Map<String, serde_json::Value> = serde_json::from_str(json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid client config: {}", e)))?;
+        for key in value.keys().filter(|key| !Self::KEYS.contains(&key.as_str())) {
+            tracing::warn!("Ignoring unknown client config key '{}'", key);
+        }
+
+        serde_json::from_value(serde_json::Value::Object(value))
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid client config: {}", e)))
+    }
+}
+
+/// Default cap on the relays listed in a key package; some relays reject events
+/// with many relay tags.
+const DEFAULT_MAX_KEY_PACKAGE_RELAYS: usize = 5;
+
+/// Default cap on message content, below common relay event size limits.
+const DEFAULT_MAX_MESSAGE_BYTES: usize = 65536;
+
+/// Default number of retries of a failed storage operation.
+const DEFAULT_STORAGE_RETRY_COUNT: u32 = 3;
+
+/// Delay before the first storage retry, doubled for each further retry.
+const STORAGE_RETRY_BACKOFF: std::time::Duration = std::time::Duration::from_millis(10);
+
+/// Past epochs whose secrets MDK is configured to keep for late messages.
+const MDK_RETAINED_PAST_EPOCHS: usize = 5;
+
+/// Number of recent epochs per group whose commit event IDs are remembered.
+/// Older redeliveries are rejected by MLS rather than reported as already applied.
+const APPLIED_COMMIT_EPOCHS: usize = 32;
+
+/// Number of messages `decrypt_message_text` holds for `decrypt_message_raw`;
+/// beyond it the oldest is dropped.
+const HELD_MESSAGES: usize = 32;
+
+/// Leading byte of the `export_all` backup format. Version 1 held group records only
+const BACKUP_FORMAT_VERSION: u8 = 2;
+
+/// Leading byte of the `export_identity_backup` format, followed by the
+/// Argon2 salt, the ChaCha20-Poly1305 nonce and the ciphertext
+const IDENTITY_BACKUP_FORMAT_VERSION: u8 = 1;
+const IDENTITY_BACKUP_SALT_LEN: usize = 16;
+const IDENTITY_BACKUP_NONCE_LEN: usize = 12;
+
+/// Maximum age NIP-59 timestamps are shifted into the past, to hide when a
+/// gift wrap was made.
+const GIFT_WRAP_MAX_TIMESTAMP_TWEAK: u64 = 2 * 24 * 60 * 60;
+
+/// Extension type of Marmot's Nostr group data, which MDK manages itself.
+const NOSTR_GROUP_DATA_EXTENSION_TYPE: u16 = 0xF2EE;
+
+/// Private-use GroupContext extension types apps may set with `set_group_extension`.
+/// Every member's leaf must list a type before a group can carry it, so this
+/// client's leaves list the whole range.
+const APP_EXTENSION_TYPES: std::ops::RangeInclusive<u16> = 0xFF00..=0xFF07;
+
+/// Private-use GroupContext extension type holding a group's `GroupCreationInfo`
+/// as JSON, committed by its creator so every later member can read it.
+const CREATION_EXTENSION_TYPE: u16 = 0xFF08;
+
+/// Rumor tag marking content sealed by `encrypt_message_sealed`, followed by
+/// the epoch whose key sealed it.
+const SEALED_TAG: [&str; 2] = ["sealed", "nip44"];
+
+/// Rumor tag marking base64 binary content from `encrypt_binary`.
+const BINARY_TAG: [&str; 2] = ["encoding", "base64"];
+
+/// An MLS ciphersuite compiled into this build.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+pub struct CiphersuiteInfo {
+    /// IANA ciphersuite value, as accepted by `new_with_ciphersuite`
+    pub code: u16,
+    /// IANA name, e.g. MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519
+    pub name: String,
+}
+
+/// A key package generated by this client whose private keys are still stored.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct StoredKeyPackage {
+    /// Hex MLS key package reference (hash)
+    pub key_package_ref: String,
+    /// Creation time in seconds since the Unix epoch
+    pub created_at: u64,
+}
+
+/// Details of a key package checked by `validate_key_package`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct KeyPackageInfo {
+    /// Hex Nostr public key from the key package's credential
+    pub public_key: String,
+    /// IANA value of the key package's MLS ciphersuite
+    pub ciphersuite: u16,
+    /// Start of the validity window, in seconds since the Unix epoch
+    pub not_before: u64,
+    /// End of the validity window, in seconds since the Unix epoch
+    pub not_after: u64,
+}
+
+/// Decryption parameters of a group's image, as stored in the group data.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct GroupImageKey {
+    /// Hex SHA-256 of the encrypted image blob
+    pub hash: String,
+    /// Hex ChaCha20-Poly1305 key
+    pub key: String,
+    /// Hex ChaCha20-Poly1305 nonce
+    pub nonce: String,
+}
+
+/// An incoming message processed by MDK.
+struct DecryptedMessage {
+    group_id: Vec<u8>,
+    sender: String,
+    content: String,
+    epoch: u64,
+    tags: Vec<nostr::Tag>,
+    /// Rumor kind of application messages; None for commits and proposals
+    kind: Option<nostr::Kind>,
+    /// The rumor's `created_at` (seconds); 0 for commits and proposals
+    created_at: u64,
+    /// The decrypted rumor of application messages
+    rumor: Option<UnsignedEvent>,
+}
+
+/// Host callback invoked for each application message handled by `process_events`:
+/// (group_id, group_id_length, sender_hex, content, kind, epoch).
+/// The strings are only valid for the duration of the call.
+pub type MessageCallback = extern "C" fn(*const u8, c_int, *const c_char, *const c_char, u16, u64);
+
+/// Test transport receiving each event JSON the client would publish.
+/// The string is only valid for the duration of the call.
+#[cfg(any(test, feature = "test-transport"))]
+pub type NostrProxyCallback = extern "C" fn(*const c_char);
 
 /// The main Marmot client that wraps MDK for FFI access.
+/// Opaque to managed code, which only ever holds a pointer to it.
 pub struct MarmotClient {
     /// Nostr keys for this client
     keys: Keys,
     /// The MDK instance with in-memory storage
-    mdk: Arc<RwLock<MDK<MdkMemoryStorage>>>,
+    mdk: Arc<RwLock<MDK<Storage>>>,
     /// Default relays for group operations
     default_relays: Vec<RelayUrl>,
+    /// ID of the most recent commit event produced by this client
+    last_commit_event_id: RwLock<Option<EventId>>,
+    /// IDs of commit events applied to each group, keyed by the epoch they entered,
+    /// so redeliveries are no-ops. Only the last `APPLIED_COMMIT_EPOCHS` are kept
+    applied_commits: Mutex<HashMap<Vec<u8>, BTreeMap<u64, EventId>>>,
+    /// Groups replaced by `reinit_group`, mapped to the ID of their replacement.
+    /// Not persisted: a client built from exported state can send to them again
+    reinitialized_groups: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
+    /// Code and message of the error from this client's last FFI call, if it failed
+    last_error: Mutex<Option<(i32, String)>>,
+    /// Host callback for application messages handled by `process_events`
+    message_callback: RwLock<Option<MessageCallback>>,
+    /// Rosters observed by this client, keyed by group ID then epoch.
+    /// MDK only keeps the current group state, so this is the only source of past rosters.
+    roster_history: RwLock<HashMap<Vec<u8>, BTreeMap<u64, Vec<String>>>>,
+    /// Epoch transitions this client has applied, keyed by group ID.
+    /// Exported with the group's state, so it survives a backup and restore
+    epoch_history: RwLock<HashMap<Vec<u8>, Vec<EpochRecord>>>,
+    /// Each member's public key and leaf encryption key at the last recorded
+    /// roster, keyed by group ID then leaf index, to identify received commits' committers
+    leaf_keys: RwLock<HashMap<Vec<u8>, HashMap<u32, (PublicKey, Vec<u8>)>>>,
+    /// Rumor kind of outgoing messages when the caller does not choose one
+    default_message_kind: RwLock<u16>,
+    /// Messages decrypted by `decrypt_message_text` whose content could not be
+    /// returned as a C string: event ID, group ID, sender, content and epoch,
+    /// oldest first. MDK consumed them, so each is held for one
+    /// `decrypt_message_raw` call with the same event. Only the last `HELD_MESSAGES` are kept
+    held_messages: Mutex<VecDeque<(EventId, Vec<u8>, String, String, u64)>>,
+    /// Host-supplied display metadata, keyed by hex public key
+    member_metadata: RwLock<HashMap<String, MemberMetadata>>,
+    /// Key packages generated by this client and not deleted through it, keyed by
+    /// hex reference, with their creation time and base64 content
+    stored_key_packages: RwLock<BTreeMap<String, (u64, String)>>,
+    /// Rekey policies, keyed by group ID
+    rekey_policies: Mutex<HashMap<Vec<u8>, RekeyState>>,
+    /// Read-only client: may decrypt and inspect groups but not change them
+    observer: bool,
+    /// Maximum number of relays listed in generated key packages
+    max_key_package_relays: RwLock<usize>,
+    /// Maximum size of outgoing message content, in bytes
+    max_message_bytes: RwLock<usize>,
+    /// How many times a storage failure is retried before it is reported
+    storage_retry_count: RwLock<u32>,
+    /// Shape of the members JSON returned across the FFI boundary
+    members_json_version: RwLock<MembersJsonVersion>,
+    /// Test transport capturing published events
+    #[cfg(any(test, feature = "test-transport"))]
+    nostr_proxy: RwLock<Option<NostrProxyCallback>>,
 }
 
 impl MarmotClient {
     /// Create a new Marmot client with the given Nostr identity.
+    /// The public key must match the private key, or be empty to derive it.
     /// The `_db_path` parameter is accepted for API compatibility but currently unused
     /// (memory storage only until SQLCipher build is resolved).
-    pub fn new(private_key_hex: &str, _public_key_hex: &str, _db_path: Option<&str>) -> Result<Self, MarmotError> {
+    pub fn new(private_key_hex: &str, public_key_hex: &str, db_path: Option<&str>) -> Result<Self, MarmotError> {
+        Self::build(private_key_hex, public_key_hex, db_path, None)
+    }
+
+    /// Create a new Marmot client whose groups and key packages use the given
+    /// MLS ciphersuite, by its IANA value (e.g. 0x0001).
+    pub fn new_with_ciphersuite(private_key_hex: &str, public_key_hex: &str, ciphersuite: u16) -> Result<Self, MarmotError> {
+        Self::build(private_key_hex, public_key_hex, None, Some(ciphersuite))
+    }
+
+    /// Create a new Marmot client from a `ClientConfig`.
+    /// SQLite storage is not available in this build and fails with `Unsupported`.
+    pub fn new_with_config(private_key_hex: &str, public_key_hex: &str, config: &ClientConfig) -> Result<Self, MarmotError> {
+        if config.storage == StorageKind::Sqlite {
+            if config.db_path.as_deref().unwrap_or_default().is_empty() {
+                return Err(MarmotError::InvalidState("SQLite storage requires a db_path".into()));
+            }
+            return Err(MarmotError::Unsupported("SQLite storage: this build has memory storage only".into()));
+        }
+
+        let relays = match &config.relays {
+            Some(urls) if urls.is_empty() => {
+                return Err(MarmotError::InvalidState("Relay list must not be empty".into()));
+            }
+            Some(urls) => Some(
+                urls.iter()
+                    .map(|url| {
+                        RelayUrl::parse(url)
+                            .map_err(|e| MarmotError::InvalidState(format!("Invalid relay URL '{}': {}", url, e)))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            None => None,
+        };
+
+        let mut client = Self::build(private_key_hex, public_key_hex, config.db_path.as_deref(), config.ciphersuite)?;
+        if let Some(relays) = relays {
+            client.default_relays = relays;
+        }
+        if let Some(version) = config.members_json_version {
+            client.set_members_json_version(version)?;
+        }
+        Ok(client)
+    }
+
+    /// Create a read-only client, e.g. for auditing. It can join groups, decrypt
+    /// and read them, but any operation that creates or changes a group fails.
+    pub fn new_observer(private_key_hex: &str, public_key_hex: &str) -> Result<Self, MarmotError> {
+        let mut client = Self::build(private_key_hex, public_key_hex, None, None)?;
+        client.observer = true;
+        Ok(client)
+    }
+
+    /// Create a new Marmot client from a raw 32-byte secret key, so the key never
+    /// has to cross the FFI boundary as a string. The public key is derived from it.
+    pub fn new_from_secret_key(secret_key: &[u8]) -> Result<Self, MarmotError> {
+        let secret_key = nostr::SecretKey::from_slice(secret_key)
+            .map_err(|e| MarmotError::InvalidKey(format!("Invalid private key: {}", e)))?;
+        Self::build_with_keys(Keys::new(secret_key), None, None)
+    }
+
+    fn build(
+        private_key_hex: &str,
+        public_key_hex: &str,
+        db_path: Option<&str>,
+        ciphersuite: Option<u16>,
+    ) -> Result<Self, MarmotError> {
         // Parse the private key to get Keys
         let secret_key = nostr::SecretKey::from_hex(private_key_hex)
             .map_err(|e| MarmotError::InvalidKey(format!("Invalid private key: {}", e)))?;
         let keys = Keys::new(secret_key);
 
-        let config = MdkConfig::default();
+        // An empty public key is derived from the private key
+        if !public_key_hex.is_empty() {
+            let public_key = PublicKey::from_hex(public_key_hex)
+                .map_err(|e| MarmotError::InvalidKey(format!("Invalid public key: {}", e)))?;
+            if public_key != keys.public_key() {
+                return Err(MarmotError::InvalidKey("public key does not match private key".into()));
+            }
+        }
+
+        Self::build_with_keys(keys, db_path, ciphersuite)
+    }
+
+    fn build_with_keys(keys: Keys, _db_path: Option<&str>, ciphersuite: Option<u16>) -> Result<Self, MarmotError> {
+        use openmls::prelude::{Ciphersuite, OpenMlsCrypto, OpenMlsProvider};
+
+        let config = MdkConfig {
+            max_past_epochs: MDK_RETAINED_PAST_EPOCHS,
+            ..MdkConfig::default()
+        };
 
         tracing::info!("Creating MarmotClient with in-memory storage");
-        let storage = MdkMemoryStorage::new();
-        let mdk = MDK::builder(storage)
+        let storage = Storage::new();
+        let mut mdk = MDK::builder(storage)
             .with_config(config)
             .build();
 
+        mdk.extensions.extend(APP_EXTENSION_TYPES.map(openmls::prelude::ExtensionType::Unknown));
+        mdk.extensions.push(openmls::prelude::ExtensionType::Unknown(CREATION_EXTENSION_TYPE));
+
+        if let Some(value) = ciphersuite {
+            let supported = mdk.provider.crypto().supported_ciphersuites();
+            let selected = Ciphersuite::try_from(value).ok().filter(|cs| supported.contains(cs));
+            match selected {
+                Some(cs) => mdk.ciphersuite = cs,
+                None => {
+                    let supported: Vec<String> = supported.iter().map(|cs| format!("0x{:04x}", *cs as u16)).collect();
+                    return Err(MarmotError::UnsupportedCiphersuite(format!(
+                        "0x{:04x}; supported: {}",
+                        value,
+                        supported.join(", ")
+                    )));
+                }
+            }
+        }
+
         // Default relays
         let default_relays = vec![
             RelayUrl::parse("wss://relay.damus.io").unwrap(),
@@ -49,294 +610,1777 @@ impl MarmotClient {
             keys,
             mdk: Arc::new(RwLock::new(mdk)),
             default_relays,
+            last_commit_event_id: RwLock::new(None),
+            applied_commits: Mutex::new(HashMap::new()),
+            reinitialized_groups: RwLock::new(HashMap::new()),
+            last_error: Mutex::new(None),
+            message_callback: RwLock::new(None),
+            roster_history: RwLock::new(HashMap::new()),
+            epoch_history: RwLock::new(HashMap::new()),
+            leaf_keys: RwLock::new(HashMap::new()),
+            // Kind 9 for chat messages
+            default_message_kind: RwLock::new(9),
+            held_messages: Mutex::new(VecDeque::new()),
+            member_metadata: RwLock::new(HashMap::new()),
+            stored_key_packages: RwLock::new(BTreeMap::new()),
+            rekey_policies: Mutex::new(HashMap::new()),
+            observer: false,
+            max_key_package_relays: RwLock::new(DEFAULT_MAX_KEY_PACKAGE_RELAYS),
+            max_message_bytes: RwLock::new(DEFAULT_MAX_MESSAGE_BYTES),
+            storage_retry_count: RwLock::new(DEFAULT_STORAGE_RETRY_COUNT),
+            members_json_version: RwLock::new(MembersJsonVersion::default()),
+            #[cfg(any(test, feature = "test-transport"))]
+            nostr_proxy: RwLock::new(None),
         })
     }
 
+    /// Drop this client's Nostr keys now, before it is freed; nostr's `SecretKey`
+    /// erases its bytes when dropped.
+    /// The client must not be used afterwards: it holds a random placeholder key.
+    /// MLS secrets in MDK's storage are freed with the client but not zeroized.
+    pub fn wipe_secrets(&mut self) {
+        drop(std::mem::replace(&mut self.keys, Keys::generate()));
+    }
+
+    /// Fail if this is an observer client.
+    fn ensure_writable(&self) -> Result<(), MarmotError> {
+        if self.observer {
+            return Err(MarmotError::InvalidState("observer client is read-only".into()));
+        }
+        Ok(())
+    }
+
+    /// Record the error of a failed FFI call on this client.
+    pub fn set_last_error(&self, code: i32, message: String) {
+        *self.last_error.lock() = Some((code, message));
+    }
+
+    /// Clear the recorded error at the start of an FFI call.
+    pub fn clear_last_error(&self) {
+        *self.last_error.lock() = None;
+    }
+
+    /// Get the message of the error from this client's last FFI call, if it failed.
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().as_ref().map(|(_, message)| message.clone())
+    }
+
+    /// Get the code of the error from this client's last FFI call, or 0 if it succeeded.
+    pub fn last_error_code(&self) -> i32 {
+        self.last_error.lock().as_ref().map(|(code, _)| *code).unwrap_or(0)
+    }
+
+    /// Set the maximum number of relays listed in generated key packages (default 5).
+    /// Only the first `max` default relays are used.
+    pub fn set_max_key_package_relays(&self, max: usize) -> Result<(), MarmotError> {
+        if max == 0 {
+            return Err(MarmotError::InvalidState("Key packages must list at least one relay".into()));
+        }
+        *self.max_key_package_relays.write() = max;
+        Ok(())
+    }
+
+    /// The relays to list in a key package: the default relays, truncated to the cap.
+    fn key_package_relays(&self) -> Vec<RelayUrl> {
+        let max = *self.max_key_package_relays.read();
+        if self.default_relays.len() > max {
+            tracing::warn!(
+                "Key package lists only {} of {} relays",
+                max,
+                self.default_relays.len()
+            );
+        }
+        self.default_relays.iter().take(max).cloned().collect()
+    }
+
     /// Generate a new KeyPackage for group invitations.
     /// Returns JSON with { "content": "<base64>", "tags": [[...], ...] }
-    pub fn generate_key_package(&self) -> Result<Vec<u8>, MarmotError> {
+    pub fn generate_key_package(&self) -> Result<zeroize::Zeroizing<Vec<u8>>, MarmotError> {
         let public_key = self.keys.public_key();
-        let relays = self.default_relays.clone();
+        let relays = self.key_package_relays();
 
-        let mdk = self.mdk.read();
+        // Creating a key package stores its private keys in MDK's storage, so it
+        // must not run alongside other readers
+        let mdk = self.mdk.write();
         let kp_data = mdk.create_key_package_for_event(&public_key, relays)
-            .map_err(|e| MarmotError::Internal(format!("Failed to create key package: {}", e)))?;
+            .map_err(|e| MarmotError::from_mdk("Failed to create key package", e))?;
+        self.track_key_package(&mdk, &kp_data.content)?;
 
-        // Use kind 30443 tags (addressable events, current MIP-00 spec)
-        let tags: Vec<Vec<String>> = kp_data.tags_30443
-            .into_iter()
-            .map(|tag: nostr::Tag| tag.to_vec())
-            .collect();
+        key_package_result(kp_data.content, kp_data.tags_30443)
+    }
 
-        // Return both content and tags as JSON
-        #[derive(serde::Serialize)]
-        struct KeyPackageResult {
-            content: String,
-            tags: Vec<Vec<String>>,
+    /// Generate a new KeyPackage valid for `valid_days` days from now.
+    /// MDK always applies the OpenMLS default lifetime, so the KeyPackage is built
+    /// directly with OpenMLS using MDK's ciphersuite, extensions and storage.
+    /// Returns JSON with { "content": "<base64>", "tags": [[...], ...] }
+    pub fn generate_key_package_with_lifetime(&self, valid_days: u32) -> Result<zeroize::Zeroizing<Vec<u8>>, MarmotError> {
+        use base64::Engine;
+        use openmls::prelude::tls_codec::Serialize;
+        use openmls::prelude::{
+            BasicCredential, Capabilities, CredentialWithKey, KeyPackage, Lifetime, OpenMlsProvider,
+        };
+        use openmls_basic_credential::SignatureKeyPair;
+
+        if valid_days == 0 {
+            return Err(MarmotError::InvalidState("Key package lifetime must be at least one day".into()));
         }
 
-        let result = KeyPackageResult {
-            content: kp_data.content,
-            tags,
+        let public_key = self.keys.public_key();
+        let relays = self.key_package_relays();
+
+        // The signer and the KeyPackage's private keys are written to storage
+        let mdk = self.mdk.write();
+
+        let signer = SignatureKeyPair::new(mdk.ciphersuite.signature_algorithm())
+            .map_err(|e| MarmotError::CryptoError(format!("Failed to generate signature key: {}", e)))?;
+        signer
+            .store(mdk.provider.storage())
+            .map_err(|e| MarmotError::storage("Failed to store signature key", e))?;
+
+        let credential = CredentialWithKey {
+            credential: BasicCredential::new(public_key.to_bytes().to_vec()).into(),
+            signature_key: signer.public().into(),
+        };
+        let capabilities = Capabilities::new(None, Some(&[mdk.ciphersuite]), Some(&mdk.extensions), None, None);
+
+        let bundle = KeyPackage::builder()
+            .leaf_node_capabilities(capabilities)
+            .key_package_lifetime(Lifetime::new(u64::from(valid_days) * 24 * 60 * 60))
+            .build(mdk.ciphersuite, &mdk.provider, &signer, credential)
+            .map_err(|e| MarmotError::MlsError(format!("Failed to build key package: {}", e)))?;
+
+        let serialized = bundle
+            .key_package()
+            .tls_serialize_detached()
+            .map(zeroize::Zeroizing::new)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize key package: {}", e)))?;
+        let content = base64::engine::general_purpose::STANDARD.encode(serialized.as_slice());
+        self.track_key_package(&mdk, &content)?;
+
+        let tags = key_package_tags(&mdk, bundle.key_package(), relays)?;
+        key_package_result(content, tags)
+    }
+
+    /// Remember a key package generated by this client, so it can be listed and deleted.
+    fn track_key_package(&self, mdk: &MDK<Storage>, content_b64: &str) -> Result<(), MarmotError> {
+        use openmls::prelude::OpenMlsProvider;
+
+        let key_package = decode_key_package(mdk, content_b64)
+            .ok_or_else(|| MarmotError::Internal("Generated an invalid key package".into()))?;
+        let hash_ref = key_package
+            .hash_ref(mdk.provider.crypto())
+            .map_err(|e| MarmotError::MlsError(format!("Failed to hash key package: {}", e)))?;
+
+        self.stored_key_packages.write().insert(
+            hex::encode(hash_ref.as_slice()),
+            (nostr::Timestamp::now().as_u64(), content_b64.to_string()),
+        );
+        Ok(())
+    }
+
+    /// List the key packages generated by this client whose private keys are
+    /// still stored, oldest first. MDK's storage cannot be enumerated, so the key
+    /// packages this client generated are looked up in it; those MDK consumed
+    /// when joining a group are left out.
+    pub fn stored_key_packages(&self) -> Vec<StoredKeyPackage> {
+        use openmls::prelude::{KeyPackageBundle, KeyPackageRef, OpenMlsProvider};
+        use openmls_traits::storage::StorageProvider;
+
+        let mdk = self.mdk.read();
+        let in_storage = |key_package_ref: &str| {
+            let Ok(bytes) = hex::decode(key_package_ref) else { return false };
+            mdk.provider
+                .storage()
+                .key_package::<KeyPackageRef, KeyPackageBundle>(&KeyPackageRef::from_slice(&bytes))
+                .map(|bundle| bundle.is_some())
+                .unwrap_or(false)
         };
 
-        serde_json::to_vec(&result)
-            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize: {}", e)))
+        let mut stored: Vec<StoredKeyPackage> = self
+            .stored_key_packages
+            .read()
+            .iter()
+            .filter(|(key_package_ref, _)| in_storage(key_package_ref))
+            .map(|(key_package_ref, (created_at, _))| StoredKeyPackage {
+                key_package_ref: key_package_ref.clone(),
+                created_at: *created_at,
+            })
+            .collect();
+        stored.sort_by_key(|kp| kp.created_at);
+        stored
+    }
+
+    /// Delete a stored key package's private keys, e.g. once it has been used.
+    /// key_package_ref: hex reference, as listed by `stored_key_packages`
+    pub fn delete_key_package(&self, key_package_ref: &str) -> Result<(), MarmotError> {
+        let content = self
+            .stored_key_packages
+            .read()
+            .get(key_package_ref)
+            .map(|(_, content)| content.clone())
+            .ok_or_else(|| MarmotError::InvalidState(format!("Unknown key package {}", key_package_ref)))?;
+
+        // Deleting twice is harmless, so a failed deletion can be retried
+        self.with_storage_retry(|mdk| {
+            let key_package = decode_key_package(mdk, &content)
+                .ok_or_else(|| MarmotError::Internal("Stored an invalid key package".into()))?;
+            mdk.delete_key_package_from_storage(&key_package)
+                .map_err(|e| MarmotError::from_mdk("Failed to delete key package", e))
+        })?;
+
+        self.stored_key_packages.write().remove(key_package_ref);
+        Ok(())
     }
 
     /// Create a new MLS group.
     /// Returns (group_id, epoch).
     pub fn create_group(&self, name: &str) -> Result<(Vec<u8>, u64), MarmotError> {
-        let public_key = self.keys.public_key();
+        let (group_id, epoch, _) = self.create_group_from_config(self.group_config(name), vec![])?;
+        Ok((group_id, epoch))
+    }
+
+    /// Create a new MLS group with additional admins.
+    /// admins_json: JSON array of hex public keys; see `validate_admins`.
+    /// Returns (group_id, epoch).
+    pub fn create_group_with_admins(&self, name: &str, admins_json: &str) -> Result<(Vec<u8>, u64), MarmotError> {
+        let admins: Vec<String> = serde_json::from_str(admins_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid admins JSON: {}", e)))?;
+
+        let mut config = self.group_config(name);
+        config.admins = self.validate_admins(&admins)?;
+
+        let (group_id, epoch, _) = self.create_group_from_config(config, vec![])?;
+        Ok((group_id, epoch))
+    }
+
+    /// Create a new MLS group with its founding members in a single commit.
+    /// key_package_events_json: JSON array of Nostr events containing the members' key packages
+    /// Returns (group_id, epoch, welcome_rumors), one welcome per founding member.
+    pub fn create_group_with_members(
+        &self,
+        name: &str,
+        key_package_events_json: &[u8],
+    ) -> Result<(Vec<u8>, u64, Vec<serde_json::Value>), MarmotError> {
+        let events: Vec<Event> = serde_json::from_slice(key_package_events_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid key package events JSON: {}", e)))?;
+
+        self.create_group_from_config(self.group_config(name), events)
+    }
+
+    /// Re-initialize a group after a suspected compromise: create a fresh group with the
+    /// same name, description, relays and admins, containing the current members.
+    /// The roster is checked and the new group created under one lock, as for `derive_subgroup`.
+    /// key_package_events_json: JSON array of fresh key package events, one per other member
+    /// Returns (new_group_id, welcome_rumors). The old group can no longer be used to send
+    /// from this client; that is recorded in memory only, not in MDK's storage.
+    pub fn reinit_group(
+        &self,
+        group_id: &[u8],
+        key_package_events_json: &[u8],
+    ) -> Result<(Vec<u8>, Vec<serde_json::Value>), MarmotError> {
+        self.ensure_writable()?;
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        let events: Vec<Event> = serde_json::from_slice(key_package_events_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid key package events JSON: {}", e)))?;
+
+        let mdk = self.mdk.write();
+        let group = mdk.get_group(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get group", e))?
+            .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
+        let relays = mdk.get_relays(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get relays", e))?;
 
-        // Create group config
+        // Every current member needs a fresh key package to be carried over
+        self.require_member_key_packages(&mdk, &mls_group_id, &events)?;
+
+        // The group image is not carried over: its key belongs to the compromised group
         let config = mdk_core::groups::NostrGroupConfigData {
-            name: name.to_string(),
-            description: String::new(),
+            name: group.name.clone(),
+            description: group.description.clone(),
             image_hash: None,
             image_key: None,
             image_nonce: None,
-            relays: self.default_relays.clone(),
-            admins: vec![public_key.clone()],
+            relays: relays.into_iter().collect(),
+            admins: group.admin_pubkeys.iter().cloned().collect(),
+        };
+        let (new_group_id, _, welcomes) = self.create_group_in(&mdk, config, events)?;
+
+        self.reinitialized_groups
+            .write()
+            .insert(group_id.to_vec(), new_group_id.clone());
+
+        Ok((new_group_id, welcomes))
+    }
+
+    /// Create a sub-conversation of a group: a new, independent group owned by this
+    /// client with the parent's current members and relays. MLS cannot reuse the
+    /// parent's leaves, so every other member is re-invited from a fresh key package.
+    /// The roster is checked and the subgroup created under one lock, so a member
+    /// removed from the parent meanwhile cannot be carried over.
+    /// key_package_events_json: JSON array of key package events, one per other member
+    /// Returns (subgroup_id, welcome_rumors).
+    pub fn derive_subgroup(
+        &self,
+        parent_group_id: &[u8],
+        name: &str,
+        key_package_events_json: &[u8],
+    ) -> Result<(Vec<u8>, Vec<serde_json::Value>), MarmotError> {
+        self.ensure_writable()?;
+        let mls_group_id = mdk_core::GroupId::from_slice(parent_group_id);
+
+        let events: Vec<Event> = serde_json::from_slice(key_package_events_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid key package events JSON: {}", e)))?;
+
+        let mdk = self.mdk.write();
+        mdk.get_group(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get group", e))?
+            .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(parent_group_id)))?;
+        let relays = mdk.get_relays(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get relays", e))?;
+
+        self.require_member_key_packages(&mdk, &mls_group_id, &events)?;
+
+        let config = mdk_core::groups::NostrGroupConfigData {
+            relays: relays.into_iter().collect(),
+            ..self.group_config(name)
         };
+        let (subgroup_id, _, welcomes) = self.create_group_in(&mdk, config, events)?;
+        Ok((subgroup_id, welcomes))
+    }
+
+    /// Fail unless `events` holds a key package for every member of the group other
+    /// than this client, and none from anyone else.
+    fn require_member_key_packages(
+        &self,
+        mdk: &MDK<Storage>,
+        mls_group_id: &mdk_core::GroupId,
+        events: &[Event],
+    ) -> Result<(), MarmotError> {
+        let members = mdk.get_members(mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get members", e))?;
+
+        let own_key = self.keys.public_key();
+        if let Some(outsider) = events
+            .iter()
+            .find(|e| e.pubkey == own_key || !members.contains(&e.pubkey))
+        {
+            return Err(MarmotError::InvalidState(format!(
+                "Key package from non-member {}",
+                outsider.pubkey.to_hex()
+            )));
+        }
+        match members
+            .iter()
+            .find(|pk| **pk != own_key && !events.iter().any(|e| e.pubkey == **pk))
+        {
+            Some(missing) => Err(MarmotError::InvalidState(format!(
+                "Missing key package for member {}",
+                missing.to_hex()
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Create a group owned by this client from a config and the founding members' key packages.
+    /// Returns (group_id, epoch, welcome_rumors).
+    fn create_group_from_config(
+        &self,
+        config: mdk_core::groups::NostrGroupConfigData,
+        key_package_events: Vec<Event>,
+    ) -> Result<(Vec<u8>, u64, Vec<serde_json::Value>), MarmotError> {
+        self.ensure_writable()?;
 
         let mdk = self.mdk.write();
+        self.create_group_in(&mdk, config, key_package_events)
+    }
+
+    /// Create a group, as `create_group_from_config`, under the caller's MDK lock.
+    /// The group is created alone, its `GroupCreationInfo` committed, and the
+    /// founding members added in one more commit. Nobody else is in the group
+    /// for those commits, so they are not published. If a step fails the group
+    /// is deactivated, as by `reset_group`.
+    fn create_group_in(
+        &self,
+        mdk: &MDK<Storage>,
+        config: mdk_core::groups::NostrGroupConfigData,
+        key_package_events: Vec<Event>,
+    ) -> Result<(Vec<u8>, u64, Vec<serde_json::Value>), MarmotError> {
+        let public_key = self.keys.public_key();
+
         let result = mdk.create_group(&public_key, vec![], config)
-            .map_err(|e| MarmotError::Internal(format!("Failed to create group: {}", e)))?;
+            .map_err(|e| MarmotError::from_mdk("Failed to create group", e))?;
+        let mls_group_id = result.group.mls_group_id;
+        let group_id = mls_group_id.as_slice().to_vec();
+        self.record_roster(mdk, &mls_group_id);
 
-        // Get the group ID as bytes
-        let group_id = result.group.mls_group_id.as_slice().to_vec();
-        let epoch = 0u64; // New groups start at epoch 0
+        match self.found_group(mdk, &mls_group_id, &key_package_events) {
+            Ok(welcomes) => {
+                let epoch = epoch_of(mdk, &mls_group_id).unwrap_or(0);
+                Ok((group_id, epoch, welcomes))
+            }
+            Err(e) => {
+                if let Err(cleanup) = deactivate_group(mdk, &group_id) {
+                    tracing::warn!("Failed to deactivate unfinished group: {}", cleanup);
+                }
+                self.forget_group(&group_id);
+                Err(e)
+            }
+        }
+    }
 
-        Ok((group_id, epoch))
+    /// Commit a new group's `GroupCreationInfo`, then add its founding members.
+    /// Returns the members' welcome rumors.
+    fn found_group(
+        &self,
+        mdk: &MDK<Storage>,
+        mls_group_id: &mdk_core::GroupId,
+        key_package_events: &[Event],
+    ) -> Result<Vec<serde_json::Value>, MarmotError> {
+        let creation = GroupCreationInfo {
+            creator: Some(self.keys.public_key().to_hex()),
+            created_at: Some(nostr::Timestamp::now().as_u64()),
+        };
+        let creation = serde_json::to_vec(&creation)?;
+        commit_group_extension(mdk, mls_group_id.as_slice(), CREATION_EXTENSION_TYPE, &creation)?;
+        mdk.merge_pending_commit(mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to merge commit", e))?;
+        self.record_roster(mdk, mls_group_id);
+
+        if key_package_events.is_empty() {
+            return Ok(Vec::new());
+        }
+        let result = mdk.add_members(mls_group_id, key_package_events)
+            .map_err(|e| MarmotError::from_mdk("Failed to add members", e))?;
+        mdk.merge_pending_commit(mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to merge commit", e))?;
+        self.record_roster(mdk, mls_group_id);
+
+        let welcomes = result
+            .welcome_rumors
+            .iter()
+            .flatten()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(welcomes)
+    }
+
+    /// Build the Nostr group config for a new group owned by this client.
+    fn group_config(&self, name: &str) -> mdk_core::groups::NostrGroupConfigData {
+        mdk_core::groups::NostrGroupConfigData {
+            name: name.to_string(),
+            description: String::new(),
+            image_hash: None,
+            image_key: None,
+            image_nonce: None,
+            relays: self.default_relays.clone(),
+            admins: vec![self.keys.public_key()],
+        }
+    }
+
+    /// Validate a caller-supplied admin list for a new group's Nostr group data.
+    /// Duplicates are dropped and this client, as the creator, is always
+    /// included first. An invalid public key fails with `InvalidKey` naming it.
+    fn validate_admins(&self, admins: &[String]) -> Result<Vec<PublicKey>, MarmotError> {
+        let mut validated = vec![self.keys.public_key()];
+        for admin in admins {
+            let pubkey = PublicKey::from_hex(admin)
+                .map_err(|e| MarmotError::InvalidKey(format!("Invalid admin public key '{}': {}", admin, e)))?;
+            if !validated.contains(&pubkey) {
+                validated.push(pubkey);
+            }
+        }
+        Ok(validated)
     }
 
     /// Add a member to a group using their KeyPackage event.
     /// key_package_event_json: JSON-serialized Nostr event containing the key package
     /// Returns JSON object with { "welcome": [...], "commit": {...} }
     pub fn add_member(&self, group_id: &[u8], key_package_event_json: &[u8]) -> Result<Vec<u8>, MarmotError> {
+        self.add_member_with(group_id, key_package_event_json, welcome_json)
+    }
+
+    /// `add_member` with the welcome serialization supplied, so tests can make it fail.
+    /// `serialize_welcome` receives MDK's welcome rumors, if any.
+    /// The commit is only merged once the whole response has been produced; on
+    /// failure the pending commit is cleared and the group is left unchanged.
+    fn add_member_with(
+        &self,
+        group_id: &[u8],
+        key_package_event_json: &[u8],
+        serialize_welcome: impl Fn(Option<&[UnsignedEvent]>) -> Result<serde_json::Value, MarmotError>,
+    ) -> Result<Vec<u8>, MarmotError> {
+        self.ensure_writable()?;
+
         // Parse the group ID
         let mls_group_id = mdk_core::GroupId::from_slice(group_id);
 
         // Parse the key package event from JSON
         let event_json = std::str::from_utf8(key_package_event_json)
-            .map_err(|e| MarmotError::Internal(format!("Invalid UTF-8 in event JSON: {}", e)))?;
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid UTF-8 in event JSON: {}", e)))?;
         let event: Event = serde_json::from_str(event_json)
-            .map_err(|e| MarmotError::Internal(format!("Invalid event JSON: {}", e)))?;
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid event JSON: {}", e)))?;
 
         let mdk = self.mdk.write();
 
+        // MDK's error for a key package of another ciphersuite does not say so
+        if let Some(key_package_ciphersuite) = key_package_ciphersuite(&mdk, &event.content) {
+            let group_ciphersuite = load_mls_group(&mdk, group_id)?.ciphersuite() as u16;
+            if key_package_ciphersuite != group_ciphersuite {
+                return Err(MarmotError::InvalidState(format!(
+                    "ciphersuite mismatch: key package uses 0x{:04x}, group uses 0x{:04x}",
+                    key_package_ciphersuite, group_ciphersuite
+                )));
+            }
+        }
+
         // Add the member
         let result = mdk
             .add_members(&mls_group_id, &[event.clone()])
-            .map_err(|e| MarmotError::Internal(format!("Failed to add member: {}", e)))?;
-
-        // Merge the pending commit
-        mdk.merge_pending_commit(&mls_group_id)
-            .map_err(|e| MarmotError::Internal(format!("Failed to merge commit: {}", e)))?;
+            .map_err(|e| MarmotError::from_mdk("Failed to add member", e))?;
 
         // Build response with both welcome and commit data
         #[derive(serde::Serialize)]
         struct AddMemberResult {
-            welcome: Option<serde_json::Value>,
+            welcome: serde_json::Value,
             commit: Option<serde_json::Value>,
+            commit_event_id: String,
         }
 
-        let response = AddMemberResult {
-            welcome: result.welcome_rumors.map(|r| serde_json::to_value(r).ok()).flatten(),
-            commit: Some(serde_json::to_value(&result.evolution_event).unwrap_or_default()),
+        let response = (|| {
+            let response = AddMemberResult {
+                welcome: serialize_welcome(result.welcome_rumors.as_deref())?,
+                commit: Some(serde_json::to_value(&result.evolution_event)?),
+                commit_event_id: result.evolution_event.id.to_hex(),
+            };
+            serde_json::to_vec(&response)
+                .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize result: {}", e)))
+        })();
+
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                // Without a welcome the invitee could never join, so drop the commit
+                mdk.clear_pending_commit(&mls_group_id)
+                    .map_err(|e| MarmotError::from_mdk("Failed to clear pending commit", e))?;
+                return Err(e);
+            }
         };
 
-        serde_json::to_vec(&response)
-            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize result: {}", e)))
-    }
+        // Merge the pending commit
+        mdk.merge_pending_commit(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to merge commit", e))?;
 
-    /// Process a Welcome message to join a group.
-    /// welcome_event_json: JSON containing wrapper_event_id and rumor_event
-    /// Returns (group_id, group_name, epoch, members_json).
-    pub fn process_welcome(&self, welcome_data: &[u8]) -> Result<(Vec<u8>, String, u64, Vec<String>), MarmotError> {
-        // Parse the welcome data (expecting a JSON object with event_id and rumor)
-        #[derive(serde::Deserialize)]
-        struct WelcomeInput {
-            wrapper_event_id: String,
-            rumor_event: serde_json::Value,
+        self.publish(&result.evolution_event);
+        for rumor in result.welcome_rumors.iter().flatten() {
+            self.publish(rumor);
         }
+        self.record_own_commit(&mdk, &mls_group_id, result.evolution_event.id);
 
-        let welcome_json = std::str::from_utf8(welcome_data)
-            .map_err(|e| MarmotError::Internal(format!("Invalid UTF-8: {}", e)))?;
-        let input: WelcomeInput = serde_json::from_str(welcome_json)
-            .map_err(|e| MarmotError::Internal(format!("Invalid welcome JSON: {}", e)))?;
+        Ok(response)
+    }
 
-        let event_id = EventId::from_hex(&input.wrapper_event_id)
-            .map_err(|e| MarmotError::Internal(format!("Invalid event ID: {}", e)))?;
-        let rumor: UnsignedEvent = serde_json::from_value(input.rumor_event)
-            .map_err(|e| MarmotError::Internal(format!("Invalid rumor event: {}", e)))?;
+    /// Add a member from a bare KeyPackage, as exchanged out-of-band (e.g. QR code invites).
+    /// content_b64: base64 KeyPackage, the `content` produced by `generate_key_package`
+    /// tags_json: JSON array of tags, the `tags` produced by `generate_key_package`
+    /// Returns JSON object with { "welcome": [...], "commit": {...} }
+    pub fn add_member_from_key_package(
+        &self,
+        group_id: &[u8],
+        content_b64: &str,
+        tags_json: &str,
+    ) -> Result<Vec<u8>, MarmotError> {
+        use base64::Engine;
+        use openmls::prelude::tls_codec::Deserialize;
+        use openmls::prelude::{BasicCredential, KeyPackageIn};
 
-        let mdk = self.mdk.write();
+        let tags: Vec<Vec<String>> = serde_json::from_str(tags_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid tags JSON: {}", e)))?;
+        let tags = tags
+            .into_iter()
+            .map(|tag| nostr::Tag::parse(tag).map_err(|e| MarmotError::SerializationError(format!("Invalid tag: {}", e))))
+            .collect::<Result<Vec<_>, _>>()?;
 
-        // Process the welcome
-        let welcome = mdk
-            .process_welcome(&event_id, &rumor)
-            .map_err(|e| MarmotError::Internal(format!("Failed to process welcome: {}", e)))?;
+        // The owner's Nostr key is the identity of the KeyPackage's basic credential
+        let kp_bytes = base64::engine::general_purpose::STANDARD
+            .decode(content_b64)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid key package encoding: {}", e)))?;
+        let key_package = KeyPackageIn::tls_deserialize_exact(&kp_bytes)
+            .map_err(|e| MarmotError::MlsError(format!("Invalid key package: {}", e)))?;
+        let credential = BasicCredential::try_from(key_package.unverified_credential().credential)
+            .map_err(|e| MarmotError::MlsError(format!("Invalid key package credential: {}", e)))?;
+        let owner = PublicKey::from_slice(credential.identity())
+            .map_err(|e| MarmotError::InvalidKey(format!("Invalid credential identity: {}", e)))?;
+
+        let event = unsigned_key_package_event(owner, content_b64, tags)?;
+        let event_json = serde_json::to_vec(&event)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize event: {}", e)))?;
+        self.add_member(group_id, &event_json)
+    }
+
+    /// Preview the effect of `add_member` without creating a commit.
+    /// The key package is validated as by `validate_key_package`; the group is not changed.
+    pub fn dry_run_add_member(&self, group_id: &[u8], key_package_event_json: &[u8]) -> Result<AddMemberPreview, MarmotError> {
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+        let info = self.validate_key_package(group_id, key_package_event_json)?;
+
+        let mdk = self.mdk.read();
+        let epoch = epoch_of(&mdk, &mls_group_id)
+            .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
+        let mut members: Vec<String> = mdk.get_members(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get members", e))?
+            .iter()
+            .map(|pk| pk.to_hex())
+            .collect();
+
+        if members.contains(&info.public_key) {
+            return Err(MarmotError::AlreadyMember);
+        }
+        members.push(info.public_key.clone());
+        members.sort();
+
+        Ok(AddMemberPreview { new_member: info.public_key, epoch: epoch + 1, members })
+    }
+
+    /// Check a key package event is well-formed, uses the ciphersuite of the group
+    /// it would join and is within its validity window, without adding it.
+    pub fn validate_key_package(&self, group_id: &[u8], key_package_event_json: &[u8]) -> Result<KeyPackageInfo, MarmotError> {
+        self.validate_key_package_at(group_id, key_package_event_json, nostr::Timestamp::now().as_u64())
+    }
+
+    /// `validate_key_package` with the validity window checked against `now`.
+    fn validate_key_package_at(
+        &self,
+        group_id: &[u8],
+        key_package_event_json: &[u8],
+        now: u64,
+    ) -> Result<KeyPackageInfo, MarmotError> {
+        use base64::Engine;
+        use openmls::prelude::tls_codec::Deserialize;
+        use openmls::prelude::{BasicCredential, KeyPackageIn, KeyPackageVerifyError, OpenMlsProvider, ProtocolVersion};
+
+        let event: Event = serde_json::from_slice(key_package_event_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid event JSON: {}", e)))?;
+        let kp_bytes = base64::engine::general_purpose::STANDARD
+            .decode(&event.content)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid key package encoding: {}", e)))?;
+        let key_package_in = KeyPackageIn::tls_deserialize_exact(&kp_bytes)
+            .map_err(|e| MarmotError::MlsError(format!("Invalid key package: {}", e)))?;
+
+        let mdk = self.mdk.read();
+        let group_ciphersuite = load_mls_group(&mdk, group_id)?.ciphersuite();
+        let key_package = key_package_in
+            .validate(mdk.provider.crypto(), ProtocolVersion::Mls10)
+            .map_err(|e| match e {
+                KeyPackageVerifyError::InvalidLifetime => MarmotError::KeyPackageExpired("Outside its lifetime".into()),
+                e => MarmotError::MlsError(format!("Invalid key package: {}", e)),
+            })?;
+
+        if key_package.ciphersuite() != group_ciphersuite {
+            return Err(MarmotError::UnsupportedCiphersuite(format!(
+                "{:?}, the group uses {:?}",
+                key_package.ciphersuite(),
+                group_ciphersuite
+            )));
+        }
+
+        let lifetime = key_package.life_time();
+        if now < lifetime.not_before() || now > lifetime.not_after() {
+            return Err(MarmotError::KeyPackageExpired(format!(
+                "Valid from {} to {}",
+                lifetime.not_before(),
+                lifetime.not_after()
+            )));
+        }
+
+        let credential = BasicCredential::try_from(key_package.leaf_node().credential().clone())
+            .map_err(|e| MarmotError::MlsError(format!("Invalid key package credential: {}", e)))?;
+        let owner = PublicKey::from_slice(credential.identity())
+            .map_err(|e| MarmotError::InvalidKey(format!("Invalid credential identity: {}", e)))?;
+
+        Ok(KeyPackageInfo {
+            public_key: owner.to_hex(),
+            ciphersuite: key_package.ciphersuite() as u16,
+            not_before: lifetime.not_before(),
+            not_after: lifetime.not_after(),
+        })
+    }
+
+    /// Process a Welcome message to join a group.
+    /// welcome_event_json: JSON containing wrapper_event_id and rumor_event
+    /// Returns (group_id, group_name, epoch, members_json).
+    pub fn process_welcome(&self, welcome_data: &[u8]) -> Result<(Vec<u8>, String, u64, Vec<String>), MarmotError> {
+        let preview = self.preview_welcome(welcome_data)?;
+        self.accept_welcome(&preview.welcome_id)
+    }
+
+    /// Process a Welcome message without joining the group, so the user can review the invite.
+    /// The welcome stays pending until `accept_welcome` or `decline_welcome` is called.
+    /// welcome_event_json: JSON containing wrapper_event_id and rumor_event
+    pub fn preview_welcome(&self, welcome_data: &[u8]) -> Result<WelcomePreview, MarmotError> {
+        let (event_id, rumor) = parse_welcome_input(welcome_data)?;
+        self.preview_welcome_rumor(&event_id, &rumor)
+    }
+
+    /// NIP-59 gift wrap a welcome rumor produced by this client for its recipient.
+    /// With `randomize_timestamp`, the seal and wrapper timestamps are shifted by a
+    /// random amount up to 48 hours into the past, so they do not reveal when the
+    /// invite was sent.
+    /// welcome_rumor_json: a welcome rumor from `add_member`
+    /// Returns the gift wrap event JSON.
+    pub fn wrap_welcome(
+        &self,
+        welcome_rumor_json: &[u8],
+        recipient_hex: &str,
+        randomize_timestamp: bool,
+    ) -> Result<Vec<u8>, MarmotError> {
+        let rumor: UnsignedEvent = serde_json::from_slice(welcome_rumor_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid rumor event: {}", e)))?;
+        if rumor.kind != nostr::Kind::MlsWelcome {
+            return Err(MarmotError::InvalidState(format!("Expected a welcome rumor, got kind {}", rumor.kind)));
+        }
+        let recipient = PublicKey::from_hex(recipient_hex)
+            .map_err(|e| MarmotError::InvalidKey(format!("Invalid recipient public key: {}", e)))?;
+
+        let wrap = gift_wrap(&self.keys, &recipient, &rumor, randomize_timestamp)?;
+        serde_json::to_vec(&wrap)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize gift wrap: {}", e)))
+    }
+
+    /// Process a Welcome delivered as a signed NIP-59 gift wrap (kind 1059)
+    /// addressed to this client, and join its group.
+    /// gift_wrap_event_json: the gift wrap event as received from a relay
+    /// Returns (group_id, group_name, epoch, members_json).
+    pub fn process_welcome_event(&self, gift_wrap_event_json: &[u8]) -> Result<(Vec<u8>, String, u64, Vec<String>), MarmotError> {
+        let gift_wrap: Event = serde_json::from_slice(gift_wrap_event_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid gift wrap JSON: {}", e)))?;
+        let rumor = unwrap_gift_wrap(&self.keys, &gift_wrap)?;
+
+        let preview = self.preview_welcome_rumor(&gift_wrap.id, &rumor)?;
+        self.accept_welcome(&preview.welcome_id)
+    }
+
+    /// Process a welcome rumor received in the wrapper event `wrapper_event_id`.
+    fn preview_welcome_rumor(&self, event_id: &EventId, rumor: &UnsignedEvent) -> Result<WelcomePreview, MarmotError> {
+        let mdk = self.mdk.write();
+
+        // Process the welcome
+        let welcome = mdk
+            .process_welcome(event_id, rumor)
+            .map_err(|e| MarmotError::from_mdk("Failed to process welcome", e))?;
+
+        // Get members
+        let members = mdk
+            .get_members(&welcome.mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get members", e))?;
+
+        Ok(WelcomePreview {
+            welcome_id: welcome.id.to_hex(),
+            group_id: hex::encode(welcome.mls_group_id.as_slice()),
+            group_name: welcome.group_name.clone(),
+            inviter: welcome.welcomer.to_hex(),
+            members: members.iter().map(|pk| pk.to_hex()).collect(),
+        })
+    }
+
+    /// List welcomes that have been processed but neither accepted nor declined,
+    /// so a restarted host can show its pending invites.
+    pub fn pending_welcomes(&self) -> Result<Vec<WelcomeSummary>, MarmotError> {
+        let mdk = self.mdk.read();
+        let welcomes = mdk.get_pending_welcomes()
+            .map_err(|e| MarmotError::from_mdk("Failed to get pending welcomes", e))?;
+
+        Ok(welcomes
+            .iter()
+            .map(|welcome| WelcomeSummary {
+                welcome_id: welcome.id.to_hex(),
+                group_id: hex::encode(welcome.mls_group_id.as_slice()),
+                group_name: welcome.group_name.clone(),
+                inviter: welcome.welcomer.to_hex(),
+            })
+            .collect())
+    }
+
+    /// Accept a previewed welcome and join its group.
+    /// welcome_id: the `welcome_id` from `preview_welcome`
+    /// Returns (group_id, group_name, epoch, members_json).
+    pub fn accept_welcome(&self, welcome_id: &str) -> Result<(Vec<u8>, String, u64, Vec<String>), MarmotError> {
+        let mdk = self.mdk.write();
+        let welcome = pending_welcome(&mdk, welcome_id)?;
 
         // Accept the welcome
         mdk.accept_welcome(&welcome)
-            .map_err(|e| MarmotError::Internal(format!("Failed to accept welcome: {}", e)))?;
+            .map_err(|e| MarmotError::from_mdk("Failed to accept welcome", e))?;
+        self.record_roster(&mdk, &welcome.mls_group_id);
 
         // Get group info
         let group_id = welcome.mls_group_id.as_slice().to_vec();
         let group_name = welcome.group_name.clone();
-        let epoch = 0u64;
+        let epoch = epoch_of(&mdk, &welcome.mls_group_id).unwrap_or(0);
 
         // Get members
         let members = mdk
             .get_members(&welcome.mls_group_id)
-            .map_err(|e| MarmotError::Internal(format!("Failed to get members: {}", e)))?;
+            .map_err(|e| MarmotError::from_mdk("Failed to get members", e))?;
         let member_pubkeys: Vec<String> = members.iter().map(|pk| pk.to_hex()).collect();
 
         Ok((group_id, group_name, epoch, member_pubkeys))
     }
 
+    /// Decline a previewed welcome, dropping it from the pending welcomes.
+    /// The private keys of the key package it was addressed to are deleted, and
+    /// the MLS welcome is removed from the stored record: MDK keeps declined
+    /// welcomes and has no way to delete them.
+    /// welcome_id: the `welcome_id` from `preview_welcome`
+    pub fn decline_welcome(&self, welcome_id: &str) -> Result<(), MarmotError> {
+        use mdk_storage_traits::welcomes::WelcomeStorage;
+        use openmls::prelude::{KeyPackageBundle, OpenMlsProvider};
+        use openmls_traits::storage::StorageProvider;
+
+        let mdk = self.mdk.write();
+        let welcome = pending_welcome(&mdk, welcome_id)?;
+
+        mdk.decline_welcome(&welcome)
+            .map_err(|e| MarmotError::from_mdk("Failed to decline welcome", e))?;
+
+        let storage = mdk.provider.storage();
+        for key_package_ref in welcome_key_package_refs(&welcome.event)? {
+            let bundle: Option<KeyPackageBundle> = storage
+                .key_package(&key_package_ref)
+                .map_err(|e| MarmotError::storage("Failed to read key package", e))?;
+            if let Some(bundle) = bundle {
+                mdk.delete_key_package_from_storage(bundle.key_package())
+                    .map_err(|e| MarmotError::from_mdk("Failed to delete key package", e))?;
+                self.stored_key_packages.write().remove(&hex::encode(key_package_ref.as_slice()));
+            }
+        }
+
+        let mut declined = pending_welcome(&mdk, welcome_id)?;
+        declined.event.content = String::new();
+        storage.save_welcome(declined)
+            .map_err(|e| MarmotError::storage("Failed to save welcome", e))
+    }
+
     /// Encrypt a message for a group.
     /// Returns JSON-serialized Nostr event.
     pub fn encrypt_message(&self, group_id: &[u8], plaintext: &str) -> Result<Vec<u8>, MarmotError> {
+        self.encrypt_with_tags(group_id, plaintext, vec![], None, None)
+    }
+
+    /// Encrypt a message, as `encrypt_message`, also returning the event's ID as
+    /// hex so the host can show the message and match relay OK responses at once.
+    /// Returns (event_json, event_id_hex).
+    pub fn encrypt_message_with_id(&self, group_id: &[u8], plaintext: &str) -> Result<(Vec<u8>, String), MarmotError> {
+        let event_json = self.encrypt_message(group_id, plaintext)?;
+        let event: Event = serde_json::from_slice(&event_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid event JSON: {}", e)))?;
+        Ok((event_json, event.id.to_hex()))
+    }
+
+    /// Encrypt a message with the given rumor kind instead of the client's default.
+    /// Returns JSON-serialized Nostr event.
+    pub fn encrypt_message_with_kind(&self, group_id: &[u8], plaintext: &str, kind: u16) -> Result<Vec<u8>, MarmotError> {
+        self.encrypt_with_tags(group_id, plaintext, vec![], Some(kind), None)
+    }
+
+    /// Set the rumor kind used by `encrypt_message` and friends (9, a chat message, by default).
+    pub fn set_default_message_kind(&self, kind: u16) {
+        *self.default_message_kind.write() = kind;
+    }
+
+    /// Encrypt a message, first checking the group is at the epoch the host
+    /// expects. A client that has missed a commit would otherwise produce a
+    /// message other members reject.
+    /// Returns JSON-serialized Nostr event.
+    pub fn encrypt_message_at_epoch(
+        &self,
+        group_id: &[u8],
+        plaintext: &str,
+        expected_epoch: Option<u64>,
+    ) -> Result<Vec<u8>, MarmotError> {
+        self.encrypt_with_tags(group_id, plaintext, vec![], None, expected_epoch)
+    }
+
+    /// Encrypt a reply to an earlier message in a group.
+    /// parent_event_id_hex: ID of the message being replied to, as 64-char hex
+    /// Returns JSON-serialized Nostr event.
+    pub fn encrypt_reply(&self, group_id: &[u8], plaintext: &str, parent_event_id_hex: &str) -> Result<Vec<u8>, MarmotError> {
+        if parent_event_id_hex.len() != 64 || !parent_event_id_hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(MarmotError::InvalidState(format!(
+                "Invalid parent event ID: {}",
+                parent_event_id_hex
+            )));
+        }
+
+        // NIP-10 marked `e` tag referencing the parent
+        let tag = nostr::Tag::parse(["e", parent_event_id_hex, "", "root"])
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to build reply tag: {}", e)))?;
+
+        self.encrypt_with_tags(group_id, plaintext, vec![tag], None, None)
+    }
+
+    /// Encrypt a message with the given rumor tags, and kind if not the default.
+    fn encrypt_with_tags(
+        &self,
+        group_id: &[u8],
+        plaintext: &str,
+        tags: Vec<nostr::Tag>,
+        kind: Option<u16>,
+        expected_epoch: Option<u64>,
+    ) -> Result<Vec<u8>, MarmotError> {
         let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+        let rumor = self.message_rumor(group_id, plaintext, tags, kind)?;
+
+        let mdk = self.mdk.write();
+        if let Some(expected) = expected_epoch {
+            let current = epoch_of(&mdk, &mls_group_id)
+                .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
+            if current < expected {
+                return Err(MarmotError::InvalidState(format!(
+                    "epoch behind: group is at {}, expected {}",
+                    current, expected
+                )));
+            }
+            if current > expected {
+                return Err(MarmotError::InvalidState(format!(
+                    "epoch ahead: group is at {}, expected {}",
+                    current, expected
+                )));
+            }
+        }
+
+        let event = self.encrypt_rumor(&mdk, group_id, rumor)?;
+
+        // Serialize to JSON
+        let event_json = serde_json::to_vec(&event)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize event: {}", e)))?;
+
+        Ok(event_json)
+    }
+
+    /// Build the rumor of an outgoing message, checking that the group may be
+    /// sent to and the message is within the size limit.
+    fn message_rumor(
+        &self,
+        group_id: &[u8],
+        plaintext: &str,
+        tags: Vec<nostr::Tag>,
+        kind: Option<u16>,
+    ) -> Result<UnsignedEvent, MarmotError> {
+        if let Some(new_group_id) = self.reinitialized_groups.read().get(group_id) {
+            return Err(MarmotError::InvalidState(format!(
+                "Group reinitialized as {}",
+                hex::encode(new_group_id)
+            )));
+        }
+
+        // Fail early rather than having relays reject the event
+        let max_bytes = *self.max_message_bytes.read();
+        if plaintext.len() > max_bytes {
+            return Err(MarmotError::InvalidState(format!(
+                "message too large: {} bytes, limit is {}",
+                plaintext.len(),
+                max_bytes
+            )));
+        }
 
         // Create an unsigned event (rumor) with the message content
-        let rumor = UnsignedEvent::new(
+        let kind = kind.unwrap_or_else(|| *self.default_message_kind.read());
+        Ok(UnsignedEvent::new(
             self.keys.public_key(),
             nostr::Timestamp::now(),
-            nostr::Kind::Custom(9), // Kind 9 for chat messages
-            vec![],
+            nostr::Kind::from(kind),
+            tags,
             plaintext.to_string(),
-        );
+        ))
+    }
 
-        let mdk = self.mdk.write();
+    /// Encrypt a message rumor into a group event, under the caller's MDK lock.
+    fn encrypt_rumor(&self, mdk: &MDK<Storage>, group_id: &[u8], rumor: UnsignedEvent) -> Result<Event, MarmotError> {
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
         let event = mdk.create_message(&mls_group_id, rumor, None)
-            .map_err(|e| MarmotError::Internal(format!("Failed to encrypt message: {}", e)))?;
+            .map_err(|e| MarmotError::from_mdk("Failed to encrypt message", e))?;
+        self.publish(&event);
 
-        // Serialize to JSON
-        let event_json = serde_json::to_vec(&event)
-            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize event: {}", e)))?;
+        if let Some(policy) = self.rekey_policies.lock().get_mut(group_id) {
+            policy.messages_sent += 1;
+        }
 
-        Ok(event_json)
+        Ok(event)
     }
 
-    /// Decrypt a message from a group.
+    /// Decrypt a message from a group. Fails if the message belongs to another group.
     /// ciphertext: JSON-serialized Nostr event
+    /// Returns (sender_pubkey, plaintext, epoch, created_at), where created_at is the
+    /// sender's timestamp from the message rumor.
+    pub fn decrypt_message(&self, group_id: &[u8], ciphertext: &[u8]) -> Result<(String, String, u64, u64), MarmotError> {
+        let message = self.decrypt_incoming(group_id, ciphertext)?;
+        Ok((message.sender, message.content, message.epoch, message.created_at))
+    }
+
+    /// Decrypt a message for a caller that needs its content as a C string.
+    /// Content with a NUL byte fails with `InvalidState`; as MDK has already
+    /// consumed the message, it is held for `decrypt_message_raw` instead.
+    /// Returns (sender_pubkey, plaintext, epoch, created_at).
+    pub fn decrypt_message_text(&self, group_id: &[u8], ciphertext: &[u8]) -> Result<(String, String, u64, u64), MarmotError> {
+        let (sender, content, epoch, created_at) = self.decrypt_message(group_id, ciphertext)?;
+        if !content.contains('\0') {
+            return Ok((sender, content, epoch, created_at));
+        }
+
+        if let Ok(event) = serde_json::from_slice::<Event>(ciphertext) {
+            let mut held = self.held_messages.lock();
+            if held.len() == HELD_MESSAGES {
+                held.pop_front();
+            }
+            held.push_back((event.id, group_id.to_vec(), sender, content, epoch));
+        }
+        Err(MarmotError::InvalidState(
+            "Message content contains a NUL byte; use marmot_decrypt_message_raw".into(),
+        ))
+    }
+
+    /// Decrypt a message, returning its content as bytes. A message that
+    /// `decrypt_message_text` could not return is taken from where it was held.
     /// Returns (sender_pubkey, plaintext, epoch).
-    pub fn decrypt_message(&self, _group_id: &[u8], ciphertext: &[u8]) -> Result<(String, String, u64), MarmotError> {
+    pub fn decrypt_message_raw(&self, group_id: &[u8], ciphertext: &[u8]) -> Result<(String, Vec<u8>, u64), MarmotError> {
+        if let Ok(event) = serde_json::from_slice::<Event>(ciphertext) {
+            let mut held = self.held_messages.lock();
+            let position = held.iter().position(|(id, held_group, ..)| {
+                *id == event.id && (group_id.is_empty() || held_group == group_id)
+            });
+            if let Some((_, _, sender, content, epoch)) = position.and_then(|i| held.remove(i)) {
+                return Ok((sender, content.into_bytes(), epoch));
+            }
+        }
+
+        let (sender, content, epoch, _) = self.decrypt_message(group_id, ciphertext)?;
+        Ok((sender, content.into_bytes(), epoch))
+    }
+
+    /// Set the maximum size of outgoing message content (default 65536 bytes).
+    /// Binary payloads count with their base64 encoding.
+    pub fn set_max_message_bytes(&self, max_bytes: usize) -> Result<(), MarmotError> {
+        if max_bytes == 0 {
+            return Err(MarmotError::InvalidState("Message size limit must be positive".into()));
+        }
+        *self.max_message_bytes.write() = max_bytes;
+        Ok(())
+    }
+
+    /// Set how many times a failed storage operation is retried before its error
+    /// is reported (default 3). Zero disables retries.
+    pub fn set_storage_retry_count(&self, count: u32) {
+        *self.storage_retry_count.write() = count;
+    }
+
+    /// Run a storage operation under the MDK write lock, retrying storage
+    /// failures (such as a locked database) with a short, doubling backoff.
+    /// The lock is released while waiting. Other errors are returned at once.
+    /// Only operations that are safe to repeat may be retried: merging a commit,
+    /// for one, may have partly applied before failing.
+    fn with_storage_retry<T>(
+        &self,
+        mut operation: impl FnMut(&MDK<Storage>) -> Result<T, MarmotError>,
+    ) -> Result<T, MarmotError> {
+        let retries = *self.storage_retry_count.read();
+        let mut attempt = 0;
+        loop {
+            let result = operation(&self.mdk.write());
+            match result {
+                Err(e) if e.is_storage() && attempt < retries => {
+                    tracing::warn!("Retrying storage operation after error: {}", e);
+                    std::thread::sleep(STORAGE_RETRY_BACKOFF * 2u32.pow(attempt.min(8)));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Decrypt a message, returning its rumor as the original JSON (pubkey, kind,
+    /// tags, content, created_at, ...) rather than flattened fields.
+    pub fn decrypt_to_rumor_json(&self, group_id: &[u8], ciphertext: &[u8]) -> Result<String, MarmotError> {
+        let rumor = self.decrypt_incoming(group_id, ciphertext)?
+            .rumor
+            .ok_or_else(|| MarmotError::InvalidState("Event is not an application message".into()))?;
+
+        serde_json::to_string(&rumor)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize rumor: {}", e)))
+    }
+
+    /// Decrypt a page of message events in one call.
+    /// events_json: JSON array of Nostr events
+    /// Returns one result per event, in input order; a failing event does not stop the rest.
+    pub fn decrypt_messages(&self, group_id: &[u8], events_json: &[u8]) -> Result<Vec<DecryptResult>, MarmotError> {
+        let events: Vec<serde_json::Value> = serde_json::from_slice(events_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid events JSON: {}", e)))?;
+
+        Ok(events
+            .iter()
+            .map(|event| {
+                let decrypted = serde_json::to_vec(event)
+                    .map_err(MarmotError::from)
+                    .and_then(|event_json| self.decrypt_message(group_id, &event_json));
+                match decrypted {
+                    Ok((sender, content, epoch, _)) => DecryptResult {
+                        ok: true,
+                        sender: Some(sender),
+                        content: Some(content),
+                        epoch: Some(epoch),
+                        error: None,
+                    },
+                    Err(e) => DecryptResult { error: Some(e.to_string()), ..Default::default() },
+                }
+            })
+            .collect())
+    }
+
+    /// Encrypt a message whose content is additionally NIP-44 sealed with a key derived
+    /// from the group's current exporter secret. The rumor carries a
+    /// `["sealed", "nip44", "<epoch>"]` tag so receivers can tell sealed from plain
+    /// messages and derive the key of the epoch it was sealed in.
+    /// Returns JSON-serialized Nostr event.
+    pub fn encrypt_message_sealed(&self, group_id: &[u8], plaintext: &str) -> Result<Vec<u8>, MarmotError> {
+        let epoch = self.current_epoch(group_id)
+            .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
+        let keys = self.sealing_keys(group_id, epoch)?;
+        let sealed = nostr::nips::nip44::encrypt(keys.secret_key(), &keys.public_key(), plaintext, nostr::nips::nip44::Version::V2)
+            .map_err(|e| MarmotError::CryptoError(format!("Failed to seal content: {}", e)))?;
+
+        let epoch = epoch.to_string();
+        let tag = nostr::Tag::parse([SEALED_TAG[0], SEALED_TAG[1], epoch.as_str()])
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to build sealed tag: {}", e)))?;
+
+        self.encrypt_with_tags(group_id, &sealed, vec![tag], None, None)
+    }
+
+    /// Decrypt a message, unsealing its content if it was sent with `encrypt_message_sealed`.
+    /// Sealed content is unsealed with the key of the epoch it was sealed in, which
+    /// lasts as long as MDK keeps that epoch's exporter secret; after that it
+    /// fails with `EpochTooOld`.
+    /// Returns (sender_pubkey, plaintext, epoch, was_sealed).
+    pub fn decrypt_message_sealed(&self, group_id: &[u8], ciphertext: &[u8]) -> Result<(String, String, u64, bool), MarmotError> {
+        let message = self.decrypt_incoming(group_id, ciphertext)?;
+
+        let sealed_epoch = message.tags.iter().find_map(|tag| match tag.as_slice() {
+            [name, scheme, epoch] if [name.as_str(), scheme.as_str()] == SEALED_TAG => Some(epoch.parse::<u64>()),
+            _ => None,
+        });
+        let Some(sealed_epoch) = sealed_epoch else {
+            return Ok((message.sender, message.content, message.epoch, false));
+        };
+        let sealed_epoch = sealed_epoch
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid sealed epoch: {}", e)))?;
+
+        let keys = self.sealing_keys(&message.group_id, sealed_epoch)?;
+        let content = nostr::nips::nip44::decrypt(keys.secret_key(), &keys.public_key(), &message.content)
+            .map_err(|e| MarmotError::CryptoError(format!("Failed to unseal content: {}", e)))?;
+
+        Ok((message.sender, content, message.epoch, true))
+    }
+
+    /// Encrypt a binary payload (e.g. protobuf) for a group. The bytes are carried
+    /// base64-encoded in the rumor content and marked with an encoding tag.
+    /// Returns JSON-serialized Nostr event.
+    pub fn encrypt_binary(&self, group_id: &[u8], data: &[u8]) -> Result<Vec<u8>, MarmotError> {
+        use base64::Engine;
+
+        let content = base64::engine::general_purpose::STANDARD.encode(data);
+        let tag = nostr::Tag::parse(BINARY_TAG)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to build encoding tag: {}", e)))?;
+
+        self.encrypt_with_tags(group_id, &content, vec![tag], None, None)
+    }
+
+    /// Decrypt a message sent with `encrypt_binary`.
+    /// Returns (sender_pubkey, data, epoch).
+    pub fn decrypt_binary(&self, group_id: &[u8], ciphertext: &[u8]) -> Result<(String, Vec<u8>, u64), MarmotError> {
+        use base64::Engine;
+
+        let message = self.decrypt_incoming(group_id, ciphertext)?;
+        if !message.tags.iter().any(|tag| tag.as_slice() == BINARY_TAG) {
+            return Err(MarmotError::InvalidState("Message does not carry binary content".into()));
+        }
+
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(&message.content)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid binary content: {}", e)))?;
+
+        Ok((message.sender, data, message.epoch))
+    }
+
+    /// Process any relay-delivered group event, whether it carries an application
+    /// message, a commit or a proposal.
+    /// event_json: JSON-serialized Nostr event
+    pub fn process_message(&self, event_json: &[u8]) -> Result<ProcessedEvent, MarmotError> {
+        let event: Event = serde_json::from_slice(event_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid event JSON: {}", e)))?;
+        if event.kind == nostr::Kind::MlsWelcome || event.kind == nostr::Kind::GiftWrap {
+            return Ok(ProcessedEvent::WelcomeUnsupported);
+        }
+
+        let message = self.decrypt_incoming(&[], event_json)?;
+        let group_id = hex::encode(&message.group_id);
+        Ok(match message.kind {
+            Some(kind) => ProcessedEvent::Message {
+                group_id,
+                sender: message.sender,
+                content: message.content,
+                epoch: message.epoch,
+                kind: kind.as_u16(),
+            },
+            None if message.sender == "commit" => ProcessedEvent::Commit { group_id, epoch: message.epoch },
+            None => ProcessedEvent::Proposal,
+        })
+    }
+
+    /// Set (or clear, with None) the callback invoked by `process_events`.
+    pub fn set_message_callback(&self, callback: Option<MessageCallback>) {
+        *self.message_callback.write() = callback;
+    }
+
+    /// Set (or clear, with None) the test transport that captures published events:
+    /// encrypted messages, commits and welcome rumors (unwrapped; gift-wrapping them
+    /// is left to the host). The proxy runs under the client's locks and must not
+    /// call back into the client.
+    #[cfg(any(test, feature = "test-transport"))]
+    pub fn set_nostr_proxy(&self, proxy: Option<NostrProxyCallback>) {
+        *self.nostr_proxy.write() = proxy;
+    }
+
+    /// Hand an event the client would publish to the test transport, if one is set.
+    #[cfg(any(test, feature = "test-transport"))]
+    fn publish(&self, event: &impl serde::Serialize) {
+        let Some(proxy) = *self.nostr_proxy.read() else { return };
+        if let Ok(json) = serde_json::to_string(event) {
+            let json = CString::new(json).unwrap_or_default();
+            proxy(json.as_ptr());
+        }
+    }
+
+    /// Events are only published through the test transport.
+    #[cfg(not(any(test, feature = "test-transport")))]
+    fn publish(&self, _event: &impl serde::Serialize) {}
+
+    /// Feed back an event captured by the test transport, as if it came from a relay:
+    /// welcome rumors are accepted, and anything else goes through `process_message`.
+    #[cfg(any(test, feature = "test-transport"))]
+    pub fn replay_published(&self, event_json: &[u8]) -> Result<ProcessedEvent, MarmotError> {
+        let event: serde_json::Value = serde_json::from_slice(event_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid event JSON: {}", e)))?;
+        if event["kind"] != u64::from(nostr::Kind::MlsWelcome.as_u16()) {
+            return self.process_message(event_json);
+        }
+
+        // Captured welcome rumors were never gift-wrapped, so there is no wrapper ID
+        let rumor: UnsignedEvent = serde_json::from_value(event)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid rumor event: {}", e)))?;
+        let preview = self.preview_welcome_rumor(&EventId::all_zeros(), &rumor)?;
+        let (group_id, _, epoch, _) = self.accept_welcome(&preview.welcome_id)?;
+        Ok(ProcessedEvent::Joined { group_id: hex::encode(group_id), epoch })
+    }
+
+    /// Process a batch of incoming message events, dispatching each application
+    /// message to the message callback. Replayed events are skipped, and an event
+    /// that fails is reported without stopping the rest of the batch.
+    /// events_json: JSON array of Nostr events
+    pub fn process_events(&self, events_json: &[u8]) -> Result<EventsReport, MarmotError> {
+        let events: Vec<serde_json::Value> = serde_json::from_slice(events_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid events JSON: {}", e)))?;
+
+        let mut report = EventsReport { dispatched: 0, errors: Vec::new() };
+        for (index, event) in events.into_iter().enumerate() {
+            let event_json = serde_json::to_vec(&event)?;
+
+            // decrypt_incoming releases the MDK lock before returning, so the
+            // callback may safely call back into this client
+            let message = match self.decrypt_incoming(&[], &event_json) {
+                Ok(message) => message,
+                Err(MarmotError::DuplicateMessage) => continue,
+                Err(e) => {
+                    report.errors.push(EventError {
+                        index,
+                        event_id: event["id"].as_str().map(str::to_string),
+                        code: e.code(),
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let Some(kind) = message.kind else { continue };
+            // Copy the callback out so the lock is not held while it runs; it may
+            // replace itself
+            let callback = *self.message_callback.read();
+            if let Some(callback) = callback {
+                let sender = CString::new(message.sender).unwrap_or_default();
+                let content = CString::new(message.content).unwrap_or_default();
+                callback(
+                    message.group_id.as_ptr(),
+                    message.group_id.len() as c_int,
+                    sender.as_ptr(),
+                    content.as_ptr(),
+                    kind.as_u16(),
+                    message.epoch,
+                );
+                report.dispatched += 1;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Sign an app-level Nostr event (e.g. profile metadata) with this client's key.
+    /// unsigned_event_json: JSON unsigned event whose pubkey is this client's
+    /// Returns the signed event JSON.
+    pub fn sign_event(&self, unsigned_event_json: &str) -> Result<String, MarmotError> {
+        let unsigned: UnsignedEvent = serde_json::from_str(unsigned_event_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid unsigned event: {}", e)))?;
+        if unsigned.pubkey != self.keys.public_key() {
+            return Err(MarmotError::InvalidKey("event pubkey does not match the client's key".into()));
+        }
+
+        let event = unsigned
+            .sign_with_keys(&self.keys)
+            .map_err(|e| MarmotError::CryptoError(format!("Failed to sign event: {}", e)))?;
+        // A supplied id that does not match the event's contents fails here
+        event
+            .verify()
+            .map_err(|e| MarmotError::InvalidState(format!("Invalid unsigned event: {}", e)))?;
+
+        serde_json::to_string(&event)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize event: {}", e)))
+    }
+
+    /// Derive the NIP-44 keys for content sealed at `epoch` from the exporter
+    /// secret MDK keeps for that epoch. Fails with `EpochTooOld` once MDK has
+    /// dropped it.
+    fn sealing_keys(&self, group_id: &[u8], epoch: u64) -> Result<Keys, MarmotError> {
+        use nostr::hashes::{sha256, Hash};
+        use openmls::prelude::OpenMlsProvider;
+
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+        let mdk = self.mdk.read();
+        let exporter_secret = mdk.provider.storage()
+            .get_group_exporter_secret(&mls_group_id, epoch)
+            .map_err(|e| MarmotError::storage("Failed to load exporter secret", e))?
+            .ok_or_else(|| MarmotError::EpochTooOld(format!("No sealing key for epoch {}", epoch)))?;
+
+        // Labelled, so the key differs from the one MDK wraps messages with
+        let exporter_secret: &[u8] = exporter_secret.secret.as_ref();
+        let input = zeroize::Zeroizing::new([b"marmot sealed content".as_slice(), exporter_secret].concat());
+        let secret = zeroize::Zeroizing::new(sha256::Hash::hash(&input).to_byte_array());
+
+        let secret_key = nostr::SecretKey::from_slice(secret.as_slice())
+            .map_err(|e| MarmotError::CryptoError(format!("Invalid sealing key: {}", e)))?;
+        Ok(Keys::new(secret_key))
+    }
+
+    /// Process an incoming message event.
+    /// Commits and proposals are reported with "commit" / "proposal" as the sender.
+    /// Fails if the event belongs to a group other than `group_id`; an empty
+    /// `group_id` accepts events of any group.
+    fn decrypt_incoming(&self, group_id: &[u8], ciphertext: &[u8]) -> Result<DecryptedMessage, MarmotError> {
         // Parse the event from JSON
         let event_json = std::str::from_utf8(ciphertext)
-            .map_err(|e| MarmotError::Internal(format!("Invalid UTF-8: {}", e)))?;
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid UTF-8: {}", e)))?;
         let event: Event = serde_json::from_str(event_json)
-            .map_err(|e| MarmotError::Internal(format!("Invalid event JSON: {}", e)))?;
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid event JSON: {}", e)))?;
 
         // Process the message
         let mdk = self.mdk.write();
-        let result = mdk.process_message(&event)
-            .map_err(|e| MarmotError::Internal(format!("Failed to process message: {}", e)))?;
+        // Catch ciphertexts the host associated with the wrong conversation before
+        // MDK consumes them
+        if !group_id.is_empty() && event_group_id(&mdk, &event)? != group_id {
+            return Err(MarmotError::InvalidState("group id mismatch".into()));
+        }
+        let result = self.process_group_event(&mdk, &event, "Failed to process message")?;
 
         // Extract the message content based on result type
-        match result {
-            mdk_core::messages::MessageProcessingResult::ApplicationMessage(msg) => {
-                let sender = msg.pubkey.to_hex();
-                let content = msg.content.clone();
-                let epoch = 0u64; // TODO: Get actual epoch
-                Ok((sender, content, epoch))
-            }
-            mdk_core::messages::MessageProcessingResult::Commit { .. } => {
-                Ok(("commit".to_string(), String::new(), 0))
-            }
+        let message = match result {
+            mdk_core::messages::MessageProcessingResult::ApplicationMessage(msg) => Ok(DecryptedMessage {
+                group_id: msg.mls_group_id.as_slice().to_vec(),
+                sender: msg.pubkey.to_hex(),
+                content: msg.content.clone(),
+                epoch: epoch_of(&mdk, &msg.mls_group_id).unwrap_or(0),
+                tags: msg.tags.iter().cloned().collect(),
+                kind: Some(msg.kind),
+                // The inner rumor's time, not the outer group event's
+                created_at: msg.event.created_at.as_u64(),
+                rumor: Some(msg.event.clone()),
+            }),
+            mdk_core::messages::MessageProcessingResult::Commit { mls_group_id } => Ok(DecryptedMessage {
+                group_id: mls_group_id.as_slice().to_vec(),
+                sender: "commit".to_string(),
+                content: String::new(),
+                epoch: epoch_of(&mdk, &mls_group_id).unwrap_or(0),
+                tags: vec![],
+                kind: None,
+                created_at: 0,
+                rumor: None,
+            }),
             mdk_core::messages::MessageProcessingResult::Proposal(_) |
-            mdk_core::messages::MessageProcessingResult::PendingProposal { .. } => {
-                Ok(("proposal".to_string(), String::new(), 0))
+            mdk_core::messages::MessageProcessingResult::PendingProposal { .. } => Ok(DecryptedMessage {
+                group_id: group_id.to_vec(),
+                sender: "proposal".to_string(),
+                content: String::new(),
+                epoch: 0,
+                tags: vec![],
+                kind: None,
+                created_at: 0,
+                rumor: None,
+            }),
+            // MDK reports messages it has no epoch secrets for as unprocessable. They may
+            // be from an epoch this client has not reached yet, so only those whose
+            // epoch is older than the retained ones are reported as too old
+            mdk_core::messages::MessageProcessingResult::Unprocessable { mls_group_id } => {
+                let current = epoch_of(&mdk, &mls_group_id).unwrap_or(0);
+                let message_epoch = event_epoch(&mdk, &mls_group_id, &event, u64::MAX);
+                if let Some(message_epoch) = message_epoch.filter(|e| e + (MDK_RETAINED_PAST_EPOCHS as u64) < current) {
+                    Err(MarmotError::EpochTooOld(format!(
+                        "Message is from epoch {}, the group is at {} and keeps {} past epochs",
+                        message_epoch, current, MDK_RETAINED_PAST_EPOCHS
+                    )))
+                } else {
+                    Err(MarmotError::Mdk {
+                        kind: MdkErrorKind::Message,
+                        message: "Message could not be processed; it may apply once missing commits are processed".into(),
+                    })
+                }
             }
-            other => Err(MarmotError::Internal(format!("Unexpected message type: {:?}", other))),
-        }
+            other => Err(MarmotError::InvalidState(format!("Unexpected message type: {:?}", other))),
+        }?;
+
+        Ok(message)
     }
 
     /// Process a commit message.
-    pub fn process_commit(&self, _group_id: &[u8], commit_data: &[u8]) -> Result<(), MarmotError> {
+    /// Returns the effect of the processed event on the group.
+    pub fn process_commit(&self, group_id: &[u8], commit_data: &[u8]) -> Result<CommitEffect, MarmotError> {
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
         // Parse the event from JSON
         let event_json = std::str::from_utf8(commit_data)
-            .map_err(|e| MarmotError::Internal(format!("Invalid UTF-8: {}", e)))?;
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid UTF-8: {}", e)))?;
         let event: Event = serde_json::from_str(event_json)
-            .map_err(|e| MarmotError::Internal(format!("Failed to process commit: {}", e)))?;
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid commit event JSON: {}", e)))?;
 
         // Process as a message (commits are processed the same way)
         let mdk = self.mdk.write();
-        let result = mdk.process_message(&event)
-            .map_err(|e| MarmotError::Internal(format!("Failed to process commit: {}", e)))?;
 
-        // Check if it was actually processed as a commit
-        match result {
-            mdk_core::messages::MessageProcessingResult::Commit { .. } => Ok(()),
-            mdk_core::messages::MessageProcessingResult::Unprocessable { .. } => {
-                Err(MarmotError::Internal("Commit was unprocessable by MLS layer".into()))
+        // A commit of another group must be rejected before MDK merges it there
+        if event_group_id(&mdk, &event)? != group_id {
+            return Err(MarmotError::InvalidState("group id mismatch".into()));
+        }
+
+        let members_before = mdk.get_members(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get members", e))?;
+        let result = match self.process_group_event(&mdk, &event, "Failed to process commit") {
+            Ok(result) => result,
+            // A relay may redeliver a commit, or it may already have been applied
+            // through decrypt_message or process_events; applying it again is harmless
+            Err(MarmotError::DuplicateMessage) => {
+                let epoch = epoch_of(&mdk, &mls_group_id)
+                    .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
+                return Ok(CommitEffect {
+                    kind: CommitKind::AlreadyApplied,
+                    epoch,
+                    member_delta: 0,
+                    added: Vec::new(),
+                    removed: Vec::new(),
+                });
             }
+            Err(e) => return Err(e),
+        };
+
+        // Check if it was actually processed as a commit
+        let kind = match result {
+            mdk_core::messages::MessageProcessingResult::Commit { .. } => CommitKind::Commit,
+            mdk_core::messages::MessageProcessingResult::Proposal(_) |
+            mdk_core::messages::MessageProcessingResult::PendingProposal { .. } => CommitKind::Proposal,
+            mdk_core::messages::MessageProcessingResult::ExternalJoinProposal { .. } => CommitKind::ExternalJoin,
+            mdk_core::messages::MessageProcessingResult::Unprocessable { .. } => CommitKind::Unprocessable,
             other => {
-                // Other results (ApplicationMessage, Proposal) are unexpected for commits
+                // Other results (ApplicationMessage) are unexpected for commits
                 // but the message was processed - don't error
-                eprintln!("[RUST] process_commit got: {:?}", other);
-                Ok(())
+                tracing::debug!("process_commit got: {:?}", other);
+                CommitKind::Other
+            }
+        };
+
+        let epoch = epoch_of(&mdk, &mls_group_id)
+            .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
+        let members_after = mdk.get_members(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get members", e))?;
+
+        Ok(CommitEffect {
+            kind,
+            epoch,
+            member_delta: members_after.len() as i32 - members_before.len() as i32,
+            added: members_after.difference(&members_before).map(|pk| pk.to_hex()).collect(),
+            removed: members_before.difference(&members_after).map(|pk| pk.to_hex()).collect(),
+        })
+    }
+
+    /// Apply commits missed while offline, in MLS epoch order, whatever order they
+    /// are supplied in. A commit's epoch is only readable once the group has reached
+    /// it, so the next commit is always one for the current epoch; events whose epoch
+    /// cannot be read yet are tried last, by creation time. Events that fail are
+    /// retried after every commit that applies, until no more progress is made.
+    /// Commits already applied are skipped.
+    /// events_json: JSON array of commit events
+    pub fn reprocess_commits(&self, group_id: &[u8], events_json: &[u8]) -> Result<ReprocessReport, MarmotError> {
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+        let mut remaining: Vec<Event> = serde_json::from_slice(events_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid events JSON: {}", e)))?;
+
+        let mut applied = 0;
+        // Events that failed since the last commit applied
+        let mut failed = Vec::new();
+        loop {
+            let next = {
+                let mdk = self.mdk.read();
+                remaining
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, event)| {
+                        let epoch = event_epoch(&mdk, &mls_group_id, event, MDK_RETAINED_PAST_EPOCHS as u64);
+                        (epoch.is_none(), epoch, event.created_at)
+                    })
+                    .map(|(index, _)| index)
+            };
+            let Some(index) = next else { break };
+            let event = remaining.remove(index);
+
+            match self.process_commit(group_id, &serde_json::to_vec(&event)?) {
+                Ok(effect) if effect.kind == CommitKind::Commit => {
+                    applied += 1;
+                    // The new epoch may be what an earlier failure was missing
+                    remaining.append(&mut failed);
+                }
+                Ok(effect) if effect.kind == CommitKind::Unprocessable => failed.push(event),
+                Ok(_) => {}
+                Err(e @ MarmotError::GroupNotFound(_)) => return Err(e),
+                Err(e) => {
+                    tracing::debug!("reprocess_commits: {} failed: {}", event.id, e);
+                    failed.push(event);
+                }
             }
         }
+
+        Ok(ReprocessReport {
+            applied,
+            failed: failed.iter().map(|event| event.id.to_hex()).collect(),
+        })
     }
 
     /// Update keys for forward secrecy.
     /// Returns JSON-serialized commit event.
     pub fn update_keys(&self, group_id: &[u8]) -> Result<Vec<u8>, MarmotError> {
-        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+        self.ensure_writable()?;
 
         let mdk = self.mdk.write();
+        let commit = self.commit_self_update(&mdk, group_id)?;
 
-        // Perform self-update
-        let result = mdk
-            .self_update(&mls_group_id)
-            .map_err(|e| MarmotError::Internal(format!("Failed to update keys: {}", e)))?;
-
-        // Merge the pending commit
-        mdk.merge_pending_commit(&mls_group_id)
-            .map_err(|e| MarmotError::Internal(format!("Failed to merge commit: {}", e)))?;
-
-        // Serialize the evolution event
-        let event_json = serde_json::to_vec(&result.evolution_event)
+        // Serialize the evolution event (its `id` is the commit event ID)
+        let event_json = serde_json::to_vec(&commit)
             .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize event: {}", e)))?;
 
         Ok(event_json)
     }
 
-    /// Remove a member from a group.
-    /// Returns JSON-serialized commit event.
-    pub fn remove_member(&self, group_id: &[u8], member_public_key: &str) -> Result<Vec<u8>, MarmotError> {
+    /// Update this client's keys and merge the commit, under the caller's MDK lock.
+    /// Returns the commit event.
+    fn commit_self_update(&self, mdk: &MDK<Storage>, group_id: &[u8]) -> Result<Event, MarmotError> {
         let mls_group_id = mdk_core::GroupId::from_slice(group_id);
 
-        // Parse the member's public key
-        let pubkey = PublicKey::from_hex(member_public_key)
-            .map_err(|e| MarmotError::InvalidKey(format!("Invalid public key: {}", e)))?;
-
-        let mdk = self.mdk.write();
-
-        // Remove the member
+        // Perform self-update
         let result = mdk
-            .remove_members(&mls_group_id, &[pubkey.clone()])
-            .map_err(|e| MarmotError::Internal(format!("Failed to remove member: {}", e)))?;
+            .self_update(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to update keys", e))?;
 
         // Merge the pending commit
         mdk.merge_pending_commit(&mls_group_id)
-            .map_err(|e| MarmotError::Internal(format!("Failed to merge commit: {}", e)))?;
+            .map_err(|e| MarmotError::from_mdk("Failed to merge commit", e))?;
 
-        // Serialize the evolution event
+        if let Some(policy) = self.rekey_policies.lock().get_mut(group_id) {
+            policy.messages_sent = 0;
+            policy.last_update = std::time::Instant::now();
+        }
+
+        self.publish(&result.evolution_event);
+        self.record_own_commit(mdk, &mls_group_id, result.evolution_event.id);
+
+        Ok(result.evolution_event)
+    }
+
+    /// Rotate this client's keys and encrypt a message under the new epoch,
+    /// e.g. to announce the rotation to the group. Both happen under one lock, so
+    /// no other commit can come between them.
+    /// Returns JSON object with { "commit": {...}, "message": {...}, "message_error" };
+    /// members must process the commit before the message. Once the commit is
+    /// merged it is always returned: if the message then fails to encrypt,
+    /// "message" is null and "message_error" says why.
+    pub fn self_update_with_message(&self, group_id: &[u8], plaintext: &str) -> Result<Vec<u8>, MarmotError> {
+        #[derive(serde::Serialize)]
+        struct SelfUpdateWithMessage {
+            commit: Event,
+            message: Option<Event>,
+            message_error: Option<String>,
+        }
+
+        self.ensure_writable()?;
+        // Checked before committing, so a message that cannot be sent rotates nothing
+        let rumor = self.message_rumor(group_id, plaintext, vec![], None)?;
+
+        let mdk = self.mdk.write();
+        let commit = self.commit_self_update(&mdk, group_id)?;
+        let (message, message_error) = match self.encrypt_rumor(&mdk, group_id, rumor) {
+            Ok(message) => (Some(message), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        serde_json::to_vec(&SelfUpdateWithMessage { commit, message, message_error })
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize result: {}", e)))
+    }
+
+    /// Set when `should_rekey` reports that a group is due for `update_keys`.
+    /// max_messages: messages sent by this client since the last self-update, 0 for no limit
+    /// max_seconds: time since the last self-update (or since the policy was set), 0 for no limit
+    pub fn set_rekey_policy(&self, group_id: &[u8], max_messages: u32, max_seconds: u64) -> Result<(), MarmotError> {
+        if self.current_epoch(group_id).is_none() {
+            return Err(MarmotError::GroupNotFound(hex::encode(group_id)));
+        }
+
+        self.rekey_policies.lock().insert(
+            group_id.to_vec(),
+            RekeyState { max_messages, max_seconds, messages_sent: 0, last_update: std::time::Instant::now() },
+        );
+        Ok(())
+    }
+
+    /// Whether the group's rekey policy has fired. Always false without a policy.
+    pub fn should_rekey(&self, group_id: &[u8]) -> bool {
+        self.rekey_policies.lock().get(group_id).is_some_and(|policy| {
+            (policy.max_messages > 0 && policy.messages_sent >= policy.max_messages)
+                || (policy.max_seconds > 0 && policy.last_update.elapsed().as_secs() >= policy.max_seconds)
+        })
+    }
+
+    /// Remove a member from a group.
+    /// Returns JSON-serialized commit event.
+    pub fn remove_member(&self, group_id: &[u8], member_public_key: &str) -> Result<Vec<u8>, MarmotError> {
+        // Parse the member's public key
+        let pubkey = PublicKey::from_hex(member_public_key)
+            .map_err(|e| MarmotError::InvalidKey(format!("Invalid public key: {}", e)))?;
+
+        self.remove_pubkeys(group_id, &[pubkey])
+    }
+
+    /// Remove several members from a group in a single commit.
+    /// pubkeys_json: JSON array of hex public keys. If any key is invalid or not a
+    /// member, nothing is removed.
+    /// Returns JSON-serialized commit event.
+    pub fn remove_members(&self, group_id: &[u8], pubkeys_json: &str) -> Result<Vec<u8>, MarmotError> {
+        let keys: Vec<String> = serde_json::from_str(pubkeys_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid public keys JSON: {}", e)))?;
+        if keys.is_empty() {
+            return Err(MarmotError::InvalidState("No members to remove".into()));
+        }
+
+        let pubkeys = keys
+            .iter()
+            .map(|key| {
+                PublicKey::from_hex(key).map_err(|e| MarmotError::InvalidKey(format!("Invalid public key: {}", e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.remove_pubkeys(group_id, &pubkeys)
+    }
+
+    /// Remove members in one commit, after checking every key is a current member.
+    fn remove_pubkeys(&self, group_id: &[u8], pubkeys: &[PublicKey]) -> Result<Vec<u8>, MarmotError> {
+        self.ensure_writable()?;
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        let mdk = self.mdk.write();
+
+        let members = mdk.get_members(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get members", e))?;
+        if let Some(missing) = pubkeys.iter().find(|pk| !members.contains(*pk)) {
+            return Err(MarmotError::MemberNotFound(missing.to_hex()));
+        }
+
+        // Remove the members
+        let result = mdk
+            .remove_members(&mls_group_id, pubkeys)
+            .map_err(|e| MarmotError::from_mdk("Failed to remove member", e))?;
+
+        // Merge the pending commit
+        mdk.merge_pending_commit(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to merge commit", e))?;
+
+        self.publish(&result.evolution_event);
+        self.record_own_commit(&mdk, &mls_group_id, result.evolution_event.id);
+
+        // Serialize the evolution event (its `id` is the commit event ID)
         let event_json = serde_json::to_vec(&result.evolution_event)
             .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize event: {}", e)))?;
 
         Ok(event_json)
     }
 
+    /// Propose removing ourselves from a group.
+    /// MLS doesn't let a member commit its own removal, so this only produces the
+    /// removal proposal; another member must include it in a commit (e.g. after
+    /// receiving it through `process_message`). Use `remove_member` to remove
+    /// someone else, which commits immediately.
+    /// Returns the JSON-serialized proposal event.
+    pub fn propose_self_removal(&self, group_id: &[u8]) -> Result<Vec<u8>, MarmotError> {
+        self.ensure_writable()?;
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        let mdk = self.mdk.write();
+        if mdk.get_group(&mls_group_id).map_err(|e| MarmotError::from_mdk("Failed to get group", e))?.is_none() {
+            return Err(MarmotError::GroupNotFound(hex::encode(group_id)));
+        }
+
+        let result = mdk
+            .leave_group(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to propose self removal", e))?;
+        self.publish(&result.evolution_event);
+
+        serde_json::to_vec(&result.evolution_event)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize event: {}", e)))
+    }
+
+    /// Get the ID of the most recent commit event produced by this client, if any.
+    pub fn last_commit_event_id(&self) -> Option<String> {
+        self.last_commit_event_id.read().map(|id| id.to_hex())
+    }
+
+    /// Check whether a group exists, without fetching its members.
+    pub fn group_exists(&self, group_id: &[u8]) -> Result<bool, MarmotError> {
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        let mdk = self.mdk.read();
+        let group = mdk.get_group(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get group", e))?;
+
+        Ok(group.is_some())
+    }
+
+    /// Check whether a group has a commit that was created but not yet merged.
+    /// MLS forbids creating another commit until it is merged or cleared.
+    pub fn has_pending_commit(&self, group_id: &[u8]) -> Result<bool, MarmotError> {
+        let mdk = self.mdk.read();
+        Ok(load_mls_group(&mdk, group_id)?.pending_commit().is_some())
+    }
+
     /// Get information about a group.
-    /// Returns (name, epoch, members_json) or None if not found.
-    pub fn get_group_info(&self, group_id: &[u8]) -> Option<(String, u64, Vec<String>)> {
+    /// Returns (name, epoch, members_json, description, creation) or None if not found.
+    pub fn get_group_info(&self, group_id: &[u8]) -> Option<(String, u64, Vec<String>, String, GroupCreationInfo)> {
         let mls_group_id = mdk_core::GroupId::from_slice(group_id);
 
         let mdk = self.mdk.read();
@@ -347,33 +2391,3243 @@ impl MarmotClient {
         // Get members
         let members = mdk.get_members(&mls_group_id).ok()?;
         let member_pubkeys: Vec<String> = members.iter().map(|pk| pk.to_hex()).collect();
+        let creation = creation_info(&load_mls_group(&mdk, group_id).ok()?);
 
         Some((
             group.name.clone(),
-            0, // TODO: Get actual epoch
+            group.epoch,
             member_pubkeys,
+            group.description.clone(),
+            creation,
         ))
     }
 
-    /// Export group state for persistence.
-    pub fn export_group_state(&self, group_id: &[u8]) -> Result<Vec<u8>, MarmotError> {
+    /// Get one page of a group's members, sorted by public key so pages are stable.
+    /// Returns (members, total member count).
+    pub fn members_page(&self, group_id: &[u8], offset: usize, limit: usize) -> Result<(Vec<String>, usize), MarmotError> {
+        if limit == 0 {
+            return Err(MarmotError::InvalidState("Page limit must be at least one".into()));
+        }
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        let mdk = self.mdk.read();
+        let mut members: Vec<String> = mdk.get_members(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get members", e))?
+            .iter()
+            .map(|pk| pk.to_hex())
+            .collect();
+        members.sort();
+
+        let total = members.len();
+        Ok((members.into_iter().skip(offset).take(limit).collect(), total))
+    }
+
+    /// Get a group's members as they were at a past (or the current) epoch.
+    /// Only epochs this client has observed since it was created are known:
+    /// MDK does not retain historical group states.
+    pub fn members_at_epoch(&self, group_id: &[u8], epoch: u64) -> Result<Vec<String>, MarmotError> {
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        let mdk = self.mdk.read();
+        let current = epoch_of(&mdk, &mls_group_id)
+            .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
+        if epoch > current {
+            return Err(MarmotError::InvalidState(format!(
+                "Epoch {} is ahead of the group's current epoch {}",
+                epoch, current
+            )));
+        }
+
+        self.roster_history
+            .read()
+            .get(group_id)
+            .and_then(|rosters| rosters.get(&epoch))
+            .cloned()
+            .ok_or_else(|| MarmotError::InvalidState(format!(
+                "Roster history not retained for epoch {}",
+                epoch
+            )))
+    }
+
+    /// Remember the group's current roster for `members_at_epoch`.
+    fn record_roster(&self, mdk: &MDK<Storage>, mls_group_id: &mdk_core::GroupId) {
+        let (Some(epoch), Ok(members)) = (epoch_of(mdk, mls_group_id), mdk.get_members(mls_group_id)) else {
+            return;
+        };
+
+        let mut members: Vec<String> = members.iter().map(|pk| pk.to_hex()).collect();
+        members.sort();
+        self.roster_history
+            .write()
+            .entry(mls_group_id.as_slice().to_vec())
+            .or_default()
+            .insert(epoch, members);
+        if let Some(leaves) = member_leaves(mdk, mls_group_id) {
+            self.leaf_keys.write().insert(mls_group_id.as_slice().to_vec(), leaves);
+        }
+    }
+
+    /// Run an incoming group event through MDK. Every path that processes group
+    /// events goes through here, so a commit applied by one is a duplicate to the
+    /// others, whether it was received or is our own commit echoed back by a relay.
+    /// context: prefix for MDK errors
+    fn process_group_event(
+        &self,
+        mdk: &MDK<Storage>,
+        event: &Event,
+        context: &str,
+    ) -> Result<mdk_core::messages::MessageProcessingResult, MarmotError> {
+        // A replay would fail in OpenMLS, whose message keys are deleted after
+        // first use, so check our record of commits and MDK's of processed events
+        if self.is_applied_commit(&event.id) || already_processed(mdk, &event.id)? {
+            return Err(MarmotError::DuplicateMessage);
+        }
+        let result = mdk.process_message(event).map_err(|e| {
+            if is_epoch_too_old(&e) {
+                MarmotError::EpochTooOld(e.to_string())
+            } else {
+                MarmotError::from_mdk(context, e)
+            }
+        })?;
+
+        if let mdk_core::messages::MessageProcessingResult::Commit { mls_group_id } = &result {
+            self.remember_commit(mdk, mls_group_id, event.id);
+            self.record_commit(mdk, mls_group_id, None);
+        }
+        Ok(result)
+    }
+
+    /// Record a commit this client created and merged.
+    fn record_own_commit(&self, mdk: &MDK<Storage>, mls_group_id: &mdk_core::GroupId, event_id: EventId) {
+        *self.last_commit_event_id.write() = Some(event_id);
+        self.remember_commit(mdk, mls_group_id, event_id);
+        self.record_commit(mdk, mls_group_id, Some(self.keys.public_key()));
+    }
+
+    /// Whether a commit event has been applied to any group.
+    fn is_applied_commit(&self, event_id: &EventId) -> bool {
+        self.applied_commits
+            .lock()
+            .values()
+            .any(|commits| commits.values().any(|id| id == event_id))
+    }
+
+    /// Remember the event of the commit that moved a group to its current epoch,
+    /// forgetting commits older than `APPLIED_COMMIT_EPOCHS`.
+    fn remember_commit(&self, mdk: &MDK<Storage>, mls_group_id: &mdk_core::GroupId, event_id: EventId) {
+        let Some(epoch) = epoch_of(mdk, mls_group_id) else {
+            return;
+        };
+        let mut applied = self.applied_commits.lock();
+        let commits = applied.entry(mls_group_id.as_slice().to_vec()).or_default();
+        commits.insert(epoch, event_id);
+        while commits.len() > APPLIED_COMMIT_EPOCHS {
+            commits.pop_first();
+        }
+    }
+
+    /// Record a merged or processed commit in the group's epoch history, classifying
+    /// it by comparing the new roster with the previous one, then record the roster.
+    /// actor: the committer, if known; otherwise it is inferred from the roster
+    fn record_commit(&self, mdk: &MDK<Storage>, mls_group_id: &mdk_core::GroupId, actor: Option<PublicKey>) {
+        if let Some((epoch, kind)) = self.classify_commit(mdk, mls_group_id) {
+            let actor = actor.or_else(|| self.commit_actor(mdk, mls_group_id));
+            self.epoch_history
+                .write()
+                .entry(mls_group_id.as_slice().to_vec())
+                .or_default()
+                .push(EpochRecord {
+                    epoch,
+                    timestamp: nostr::Timestamp::now().as_u64(),
+                    kind,
+                    actor: actor.map(|pk| pk.to_hex()),
+                });
+        }
+        self.record_roster(mdk, mls_group_id);
+    }
+
+    /// Classify the group's latest commit against the last recorded roster.
+    /// Returns None if the epoch did not advance (proposals, replays) or there is
+    /// no earlier roster to compare with.
+    fn classify_commit(&self, mdk: &MDK<Storage>, mls_group_id: &mdk_core::GroupId) -> Option<(u64, EpochChangeKind)> {
+        let epoch = epoch_of(mdk, mls_group_id)?;
+        let members: Vec<String> = mdk.get_members(mls_group_id).ok()?.iter().map(|pk| pk.to_hex()).collect();
+
+        let rosters = self.roster_history.read();
+        let (&last_epoch, previous) = rosters.get(mls_group_id.as_slice())?.last_key_value()?;
+        if last_epoch >= epoch {
+            return None;
+        }
+
+        let added = members.iter().any(|pk| !previous.contains(pk));
+        let removed = previous.iter().any(|pk| !members.contains(pk));
+        let kind = match (added, removed) {
+            (true, true) => EpochChangeKind::AddRemove,
+            (true, false) => EpochChangeKind::Add,
+            (false, true) => EpochChangeKind::Remove,
+            (false, false) => EpochChangeKind::Update,
+        };
+        Some((epoch, kind))
+    }
+
+    /// Identify the committer of a received commit from the last recorded roster:
+    /// the one remaining member whose leaf encryption key the commit's update path
+    /// replaced. A commit that only adds members has no path, and only admins may
+    /// add members, so its committer is the group's other admin if there is just one.
+    fn commit_actor(&self, mdk: &MDK<Storage>, mls_group_id: &mdk_core::GroupId) -> Option<PublicKey> {
+        let own_key = self.keys.public_key();
+        let current = member_leaves(mdk, mls_group_id)?;
+        let leaf_keys = self.leaf_keys.read();
+        let previous = leaf_keys.get(mls_group_id.as_slice())?;
+
+        let mut updated = current
+            .iter()
+            .filter(|(index, (pk, key))| {
+                previous.get(index).is_some_and(|(previous_pk, previous_key)| previous_pk == pk && previous_key != key)
+            })
+            .map(|(_, (pk, _))| *pk);
+        if let Some(committer) = updated.next() {
+            return (updated.next().is_none() && committer != own_key).then_some(committer);
+        }
+
+        let group = mdk.get_group(mls_group_id).ok()??;
+        let mut admins = group.admin_pubkeys.iter().filter(|pk| **pk != own_key);
+        match (admins.next(), admins.next()) {
+            (Some(admin), None) => Some(*admin),
+            _ => None,
+        }
+    }
+
+    /// Get the epoch transitions this client has applied to a group, oldest first.
+    /// The history starts when this client created or joined the group. It is not
+    /// part of the MLS group state, but is exported with it by `export_group_state`
+    /// and `export_all`.
+    pub fn epoch_history(&self, group_id: &[u8]) -> Result<Vec<EpochRecord>, MarmotError> {
+        if self.current_epoch(group_id).is_none() {
+            return Err(MarmotError::GroupNotFound(hex::encode(group_id)));
+        }
+
+        Ok(self.epoch_history.read().get(group_id).cloned().unwrap_or_default())
+    }
+
+    /// Count the commits (including self-updates) this client has applied to a
+    /// group since creating or joining it. Derived from the epoch history, so it
+    /// is exported and restored with the group's state.
+    pub fn rotation_count(&self, group_id: &[u8]) -> Result<u64, MarmotError> {
+        Ok(self.epoch_history(group_id)?.len() as u64)
+    }
+
+    /// Cache a member's display metadata, replacing any earlier entry.
+    /// This is not part of any group state; it only saves the host a separate map.
+    pub fn set_member_metadata(&self, pubkey_hex: &str, name: &str, picture_url: Option<&str>) -> Result<(), MarmotError> {
+        let pubkey = PublicKey::from_hex(pubkey_hex)
+            .map_err(|e| MarmotError::InvalidKey(format!("Invalid public key: {}", e)))?;
+
+        self.member_metadata.write().insert(
+            pubkey.to_hex(),
+            MemberMetadata { name: name.to_string(), picture_url: picture_url.map(str::to_string) },
+        );
+        Ok(())
+    }
+
+    /// Get a member's cached display metadata, or None if none was set.
+    pub fn get_member_metadata(&self, pubkey_hex: &str) -> Result<Option<MemberMetadata>, MarmotError> {
+        let pubkey = PublicKey::from_hex(pubkey_hex)
+            .map_err(|e| MarmotError::InvalidKey(format!("Invalid public key: {}", e)))?;
+
+        Ok(self.member_metadata.read().get(&pubkey.to_hex()).cloned())
+    }
+
+    /// Count this client's groups and their members. Read-only.
+    pub fn stats(&self) -> Result<ClientStats, MarmotError> {
+        let mdk = self.mdk.read();
+        let groups = mdk.get_groups()
+            .map_err(|e| MarmotError::from_mdk("Failed to get groups", e))?;
+
+        let mut stats = ClientStats { group_count: groups.len(), ..Default::default() };
+        for group in &groups {
+            stats.total_members += mdk.get_members(&group.mls_group_id)
+                .map_err(|e| MarmotError::from_mdk("Failed to get members", e))?
+                .len();
+            if load_mls_group(&mdk, group.mls_group_id.as_slice())?.pending_commit().is_some() {
+                stats.pending_commits += 1;
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Get who created a group and when, from its `CREATION_EXTENSION_TYPE`
+    /// extension, which every member can read.
+    pub fn group_creation_info(&self, group_id: &[u8]) -> Result<GroupCreationInfo, MarmotError> {
+        let mdk = self.mdk.read();
+        Ok(creation_info(&load_mls_group(&mdk, group_id)?))
+    }
+
+    /// Get the current epoch of a group, or None if not found.
+    pub fn current_epoch(&self, group_id: &[u8]) -> Option<u64> {
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        let mdk = self.mdk.read();
+        epoch_of(&mdk, &mls_group_id)
+    }
+
+    /// Get the IANA value of a group's MLS ciphersuite, or None if not found.
+    pub fn group_ciphersuite(&self, group_id: &[u8]) -> Option<u16> {
+        let mdk = self.mdk.read();
+        load_mls_group(&mdk, group_id).ok().map(|group| group.ciphersuite() as u16)
+    }
+
+    /// Get the relays configured for a group.
+    /// These come from the group's Nostr group data, so joiners see the creator's relays.
+    pub fn group_relays(&self, group_id: &[u8]) -> Result<Vec<String>, MarmotError> {
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        let mdk = self.mdk.read();
+        mdk.get_group(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get group", e))?
+            .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
+
+        let relays = mdk
+            .get_relays(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get relays", e))?;
+
+        Ok(relays.iter().map(|relay| relay.to_string()).collect())
+    }
+
+    /// Get a group's configuration (name, description, relays and admins) as
+    /// stored in its Nostr group data.
+    pub fn resolved_group_config(&self, group_id: &[u8]) -> Result<GroupConfig, MarmotError> {
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        let mdk = self.mdk.read();
+        let group = mdk.get_group(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get group", e))?
+            .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
+        let relays = mdk.get_relays(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get relays", e))?;
+
+        Ok(GroupConfig {
+            name: group.name,
+            description: group.description,
+            relays: relays.iter().map(|relay| relay.to_string()).collect(),
+            admins: group.admin_pubkeys.iter().map(|pk| pk.to_hex()).collect(),
+        })
+    }
+
+    /// Get the hex public keys of a group's admins, from its Nostr group data.
+    pub fn group_admins(&self, group_id: &[u8]) -> Result<Vec<String>, MarmotError> {
         let mls_group_id = mdk_core::GroupId::from_slice(group_id);
 
         let mdk = self.mdk.read();
         let group = mdk.get_group(&mls_group_id)
-            .map_err(|e| MarmotError::Internal(format!("Failed to get group: {}", e)))?
+            .map_err(|e| MarmotError::from_mdk("Failed to get group", e))?
             .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
 
-        let state = serde_json::to_vec(&group)
-            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize group: {}", e)))?;
+        Ok(group.admin_pubkeys.iter().map(|pk| pk.to_hex()).collect())
+    }
+
+    /// Choose the members JSON version: 1, a flat array of hex public keys (the
+    /// default), or 2, an envelope of member objects with their admin role.
+    pub fn set_members_json_version(&self, version: u32) -> Result<(), MarmotError> {
+        *self.members_json_version.write() = match version {
+            1 => MembersJsonVersion::V1,
+            2 => MembersJsonVersion::V2,
+            _ => return Err(MarmotError::InvalidState(format!("Unsupported members JSON version: {}", version))),
+        };
+        Ok(())
+    }
+
+    /// Serialize a group's members in the configured members JSON version.
+    /// Version 2 marks the group's current admins.
+    pub fn members_json(&self, group_id: &[u8], members: &[String]) -> String {
+        match *self.members_json_version.read() {
+            MembersJsonVersion::V1 => serde_json::to_string(members).unwrap_or_else(|_| "[]".to_string()),
+            MembersJsonVersion::V2 => {
+                let admins = self.group_admins(group_id).unwrap_or_default();
+                let envelope = MembersEnvelope {
+                    v: 2,
+                    members: members
+                        .iter()
+                        .map(|pubkey| MemberEntry { pubkey, admin: admins.contains(pubkey) })
+                        .collect(),
+                };
+                serde_json::to_string(&envelope).unwrap_or_else(|_| r#"{"v":2,"members":[]}"#.to_string())
+            }
+        }
+    }
+
+    /// Replace the relays configured for a group.
+    /// relays_json: JSON array of relay URLs, must not be empty
+    /// Returns JSON-serialized commit event.
+    pub fn set_group_relays(&self, group_id: &[u8], relays_json: &str) -> Result<Vec<u8>, MarmotError> {
+        self.ensure_writable()?;
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        // Parse and validate the relay URLs
+        let urls: Vec<String> = serde_json::from_str(relays_json)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid relays JSON: {}", e)))?;
+        if urls.is_empty() {
+            return Err(MarmotError::InvalidState("Relay list must not be empty".into()));
+        }
+        let relays = urls
+            .iter()
+            .map(|url| {
+                RelayUrl::parse(url)
+                    .map_err(|e| MarmotError::InvalidState(format!("Invalid relay URL '{}': {}", url, e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mdk = self.mdk.write();
+
+        // Rewrite the relay list in the group data extension
+        let update = mdk_core::groups::NostrGroupDataUpdate::new().relays(relays);
+        let result = mdk
+            .update_group_data(&mls_group_id, update)
+            .map_err(|e| MarmotError::from_mdk("Failed to update relays", e))?;
+
+        // Merge the pending commit
+        mdk.merge_pending_commit(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to merge commit", e))?;
+
+        self.publish(&result.evolution_event);
+        self.record_own_commit(&mdk, &mls_group_id, result.evolution_event.id);
 
-        Ok(state)
+        // Serialize the evolution event (its `id` is the commit event ID)
+        let event_json = serde_json::to_vec(&result.evolution_event)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize event: {}", e)))?;
+
+        Ok(event_json)
+    }
+
+    /// Rename a group with a commit that changes only its name.
+    /// Returns JSON-serialized commit event.
+    pub fn rename_group(&self, group_id: &[u8], new_name: &str) -> Result<Vec<u8>, MarmotError> {
+        self.ensure_writable()?;
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        if new_name.trim().is_empty() {
+            return Err(MarmotError::InvalidState("Group name must not be empty".into()));
+        }
+
+        let mdk = self.mdk.write();
+
+        let update = mdk_core::groups::NostrGroupDataUpdate::new().name(new_name.to_string());
+        let result = mdk
+            .update_group_data(&mls_group_id, update)
+            .map_err(|e| MarmotError::from_mdk("Failed to rename group", e))?;
+
+        mdk.merge_pending_commit(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to merge commit", e))?;
+
+        self.publish(&result.evolution_event);
+        self.record_own_commit(&mdk, &mls_group_id, result.evolution_event.id);
+
+        let event_json = serde_json::to_vec(&result.evolution_event)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize event: {}", e)))?;
+
+        Ok(event_json)
+    }
+
+    /// Set a group's description. Only the description in the group data is
+    /// changed; an empty description clears it.
+    /// Returns JSON-serialized commit event.
+    pub fn set_group_description(&self, group_id: &[u8], description: &str) -> Result<Vec<u8>, MarmotError> {
+        self.ensure_writable()?;
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        let mdk = self.mdk.write();
+
+        let update = mdk_core::groups::NostrGroupDataUpdate::new().description(description.to_string());
+        let result = mdk
+            .update_group_data(&mls_group_id, update)
+            .map_err(|e| MarmotError::from_mdk("Failed to set group description", e))?;
+
+        mdk.merge_pending_commit(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to merge commit", e))?;
+
+        self.publish(&result.evolution_event);
+        self.record_own_commit(&mdk, &mls_group_id, result.evolution_event.id);
+
+        let event_json = serde_json::to_vec(&result.evolution_event)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize event: {}", e)))?;
+
+        Ok(event_json)
+    }
+
+    /// Set a group's image. The image is encrypted with a fresh key and nonce,
+    /// which are stored with the blob's hash in the group data via a commit.
+    /// Returns (encrypted_blob, commit_event_json); the host uploads the blob.
+    pub fn set_group_image(&self, group_id: &[u8], image: &[u8]) -> Result<(Vec<u8>, Vec<u8>), MarmotError> {
+        use chacha20poly1305::aead::{Aead, KeyInit};
+        use chacha20poly1305::ChaCha20Poly1305;
+        use nostr::hashes::{sha256, Hash};
+
+        self.ensure_writable()?;
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        let mut key = [0u8; 32];
+        let mut nonce = [0u8; 12];
+        key.copy_from_slice(&random_bytes(32));
+        nonce.copy_from_slice(&random_bytes(12));
+
+        let encrypted = ChaCha20Poly1305::new(&key.into())
+            .encrypt(&nonce.into(), image)
+            .map_err(|e| MarmotError::CryptoError(format!("Failed to encrypt image: {}", e)))?;
+        let hash = sha256::Hash::hash(&encrypted).to_byte_array();
+
+        let mdk = self.mdk.write();
+
+        let update = mdk_core::groups::NostrGroupDataUpdate::new()
+            .image_hash(Some(hash))
+            .image_key(Some(key))
+            .image_nonce(Some(nonce));
+        let result = mdk
+            .update_group_data(&mls_group_id, update)
+            .map_err(|e| MarmotError::from_mdk("Failed to update image", e))?;
+
+        // Merge the pending commit
+        mdk.merge_pending_commit(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to merge commit", e))?;
+
+        self.publish(&result.evolution_event);
+        self.record_own_commit(&mdk, &mls_group_id, result.evolution_event.id);
+
+        // Serialize the evolution event (its `id` is the commit event ID)
+        let event_json = serde_json::to_vec(&result.evolution_event)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize event: {}", e)))?;
+
+        Ok((encrypted, event_json))
     }
 
-    /// Import group state from persistence.
-    pub fn import_group_state(&self, _group_id: &[u8], _state: &[u8]) -> Result<(), MarmotError> {
-        // With memory storage, full import is not supported.
-        // Use the managed (C#) backend for persistent MLS state.
-        Err(MarmotError::Internal("Import not supported with memory storage — use managed backend".into()))
+    /// Get the parameters needed to decrypt a group's image, or None if it has no image.
+    pub fn group_image_key(&self, group_id: &[u8]) -> Result<Option<GroupImageKey>, MarmotError> {
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+
+        let mdk = self.mdk.read();
+        let group = mdk.get_group(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get group", e))?
+            .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
+
+        Ok(match (group.image_hash, group.image_key, group.image_nonce) {
+            (Some(hash), Some(key), Some(nonce)) => Some(GroupImageKey {
+                hash: hex::encode(hash),
+                key: hex::encode(key),
+                nonce: hex::encode(nonce),
+            }),
+            _ => None,
+        })
+    }
+
+    /// Get the MLS epoch authenticator for a group.
+    /// Members at the same epoch get identical bytes, so hosts can compare them
+    /// out-of-band (safety-number style) without revealing any group secret.
+    pub fn epoch_authenticator(&self, group_id: &[u8]) -> Result<Vec<u8>, MarmotError> {
+        let mdk = self.mdk.read();
+        let group = load_mls_group(&mdk, group_id)?;
+
+        Ok(group.epoch_authenticator().as_slice().to_vec())
+    }
+
+    /// Export the group's ratchet tree for debugging and interop testing.
+    /// The output is the standard MLS (RFC 9420) TLS encoding of the ratchet tree.
+    pub fn export_ratchet_tree(&self, group_id: &[u8]) -> Result<Vec<u8>, MarmotError> {
+        use openmls::prelude::tls_codec::Serialize;
+
+        let mdk = self.mdk.read();
+        let group = load_mls_group(&mdk, group_id)?;
+
+        group
+            .export_ratchet_tree()
+            .tls_serialize_detached()
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize ratchet tree: {}", e)))
+    }
+
+    /// Export the group's MLS GroupInfo, including the ratchet tree, as the
+    /// standard TLS-encoded MLSMessage (RFC 9420). Unlike the JSON snapshot from
+    /// `export_group_state`, any MLS implementation can read it, e.g. to join the
+    /// group with an external commit. It holds no secrets.
+    pub fn export_group_info_mls(&self, group_id: &[u8]) -> Result<Vec<u8>, MarmotError> {
+        use openmls::prelude::tls_codec::Serialize;
+        use openmls::prelude::OpenMlsProvider;
+
+        let mdk = self.mdk.read();
+        let group = load_mls_group(&mdk, group_id)?;
+        let signer = load_signer(&mdk, &group)?;
+
+        group
+            .export_group_info(mdk.provider.crypto(), &signer, true)
+            .map_err(|e| MarmotError::MlsError(format!("Failed to export group info: {}", e)))?
+            .tls_serialize_detached()
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize group info: {}", e)))
+    }
+
+    /// Read a custom extension from a group's MLS GroupContext.
+    /// Returns the extension data, or None if the group has no such extension.
+    pub fn group_extension(&self, group_id: &[u8], extension_type: u16) -> Result<Option<Vec<u8>>, MarmotError> {
+        let mdk = self.mdk.read();
+        let group = load_mls_group(&mdk, group_id)?;
+
+        Ok(group.extensions().unknown(extension_type).map(|extension| extension.0.clone()))
+    }
+
+    /// Set a custom extension in a group's MLS GroupContext, for app data such as
+    /// a topic, with a GroupContextExtensions commit. Only `APP_EXTENSION_TYPES`
+    /// may be set; a member whose leaf does not list the type (e.g. one using
+    /// another Marmot client) makes the commit fail.
+    /// Returns the JSON-serialized commit event.
+    pub fn set_group_extension(&self, group_id: &[u8], extension_type: u16, data: &[u8]) -> Result<Vec<u8>, MarmotError> {
+        use nostr::nips::nip44;
+        use openmls::prelude::OpenMlsProvider;
+
+        check_app_extension_type(extension_type)?;
+        if !APP_EXTENSION_TYPES.contains(&extension_type) {
+            return Err(MarmotError::InvalidState(format!(
+                "Extension type 0x{:04x} is outside the app range 0x{:04x}-0x{:04x}",
+                extension_type,
+                APP_EXTENSION_TYPES.start(),
+                APP_EXTENSION_TYPES.end()
+            )));
+        }
+        self.ensure_writable()?;
+
+        let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+        let mdk = self.mdk.write();
+        let stored = mdk.get_group(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get group", e))?
+            .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
+        let commit = commit_group_extension(&mdk, group_id, extension_type, data)?;
+
+        // Wrap the commit the way MDK wraps its own: NIP-44 encrypted with the
+        // current epoch's exporter secret, signed by a one-time key
+        let secret = mdk.provider.storage()
+            .get_group_exporter_secret(&mls_group_id, stored.epoch)
+            .map_err(|e| MarmotError::storage("Failed to load exporter secret", e))?
+            .ok_or_else(|| MarmotError::InvalidState("Exporter secret not found".into()))?;
+        let keys = Keys::new(
+            nostr::SecretKey::from_slice(secret.secret.as_ref())
+                .map_err(|e| MarmotError::CryptoError(format!("Invalid exporter secret: {}", e)))?,
+        );
+        let content = nip44::encrypt(keys.secret_key(), &keys.public_key(), commit, nip44::Version::V2)
+            .map_err(|e| MarmotError::CryptoError(format!("Failed to encrypt commit: {}", e)))?;
+        let event = nostr::EventBuilder::new(nostr::Kind::MlsGroupMessage, content)
+            .tag(nostr::Tag::custom(nostr::TagKind::h(), [hex::encode(stored.nostr_group_id)]))
+            .sign_with_keys(&Keys::generate())
+            .map_err(|e| MarmotError::CryptoError(format!("Failed to sign commit event: {}", e)))?;
+
+        // OpenMLS stored the commit as pending; MDK merges it and advances its own records
+        mdk.merge_pending_commit(&mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to merge commit", e))?;
+
+        self.publish(&event);
+        self.record_own_commit(&mdk, &mls_group_id, event.id);
+
+        serde_json::to_vec(&event)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize event: {}", e)))
+    }
+
+    /// Export a group's complete state for `import_group_state`: MDK's group record
+    /// and relays, and this member's OpenMLS state and keys. The blob holds the
+    /// group's secrets. The exporting client must not send in the group afterwards,
+    /// as it would reuse the importing client's message keys.
+    pub fn export_group_state(&self, group_id: &[u8]) -> Result<zeroize::Zeroizing<Vec<u8>>, MarmotError> {
+        let mdk = self.mdk.read();
+        let snapshot = self.snapshot_group(&mdk, group_id)?;
+
+        serde_json::to_vec(&snapshot)
+            .map(zeroize::Zeroizing::new)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize group: {}", e)))
+    }
+
+    /// Export every active group as a single backup blob: a format version byte
+    /// followed by a JSON object holding each group's complete state, as
+    /// `export_group_state` produces it. Inactive groups have no MLS state left
+    /// to back up and are skipped.
+    pub fn export_all(&self) -> Result<zeroize::Zeroizing<Vec<u8>>, MarmotError> {
+        use mdk_storage_traits::groups::types::GroupState;
+
+        let mdk = self.mdk.read();
+        let groups = mdk.get_groups()
+            .map_err(|e| MarmotError::from_mdk("Failed to get groups", e))?
+            .into_iter()
+            .filter(|group| matches!(group.state, GroupState::Active))
+            .map(|group| self.snapshot_group(&mdk, group.mls_group_id.as_slice()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let groups = serde_json::to_vec(&serde_json::json!({ "groups": groups }))
+            .map(zeroize::Zeroizing::new)
+            .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize groups: {}", e)))?;
+
+        // Sized up front, so the blob is not left behind in reallocated buffers
+        let mut backup = zeroize::Zeroizing::new(Vec::with_capacity(groups.len() + 1));
+        backup.push(BACKUP_FORMAT_VERSION);
+        backup.extend_from_slice(&groups);
+        Ok(backup)
+    }
+
+    /// Restore the groups in a blob produced by `export_all` on a client of the
+    /// same identity, skipping groups that already exist. Returns the number of
+    /// groups restored. Fails if the groups belong to another identity.
+    pub fn import_all(&self, backup: &[u8]) -> Result<usize, MarmotError> {
+        self.ensure_writable()?;
+        let (version, body) = backup.split_first()
+            .ok_or_else(|| MarmotError::SerializationError("Empty backup".to_string()))?;
+        if *version != BACKUP_FORMAT_VERSION {
+            return Err(MarmotError::SerializationError(format!("Unsupported backup format version: {}", version)));
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Backup {
+            groups: Vec<group_state::GroupSnapshot>,
+        }
+        let backup: Backup = serde_json::from_slice(body)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid backup: {}", e)))?;
+
+        let mdk = self.mdk.write();
+        let mut restored = 0;
+        for snapshot in backup.groups {
+            let existing = mdk.get_group(&snapshot.group.mls_group_id)
+                .map_err(|e| MarmotError::from_mdk("Failed to get group", e))?;
+            if existing.is_some() {
+                continue;
+            }
+            self.restore_group(&mdk, snapshot)?;
+            restored += 1;
+        }
+
+        Ok(restored)
+    }
+
+    /// Export this client's Nostr identity and groups, with their MLS state as
+    /// `export_all` produces it, encrypted with a key derived from `passphrase`
+    /// (Argon2id, then ChaCha20-Poly1305), for moving to another device.
+    pub fn export_identity_backup(&self, passphrase: &str) -> Result<Vec<u8>, MarmotError> {
+        use base64::Engine;
+        use chacha20poly1305::aead::{Aead, KeyInit};
+        use chacha20poly1305::ChaCha20Poly1305;
+
+        #[derive(serde::Serialize)]
+        struct IdentityBackup<'a> {
+            secret_key: &'a str,
+            groups: &'a str,
+        }
+
+        let secret_key = zeroize::Zeroizing::new(self.keys.secret_key().to_secret_hex());
+        let groups = self.export_all()?;
+        let groups = zeroize::Zeroizing::new(base64::engine::general_purpose::STANDARD.encode(groups.as_slice()));
+        let plaintext = zeroize::Zeroizing::new(serde_json::to_vec(&IdentityBackup {
+            secret_key: &secret_key,
+            groups: &groups,
+        })?);
+
+        let salt = random_bytes(IDENTITY_BACKUP_SALT_LEN);
+        let nonce = random_bytes(IDENTITY_BACKUP_NONCE_LEN);
+        let key = identity_backup_key(passphrase, &salt)?;
+
+        let ciphertext = ChaCha20Poly1305::new(&(*key).into())
+            .encrypt(nonce.as_slice().into(), plaintext.as_slice())
+            .map_err(|e| MarmotError::CryptoError(format!("Failed to encrypt backup: {}", e)))?;
+
+        let mut backup = vec![IDENTITY_BACKUP_FORMAT_VERSION];
+        backup.extend_from_slice(&salt);
+        backup.extend_from_slice(&nonce);
+        backup.extend_from_slice(&ciphertext);
+        Ok(backup)
+    }
+
+    /// Create a client from a backup made with `export_identity_backup`.
+    /// A wrong passphrase fails with `CryptoError`.
+    pub fn import_identity_backup(backup: &[u8], passphrase: &str) -> Result<Self, MarmotError> {
+        use base64::Engine;
+        use chacha20poly1305::aead::{Aead, KeyInit};
+        use chacha20poly1305::ChaCha20Poly1305;
+
+        let (version, body) = backup.split_first()
+            .ok_or_else(|| MarmotError::SerializationError("Empty backup".to_string()))?;
+        if *version != IDENTITY_BACKUP_FORMAT_VERSION {
+            return Err(MarmotError::SerializationError(format!("Unsupported backup format version: {}", version)));
+        }
+        if body.len() < IDENTITY_BACKUP_SALT_LEN + IDENTITY_BACKUP_NONCE_LEN {
+            return Err(MarmotError::SerializationError("Truncated backup".to_string()));
+        }
+        let (salt, body) = body.split_at(IDENTITY_BACKUP_SALT_LEN);
+        let (nonce, ciphertext) = body.split_at(IDENTITY_BACKUP_NONCE_LEN);
+
+        let key = identity_backup_key(passphrase, salt)?;
+        let plaintext = ChaCha20Poly1305::new(&(*key).into())
+            .decrypt(nonce.into(), ciphertext)
+            .map(zeroize::Zeroizing::new)
+            .map_err(|_| MarmotError::CryptoError("Wrong passphrase or corrupted backup".into()))?;
+
+        #[derive(serde::Deserialize)]
+        struct IdentityBackup {
+            secret_key: String,
+            groups: String,
+        }
+        let backup: IdentityBackup = serde_json::from_slice(&plaintext)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid backup: {}", e)))?;
+        let secret_key = zeroize::Zeroizing::new(backup.secret_key);
+        let encoded_groups = zeroize::Zeroizing::new(backup.groups);
+        let groups = base64::engine::general_purpose::STANDARD
+            .decode(encoded_groups.as_str())
+            .map(zeroize::Zeroizing::new)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid backup: {}", e)))?;
+
+        let client = Self::build(&secret_key, "", None, None)?;
+        client.import_all(&groups)?;
+        Ok(client)
+    }
+
+    /// Drop this client's state for a group whose epoch has diverged, so the host
+    /// can re-join it from a fresh welcome. Other groups are unaffected.
+    /// MDK's storage cannot delete a group record, so the record is kept but marked
+    /// inactive; the MLS state is deleted, so the group is unusable until re-joined.
+    /// Storage failures are retried: a retry skips MLS state already deleted.
+    pub fn reset_group(&self, group_id: &[u8]) -> Result<(), MarmotError> {
+        self.ensure_writable()?;
+        self.with_storage_retry(|mdk| deactivate_group(mdk, group_id))?;
+        self.forget_group(group_id);
+        Ok(())
+    }
+
+    /// Drop this client's in-memory records of a group.
+    fn forget_group(&self, group_id: &[u8]) {
+        self.roster_history.write().remove(group_id);
+        self.epoch_history.write().remove(group_id);
+        self.leaf_keys.write().remove(group_id);
+        self.applied_commits.lock().remove(group_id);
+        self.rekey_policies.lock().remove(group_id);
+    }
+
+    /// Import a group's state produced by `export_group_state`, e.g. to hand a
+    /// group over to another client of the same identity in the same session.
+    /// Fails if the group already exists or the state belongs to another identity.
+    pub fn import_group_state(&self, group_id: &[u8], state: &[u8]) -> Result<(), MarmotError> {
+        self.ensure_writable()?;
+        let snapshot: group_state::GroupSnapshot = serde_json::from_slice(state)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid group state: {}", e)))?;
+        if snapshot.group.mls_group_id.as_slice() != group_id {
+            return Err(MarmotError::InvalidState("group id mismatch".into()));
+        }
+
+        let mdk = self.mdk.write();
+        let existing = mdk.get_group(&snapshot.group.mls_group_id)
+            .map_err(|e| MarmotError::from_mdk("Failed to get group", e))?;
+        if existing.is_some() {
+            return Err(MarmotError::InvalidState(format!("Group {} already exists", hex::encode(group_id))));
+        }
+
+        self.restore_group(&mdk, snapshot)
+    }
+
+    /// Read a group's complete state, with this client's epoch history of it.
+    fn snapshot_group(&self, mdk: &MDK<Storage>, group_id: &[u8]) -> Result<group_state::GroupSnapshot, MarmotError> {
+        let epoch_history = self.epoch_history.read().get(group_id).cloned().unwrap_or_default();
+        group_state::export_group(mdk, group_id, epoch_history)
+    }
+
+    /// Write a group's complete state from `snapshot_group` and take over its history.
+    fn restore_group(&self, mdk: &MDK<Storage>, mut snapshot: group_state::GroupSnapshot) -> Result<(), MarmotError> {
+        let mls_group_id = snapshot.group.mls_group_id.clone();
+        let epoch_history = std::mem::take(&mut snapshot.epoch_history);
+        group_state::import_group(mdk, &self.keys.public_key(), snapshot)?;
+
+        self.epoch_history.write().insert(mls_group_id.as_slice().to_vec(), epoch_history);
+        self.record_roster(mdk, &mls_group_id);
+        Ok(())
+    }
+}
+
+/// Parse the host's welcome input: a JSON object with wrapper_event_id and rumor_event.
+fn parse_welcome_input(welcome_data: &[u8]) -> Result<(EventId, UnsignedEvent), MarmotError> {
+    #[derive(serde::Deserialize)]
+    struct WelcomeInput {
+        wrapper_event_id: String,
+        rumor_event: serde_json::Value,
+    }
+
+    let welcome_json = std::str::from_utf8(welcome_data)
+        .map_err(|e| MarmotError::SerializationError(format!("Invalid UTF-8: {}", e)))?;
+    let input: WelcomeInput = serde_json::from_str(welcome_json)
+        .map_err(|e| MarmotError::SerializationError(format!("Invalid welcome JSON: {}", e)))?;
+
+    let event_id = EventId::from_hex(&input.wrapper_event_id)
+        .map_err(|e| MarmotError::SerializationError(format!("Invalid event ID: {}", e)))?;
+    let rumor: UnsignedEvent = serde_json::from_value(input.rumor_event)
+        .map_err(|e| MarmotError::SerializationError(format!("Invalid rumor event: {}", e)))?;
+
+    Ok((event_id, rumor))
+}
+
+/// Wrap a bare KeyPackage in the key package event MDK's add path requires.
+/// MDK only adds members from events and has no path for a bare KeyPackage.
+///
+/// The result is NOT a signed event: its signature is all zeros and its
+/// `created_at` is the time of the call, not of publication. It must only be
+/// handed straight to MDK, which reads the KeyPackage from it and checks the
+/// credential against `owner`; the KeyPackage itself carries its owner's signature.
+fn unsigned_key_package_event(owner: PublicKey, content_b64: &str, tags: Vec<nostr::Tag>) -> Result<Event, MarmotError> {
+    let created_at = nostr::Timestamp::now();
+    let kind = nostr::Kind::Custom(30443);
+    let event_id = EventId::new(&owner, &created_at, &kind, &tags, content_b64);
+    let placeholder_signature = nostr::secp256k1::schnorr::Signature::from_slice(&[0u8; 64])
+        .map_err(|e| MarmotError::CryptoError(e.to_string()))?;
+    Ok(Event::new(event_id, owner, created_at, kind, tags, content_b64, placeholder_signature))
+}
+
+/// Serialize the welcome rumors of an add. An add that produced no welcome is an
+/// error, as the invitee could never join.
+fn welcome_json(rumors: Option<&[UnsignedEvent]>) -> Result<serde_json::Value, MarmotError> {
+    match rumors {
+        Some(rumors) if !rumors.is_empty() => Ok(serde_json::to_value(rumors)?),
+        _ => Err(MarmotError::Internal("no welcome produced".into())),
+    }
+}
+
+/// NIP-59 gift wrap `rumor` from `sender` to `receiver`: seal it with the sender's
+/// key, then wrap the seal with a one-time key.
+fn gift_wrap(sender: &Keys, receiver: &PublicKey, rumor: &UnsignedEvent, randomize_timestamp: bool) -> Result<Event, MarmotError> {
+    use nostr::nips::nip44::{self, Version};
+
+    if rumor.pubkey != sender.public_key() {
+        return Err(MarmotError::InvalidState("Rumor author does not match the sender".into()));
+    }
+
+    let created_at = || {
+        let now = nostr::Timestamp::now();
+        if !randomize_timestamp {
+            return now;
+        }
+        let random: [u8; 8] = random_bytes(8).try_into().unwrap_or_default();
+        let tweak = u64::from_le_bytes(random) % (GIFT_WRAP_MAX_TIMESTAMP_TWEAK + 1);
+        nostr::Timestamp::from(now.as_u64().saturating_sub(tweak))
+    };
+
+    let rumor_json = serde_json::to_string(rumor)
+        .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize rumor: {}", e)))?;
+    let sealed = nip44::encrypt(sender.secret_key(), receiver, rumor_json, Version::V2)
+        .map_err(|e| MarmotError::CryptoError(format!("Failed to seal rumor: {}", e)))?;
+    let seal = nostr::EventBuilder::new(nostr::Kind::Seal, sealed)
+        .custom_created_at(created_at())
+        .sign_with_keys(sender)
+        .map_err(|e| MarmotError::CryptoError(format!("Failed to sign seal: {}", e)))?;
+
+    let ephemeral = Keys::generate();
+    let seal_json = serde_json::to_string(&seal)
+        .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize seal: {}", e)))?;
+    let wrapped = nip44::encrypt(ephemeral.secret_key(), receiver, seal_json, Version::V2)
+        .map_err(|e| MarmotError::CryptoError(format!("Failed to wrap seal: {}", e)))?;
+    nostr::EventBuilder::new(nostr::Kind::GiftWrap, wrapped)
+        .tag(nostr::Tag::public_key(*receiver))
+        .custom_created_at(created_at())
+        .sign_with_keys(&ephemeral)
+        .map_err(|e| MarmotError::CryptoError(format!("Failed to sign gift wrap: {}", e)))
+}
+
+/// Unwrap a NIP-59 gift wrap addressed to `keys`, returning the rumor inside.
+/// The seal's signature is verified and must come from the rumor's author.
+fn unwrap_gift_wrap(keys: &Keys, gift_wrap: &Event) -> Result<UnsignedEvent, MarmotError> {
+    use nostr::nips::nip44;
+
+    if gift_wrap.kind != nostr::Kind::GiftWrap {
+        return Err(MarmotError::InvalidState(format!("Expected a gift wrap, got kind {}", gift_wrap.kind)));
+    }
+
+    let seal_json = nip44::decrypt(keys.secret_key(), &gift_wrap.pubkey, &gift_wrap.content)
+        .map_err(|e| MarmotError::CryptoError(format!("Failed to unwrap gift wrap: {}", e)))?;
+    let seal: Event = serde_json::from_str(&seal_json)
+        .map_err(|e| MarmotError::SerializationError(format!("Invalid seal: {}", e)))?;
+    if seal.kind != nostr::Kind::Seal {
+        return Err(MarmotError::InvalidState(format!("Expected a seal, got kind {}", seal.kind)));
+    }
+    seal.verify()
+        .map_err(|e| MarmotError::CryptoError(format!("Invalid seal signature: {}", e)))?;
+
+    let rumor_json = nip44::decrypt(keys.secret_key(), &seal.pubkey, &seal.content)
+        .map_err(|e| MarmotError::CryptoError(format!("Failed to open seal: {}", e)))?;
+    let rumor: UnsignedEvent = serde_json::from_str(&rumor_json)
+        .map_err(|e| MarmotError::SerializationError(format!("Invalid rumor event: {}", e)))?;
+    if rumor.pubkey != seal.pubkey {
+        return Err(MarmotError::CryptoError("Rumor author does not match the seal signer".into()));
+    }
+
+    Ok(rumor)
+}
+
+/// Look up a welcome processed by `preview_welcome`.
+fn pending_welcome(
+    mdk: &MDK<Storage>,
+    welcome_id: &str,
+) -> Result<mdk_core::prelude::welcome_types::Welcome, MarmotError> {
+    let event_id = EventId::from_hex(welcome_id)
+        .map_err(|e| MarmotError::InvalidState(format!("Invalid welcome ID: {}", e)))?;
+
+    mdk.get_welcome(&event_id)
+        .map_err(|e| MarmotError::from_mdk("Failed to get welcome", e))?
+        .ok_or_else(|| MarmotError::WelcomeNotFound(welcome_id.to_string()))
+}
+
+/// Read the references of the key packages a welcome rumor's MLS welcome is
+/// encrypted to. The content is base64 when the rumor carries an encoding tag,
+/// hex otherwise.
+fn welcome_key_package_refs(rumor: &UnsignedEvent) -> Result<Vec<openmls::prelude::KeyPackageRef>, MarmotError> {
+    use base64::Engine;
+    use openmls::prelude::tls_codec::Deserialize;
+    use openmls::prelude::{MlsMessageBodyIn, MlsMessageIn};
+
+    let base64 = rumor.tags.iter().any(|tag| tag.as_slice() == BINARY_TAG);
+    let bytes = if base64 {
+        base64::engine::general_purpose::STANDARD.decode(&rumor.content)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid welcome encoding: {}", e)))?
+    } else {
+        hex::decode(&rumor.content)
+            .map_err(|e| MarmotError::SerializationError(format!("Invalid welcome encoding: {}", e)))?
+    };
+    let message = MlsMessageIn::tls_deserialize_exact(&bytes)
+        .map_err(|e| MarmotError::MlsError(format!("Invalid welcome: {}", e)))?;
+    match message.extract() {
+        MlsMessageBodyIn::Welcome(welcome) => {
+            Ok(welcome.secrets().iter().map(|secrets| secrets.new_member()).collect())
+        }
+        _ => Err(MarmotError::MlsError("Welcome rumor does not hold an MLS welcome".into())),
+    }
+}
+
+/// Build the kind 30443 tags MIP-00 requires for a KeyPackage event, as MDK does
+/// for the key packages it creates: a fresh `d` slot, the protocol version,
+/// ciphersuite and extensions, the content encoding, the KeyPackage reference
+/// and the relays it is published to.
+fn key_package_tags(
+    mdk: &MDK<Storage>,
+    key_package: &openmls::prelude::KeyPackage,
+    relays: Vec<RelayUrl>,
+) -> Result<Vec<nostr::Tag>, MarmotError> {
+    use openmls::prelude::OpenMlsProvider;
+
+    let hash_ref = key_package
+        .hash_ref(mdk.provider.crypto())
+        .map_err(|e| MarmotError::MlsError(format!("Failed to hash key package: {}", e)))?;
+
+    let mut extensions = vec!["mls_extensions".to_string()];
+    extensions.extend(mdk.extensions.iter().map(|extension| format!("0x{:04x}", u16::from(*extension))));
+    let mut relay_tag = vec!["relays".to_string()];
+    relay_tag.extend(relays.iter().map(|relay| relay.to_string()));
+
+    let tags = vec![
+        vec!["d".to_string(), hex::encode(random_bytes(32))],
+        vec!["mls_protocol_version".to_string(), "1.0".to_string()],
+        vec!["mls_ciphersuite".to_string(), format!("0x{:04x}", key_package.ciphersuite() as u16)],
+        extensions,
+        BINARY_TAG.iter().map(|value| value.to_string()).collect(),
+        vec!["i".to_string(), hex::encode(hash_ref.as_slice())],
+        relay_tag,
+    ];
+    tags.into_iter()
+        .map(|tag| nostr::Tag::parse(tag).map_err(|e| MarmotError::SerializationError(format!("Invalid tag: {}", e))))
+        .collect()
+}
+
+/// Serialize a KeyPackage and its kind 30443 tags (addressable events, current MIP-00 spec)
+/// as JSON with { "content": "<base64>", "tags": [[...], ...] }
+fn key_package_result(content: String, tags: Vec<nostr::Tag>) -> Result<zeroize::Zeroizing<Vec<u8>>, MarmotError> {
+    #[derive(serde::Serialize)]
+    struct KeyPackageResult {
+        content: String,
+        tags: Vec<Vec<String>>,
+    }
+
+    let result = KeyPackageResult {
+        content,
+        tags: tags.into_iter().map(|tag| tag.to_vec()).collect(),
+    };
+
+    serde_json::to_vec(&result)
+        .map(zeroize::Zeroizing::new)
+        .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize: {}", e)))
+}
+
+/// List the MLS ciphersuites supported by MDK's crypto provider in this build.
+pub fn supported_ciphersuites() -> Vec<CiphersuiteInfo> {
+    use openmls::prelude::{OpenMlsCrypto, OpenMlsProvider};
+
+    let mdk = MDK::builder(MdkMemoryStorage::new()).build();
+    mdk.provider
+        .crypto()
+        .supported_ciphersuites()
+        .into_iter()
+        .map(|cs| CiphersuiteInfo { code: cs as u16, name: format!("{:?}", cs) })
+        .collect()
+}
+
+/// Self-test the library with a full local round trip between two throwaway
+/// clients: key package, group creation, add member, welcome, encrypt and decrypt.
+/// The error names the step that failed and keeps that step's error code.
+pub fn health_check() -> Result<(), MarmotError> {
+    const PLAINTEXT: &str = "health check";
+
+    fn step<T>(name: &str, result: Result<T, MarmotError>) -> Result<T, MarmotError> {
+        result.map_err(|e| e.context(&format!("Health check failed to {}", name)))
+    }
+
+    #[derive(serde::Deserialize)]
+    struct KeyPackageResult {
+        content: String,
+        tags: serde_json::Value,
+    }
+
+    let new_client = || {
+        let keys = Keys::generate();
+        MarmotClient::new(&keys.secret_key().to_secret_hex(), &keys.public_key().to_hex(), None)
+    };
+    let alice = step("create a client", new_client())?;
+    let bob = step("create a client", new_client())?;
+
+    let key_package = step("generate a key package", bob.generate_key_package())?;
+    let key_package: KeyPackageResult = step("parse the key package", serde_json::from_slice(&key_package).map_err(Into::into))?;
+
+    let (group_id, _) = step("create a group", alice.create_group("health check"))?;
+    let added = step(
+        "add a member",
+        alice.add_member_from_key_package(&group_id, &key_package.content, &key_package.tags.to_string()),
+    )?;
+    let added: serde_json::Value = step("parse the welcome", serde_json::from_slice(&added).map_err(Into::into))?;
+    let welcome = serde_json::json!({
+        "wrapper_event_id": EventId::all_zeros().to_hex(),
+        "rumor_event": added["welcome"][0],
+    });
+    step("process the welcome", bob.process_welcome(welcome.to_string().as_bytes()))?;
+
+    let event = step("encrypt a message", alice.encrypt_message(&group_id, PLAINTEXT))?;
+    let (_, plaintext, _, _) = step("decrypt a message", bob.decrypt_message(&group_id, &event))?;
+    if plaintext != PLAINTEXT {
+        return Err(MarmotError::Internal("Health check decrypted the wrong plaintext".into()));
+    }
+
+    Ok(())
+}
+
+/// Check a Nostr event's id and signature.
+/// Returns false for a well-formed event that fails verification.
+pub fn verify_event(event_json: &str) -> Result<bool, MarmotError> {
+    let event: Event = serde_json::from_str(event_json)
+        .map_err(|e| MarmotError::SerializationError(format!("Invalid event: {}", e)))?;
+    Ok(event.verify().is_ok())
+}
+
+/// Decrypt a downloaded group image blob, checking it against the stored hash.
+pub fn decrypt_group_image(encrypted: &[u8], image_key: &GroupImageKey) -> Result<Vec<u8>, MarmotError> {
+    use chacha20poly1305::aead::{Aead, KeyInit};
+    use chacha20poly1305::ChaCha20Poly1305;
+    use nostr::hashes::{sha256, Hash};
+
+    if hex::encode(sha256::Hash::hash(encrypted).to_byte_array()) != image_key.hash {
+        return Err(MarmotError::CryptoError("Image does not match its hash".into()));
+    }
+
+    let key: [u8; 32] = hex::decode(&image_key.key)?
+        .try_into()
+        .map_err(|_| MarmotError::InvalidKey("Image key must be 32 bytes".into()))?;
+    let nonce: [u8; 12] = hex::decode(&image_key.nonce)?
+        .try_into()
+        .map_err(|_| MarmotError::InvalidKey("Image nonce must be 12 bytes".into()))?;
+
+    ChaCha20Poly1305::new(&key.into())
+        .decrypt(&nonce.into(), encrypted)
+        .map_err(|e| MarmotError::CryptoError(format!("Failed to decrypt image: {}", e)))
+}
+
+/// Derive the key protecting an identity backup from its passphrase.
+fn identity_backup_key(passphrase: &str, salt: &[u8]) -> Result<zeroize::Zeroizing<[u8; 32]>, MarmotError> {
+    let mut key = zeroize::Zeroizing::new([0u8; 32]);
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| MarmotError::CryptoError(format!("Failed to derive backup key: {}", e)))?;
+    Ok(key)
+}
+
+/// Generate random bytes, from the seeded test RNG when the `test-rng` feature is enabled.
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+
+    #[cfg(feature = "test-rng")]
+    crate::test_rng::fill_bytes(&mut bytes);
+
+    #[cfg(not(feature = "test-rng"))]
+    {
+        use rand::RngCore;
+        rand::rngs::OsRng.fill_bytes(&mut bytes);
+    }
+
+    bytes
+}
+
+/// Read a group's current epoch from MDK, or None if the group is not found.
+fn epoch_of(mdk: &MDK<Storage>, mls_group_id: &mdk_core::GroupId) -> Option<u64> {
+    mdk.get_group(mls_group_id).ok()?.map(|group| group.epoch)
+}
+
+/// Each member's public key and leaf encryption key, keyed by leaf index.
+/// None if the group's MLS state cannot be loaded.
+fn member_leaves(
+    mdk: &MDK<Storage>,
+    mls_group_id: &mdk_core::GroupId,
+) -> Option<HashMap<u32, (PublicKey, Vec<u8>)>> {
+    use openmls::prelude::BasicCredential;
+
+    let group = load_mls_group(mdk, mls_group_id.as_slice()).ok()?;
+    Some(
+        group
+            .members()
+            .filter_map(|member| {
+                let credential = BasicCredential::try_from(member.credential).ok()?;
+                let pk = PublicKey::from_slice(credential.identity()).ok()?;
+                Some((member.index.u32(), (pk, member.encryption_key)))
+            })
+            .collect(),
+    )
+}
+
+/// Read the MLS epoch of a group event without processing it. Group events are
+/// NIP-44 encrypted with their epoch's exporter secret, which MDK stores, and
+/// the MLS message inside carries its epoch in the clear. Only the current
+/// epoch and the `max_age` before it are tried.
+/// None if the event was not encrypted for one of those epochs, e.g. because it
+/// belongs to an epoch the group has not reached.
+fn event_epoch(mdk: &MDK<Storage>, mls_group_id: &mdk_core::GroupId, event: &Event, max_age: u64) -> Option<u64> {
+    use nostr::nips::nip44;
+    use openmls::prelude::tls_codec::Deserialize;
+    use openmls::prelude::{MlsMessageIn, OpenMlsProvider};
+
+    let current = epoch_of(mdk, mls_group_id)?;
+    let oldest = current.saturating_sub(max_age);
+    (oldest..=current).rev().find_map(|epoch| {
+        let secret = mdk.provider.storage().get_group_exporter_secret(mls_group_id, epoch).ok()??;
+        let keys = Keys::new(nostr::SecretKey::from_slice(secret.secret.as_ref()).ok()?);
+        let message = nip44::decrypt_to_bytes(keys.secret_key(), &keys.public_key(), &event.content).ok()?;
+        let message = MlsMessageIn::tls_deserialize_exact(&message)
+            .ok()?
+            .try_into_protocol_message()
+            .ok()?;
+        Some(message.epoch().as_u64())
+    })
+}
+
+/// Load the OpenMLS group backing an MDK group from MDK's storage.
+pub(crate) fn load_mls_group(
+    mdk: &MDK<Storage>,
+    group_id: &[u8],
+) -> Result<openmls::group::MlsGroup, MarmotError> {
+    use openmls::prelude::OpenMlsProvider;
+
+    let mls_group_id = openmls::group::GroupId::from_slice(group_id);
+    openmls::group::MlsGroup::load(mdk.provider.storage(), &mls_group_id)
+        .map_err(|e| MarmotError::storage("Failed to load MLS group", e))?
+        .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))
+}
+
+/// Delete a group's MLS state and mark MDK's record of it inactive, as MDK's
+/// storage cannot delete the record. MLS state already deleted is skipped.
+fn deactivate_group(mdk: &MDK<Storage>, group_id: &[u8]) -> Result<(), MarmotError> {
+    use mdk_storage_traits::groups::types::GroupState;
+    use openmls::prelude::OpenMlsProvider;
+
+    let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+    let mut group = mdk.get_group(&mls_group_id)
+        .map_err(|e| MarmotError::from_mdk("Failed to get group", e))?
+        .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
+
+    match load_mls_group(mdk, group_id) {
+        Ok(mut mls_group) => mls_group.delete(mdk.provider.storage())
+            .map_err(|e| MarmotError::storage("Failed to delete group state", e))?,
+        Err(MarmotError::GroupNotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    group.state = GroupState::Inactive;
+    mdk.provider.storage().save_group(group)
+        .map_err(|e| MarmotError::storage("Failed to save group", e))
+}
+
+/// Create a GroupContextExtensions commit setting one extension, replacing it
+/// if the group already carries it. OpenMLS stores the commit as pending for
+/// the caller to merge.
+/// Returns the TLS-serialized commit.
+fn commit_group_extension(
+    mdk: &MDK<Storage>,
+    group_id: &[u8],
+    extension_type: u16,
+    data: &[u8],
+) -> Result<Vec<u8>, MarmotError> {
+    use openmls::prelude::tls_codec::Serialize;
+    use openmls::prelude::{Extension, Extensions, UnknownExtension};
+
+    let mut group = load_mls_group(mdk, group_id)?;
+    let signer = load_signer(mdk, &group)?;
+
+    let mut extensions: Vec<Extension> = group
+        .extensions()
+        .iter()
+        .filter(|extension| u16::from(extension.extension_type()) != extension_type)
+        .cloned()
+        .collect();
+    extensions.push(Extension::Unknown(extension_type, UnknownExtension(data.to_vec())));
+    let extensions = Extensions::from_vec(extensions)
+        .map_err(|e| MarmotError::MlsError(format!("Invalid group extensions: {}", e)))?;
+
+    let (commit, _, _) = group
+        .update_group_context_extensions(&mdk.provider, extensions, &signer)
+        .map_err(|e| MarmotError::MlsError(format!("Failed to update group extensions: {}", e)))?;
+    commit
+        .tls_serialize_detached()
+        .map_err(|e| MarmotError::SerializationError(format!("Failed to serialize commit: {}", e)))
+}
+
+/// Read a group's `GroupCreationInfo` from its `CREATION_EXTENSION_TYPE`
+/// extension. A group without a readable one has unknown creation info.
+fn creation_info(group: &openmls::group::MlsGroup) -> GroupCreationInfo {
+    group
+        .extensions()
+        .unknown(CREATION_EXTENSION_TYPE)
+        .and_then(|extension| serde_json::from_slice(&extension.0).ok())
+        .unwrap_or_default()
+}
+
+/// Decode and validate a base64 key package, as found in a key package event's
+/// content. None if it is not a valid key package.
+fn decode_key_package(mdk: &MDK<Storage>, content_b64: &str) -> Option<openmls::prelude::KeyPackage> {
+    use base64::Engine;
+    use openmls::prelude::tls_codec::Deserialize;
+    use openmls::prelude::{KeyPackageIn, OpenMlsProvider, ProtocolVersion};
+
+    let kp_bytes = base64::engine::general_purpose::STANDARD.decode(content_b64).ok()?;
+    KeyPackageIn::tls_deserialize_exact(&kp_bytes)
+        .ok()?
+        .validate(mdk.provider.crypto(), ProtocolVersion::Mls10)
+        .ok()
+}
+
+/// Read the IANA ciphersuite of a base64 key package. None if it is not a valid key package.
+fn key_package_ciphersuite(mdk: &MDK<Storage>, content_b64: &str) -> Option<u16> {
+    decode_key_package(mdk, content_b64).map(|key_package| key_package.ciphersuite() as u16)
+}
+
+/// Load this client's signature key pair for an OpenMLS group from MDK's storage.
+pub(crate) fn load_signer(
+    mdk: &MDK<Storage>,
+    group: &openmls::group::MlsGroup,
+) -> Result<openmls_basic_credential::SignatureKeyPair, MarmotError> {
+    use openmls::prelude::OpenMlsProvider;
+
+    let own_leaf = group
+        .own_leaf_node()
+        .ok_or(MarmotError::NotMember)?;
+    openmls_basic_credential::SignatureKeyPair::read(
+        mdk.provider.storage(),
+        own_leaf.signature_key().as_slice(),
+        group.ciphersuite().signature_algorithm(),
+    )
+    .ok_or_else(|| MarmotError::InvalidState("Signature key not found".into()))
+}
+
+/// Reject extension types an app may not set: those defined by MLS (including
+/// reserved and GREASE values) and Marmot's Nostr group data extension.
+fn check_app_extension_type(extension_type: u16) -> Result<(), MarmotError> {
+    use openmls::prelude::ExtensionType;
+
+    let grease = extension_type & 0x0F0F == 0x0A0A && extension_type >> 12 == (extension_type >> 4) & 0x0F;
+    let reserved = extension_type == 0
+        || grease
+        || extension_type == NOSTR_GROUP_DATA_EXTENSION_TYPE
+        || !matches!(ExtensionType::from(extension_type), ExtensionType::Unknown(_));
+    if reserved {
+        return Err(MarmotError::InvalidState(format!("Reserved extension type 0x{:04x}", extension_type)));
+    }
+    Ok(())
+}
+
+/// Whether MDK failed to process a message because it was sent in an epoch
+/// whose secrets are no longer held.
+fn is_epoch_too_old(err: &mdk_core::Error) -> bool {
+    matches!(err, mdk_core::Error::ProcessMessageWrongEpoch { .. })
+}
+
+/// Find which of this client's groups an incoming group event belongs to, from
+/// its `h` tag (the group's Nostr group ID).
+fn event_group_id(mdk: &MDK<Storage>, event: &Event) -> Result<Vec<u8>, MarmotError> {
+    let nostr_group_id = event
+        .tags
+        .iter()
+        .find(|tag| tag.as_slice().first().map(String::as_str) == Some("h"))
+        .and_then(|tag| tag.as_slice().get(1))
+        .and_then(|id| hex::decode(id).ok())
+        .ok_or_else(|| MarmotError::InvalidState("Group event has no valid h tag".into()))?;
+
+    let groups = mdk.get_groups()
+        .map_err(|e| MarmotError::from_mdk("Failed to get groups", e))?;
+    groups
+        .into_iter()
+        .find(|group| group.nostr_group_id.as_slice() == nostr_group_id.as_slice())
+        .map(|group| group.mls_group_id.as_slice().to_vec())
+        .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(&nostr_group_id)))
+}
+
+/// Whether MDK has already processed an event, from its record of processed
+/// events. Events this client created itself are not counted.
+fn already_processed(mdk: &MDK<Storage>, event_id: &EventId) -> Result<bool, MarmotError> {
+    use mdk_storage_traits::messages::types::ProcessedMessageState;
+    use mdk_storage_traits::messages::MessageStorage;
+    use openmls::prelude::OpenMlsProvider;
+
+    let processed = mdk.provider.storage()
+        .find_processed_message_by_event_id(event_id)
+        .map_err(|e| MarmotError::storage("Failed to read processed events", e))?;
+    Ok(matches!(
+        processed.map(|message| message.state),
+        Some(ProcessedMessageState::Processed | ProcessedMessageState::ProcessedCommit)
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use nostr::{EventBuilder, Kind, Tag};
+
+    pub(crate) fn new_client() -> MarmotClient {
+        let keys = Keys::generate();
+        MarmotClient::new(&keys.secret_key().to_secret_hex(), &keys.public_key().to_hex(), None).unwrap()
+    }
+
+    /// Make the client's next `count` group storage reads fail.
+    pub(crate) fn fail_storage_reads(client: &MarmotClient, count: u32) {
+        use openmls::prelude::OpenMlsProvider;
+        client.mdk.read().provider.storage().fail_reads(count);
+    }
+
+    /// Make the client's next `count` group storage writes fail.
+    pub(crate) fn fail_storage_writes(client: &MarmotClient, count: u32) {
+        use openmls::prelude::OpenMlsProvider;
+        client.mdk.read().provider.storage().fail_writes(count);
+    }
+
+    /// Sign the key package produced by `client` into a Nostr event.
+    pub(crate) fn key_package_event(client: &MarmotClient) -> Vec<u8> {
+        #[derive(serde::Deserialize)]
+        struct KeyPackageResult {
+            content: String,
+            tags: Vec<Vec<String>>,
+        }
+
+        let kp: KeyPackageResult = serde_json::from_slice(&client.generate_key_package().unwrap()).unwrap();
+        let tags: Vec<Tag> = kp.tags.into_iter().map(|t| Tag::parse(t).unwrap()).collect();
+        let event = EventBuilder::new(Kind::Custom(30443), kp.content)
+            .tags(tags)
+            .sign_with_keys(&client.keys)
+            .unwrap();
+        serde_json::to_vec(&event).unwrap()
+    }
+
+    /// NIP-59 gift wrap `rumor` from `sender` to `receiver`, as a relay client would deliver it.
+    pub(crate) fn gift_wrap(sender: &Keys, receiver: &PublicKey, rumor: &serde_json::Value) -> Vec<u8> {
+        use nostr::nips::nip44::{encrypt, Version};
+
+        let sealed = encrypt(sender.secret_key(), receiver, rumor.to_string(), Version::V2).unwrap();
+        let seal = EventBuilder::new(Kind::Seal, sealed).sign_with_keys(sender).unwrap();
+
+        let ephemeral = Keys::generate();
+        let wrapped = encrypt(ephemeral.secret_key(), receiver, serde_json::to_string(&seal).unwrap(), Version::V2).unwrap();
+        let wrap = EventBuilder::new(Kind::GiftWrap, wrapped)
+            .tag(Tag::public_key(*receiver))
+            .sign_with_keys(&ephemeral)
+            .unwrap();
+        serde_json::to_vec(&wrap).unwrap()
+    }
+
+    /// Wrap the first welcome rumor of an `add_member` result for `process_welcome`.
+    pub(crate) fn welcome_input(add_member_result: &[u8]) -> Vec<u8> {
+        let result: serde_json::Value = serde_json::from_slice(add_member_result).unwrap();
+        let rumor = result["welcome"][0].clone();
+        serde_json::to_vec(&serde_json::json!({
+            "wrapper_event_id": EventId::all_zeros().to_hex(),
+            "rumor_event": rumor,
+        }))
+        .unwrap()
+    }
+
+    /// Create a group owned by a new client and have a second client join it.
+    /// Returns (alice, bob, group_id).
+    pub(crate) fn joined_pair() -> (MarmotClient, MarmotClient, Vec<u8>) {
+        let alice = new_client();
+        let bob = new_client();
+
+        let (group_id, _) = alice.create_group("test group").unwrap();
+        let added = alice.add_member(&group_id, &key_package_event(&bob)).unwrap();
+        bob.process_welcome(&welcome_input(&added)).unwrap();
+
+        (alice, bob, group_id)
+    }
+
+    #[test]
+    fn decrypting_the_same_event_twice_reports_duplicate() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let event = alice.encrypt_message(&group_id, "hello").unwrap();
+
+        let (sender, plaintext, _, _) = bob.decrypt_message(&group_id, &event).unwrap();
+        assert_eq!(sender, alice.keys.public_key().to_hex());
+        assert_eq!(plaintext, "hello");
+
+        let err = bob.decrypt_message(&group_id, &event).unwrap_err();
+        assert_eq!(err.code(), MarmotError::DuplicateMessage.code());
+    }
+
+    #[test]
+    fn only_the_most_recent_nul_messages_are_held() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let events: Vec<Vec<u8>> = (0..=HELD_MESSAGES)
+            .map(|i| alice.encrypt_message(&group_id, &format!("{}\0", i)).unwrap())
+            .collect();
+        for event in &events {
+            assert!(matches!(bob.decrypt_message_text(&group_id, event), Err(MarmotError::InvalidState(_))));
+        }
+
+        // The oldest was dropped to make room, so it is reported as already processed
+        let err = bob.decrypt_message_raw(&group_id, &events[0]).unwrap_err();
+        assert_eq!(err.code(), MarmotError::DuplicateMessage.code());
+        let (_, content, _) = bob.decrypt_message_raw(&group_id, &events[HELD_MESSAGES]).unwrap();
+        assert_eq!(content, format!("{}\0", HELD_MESSAGES).into_bytes());
+    }
+
+    #[test]
+    fn decrypt_rejects_messages_from_another_group() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let event = alice.encrypt_message(&group_id, "hello").unwrap();
+        let err = bob.decrypt_message(&[0u8; 32], &event).unwrap_err();
+        assert!(matches!(err, MarmotError::InvalidState(ref m) if m == "group id mismatch"));
+    }
+
+    #[test]
+    fn oversized_messages_are_rejected_before_encryption() {
+        let (alice, _bob, group_id) = joined_pair();
+        alice.set_max_message_bytes(16).unwrap();
+
+        let err = alice.encrypt_message(&group_id, &"x".repeat(17)).unwrap_err();
+        assert!(matches!(err, MarmotError::InvalidState(ref m) if m == "message too large: 17 bytes, limit is 16"));
+        assert!(alice.encrypt_binary(&group_id, &[0u8; 16]).is_err());
+
+        alice.encrypt_message(&group_id, &"x".repeat(16)).unwrap();
+    }
+
+    #[test]
+    fn decrypt_messages_keeps_order_and_isolates_failures() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let valid: serde_json::Value = serde_json::from_slice(&alice.encrypt_message(&group_id, "hello").unwrap()).unwrap();
+        let mut corrupt: serde_json::Value =
+            serde_json::from_slice(&alice.encrypt_message(&group_id, "lost").unwrap()).unwrap();
+        corrupt["content"] = serde_json::Value::String("not a ciphertext".into());
+        let events = serde_json::to_vec(&serde_json::json!([corrupt, valid])).unwrap();
+
+        let results = bob.decrypt_messages(&group_id, &events).unwrap();
+        assert_eq!(results.len(), 2);
+
+        assert!(!results[0].ok);
+        assert!(results[0].error.is_some());
+        assert!(results[0].content.is_none());
+
+        assert!(results[1].ok);
+        assert_eq!(results[1].content.as_deref(), Some("hello"));
+        assert_eq!(results[1].sender, Some(alice.keys.public_key().to_hex()));
+        assert!(results[1].error.is_none());
+    }
+
+    #[test]
+    fn messages_from_expired_epochs_report_epoch_too_old() {
+        let (alice, bob, group_id) = joined_pair();
+        let old = alice.encrypt_message(&group_id, "too late").unwrap();
+
+        // Advance just past the epochs MDK retains secrets for
+        for _ in 0..=MDK_RETAINED_PAST_EPOCHS {
+            let commit = alice.update_keys(&group_id).unwrap();
+            bob.process_commit(&group_id, &commit).unwrap();
+        }
+
+        let err = bob.decrypt_message(&group_id, &old).unwrap_err();
+        assert_eq!(err.code(), MarmotError::EpochTooOld(String::new()).code());
+    }
+
+    #[test]
+    fn messages_from_a_future_epoch_are_not_too_old() {
+        let (alice, bob, group_id) = joined_pair();
+
+        // Bob has not seen the commit yet, so he cannot decrypt the next message
+        alice.update_keys(&group_id).unwrap();
+        let early = alice.encrypt_message(&group_id, "early").unwrap();
+
+        let err = bob.decrypt_message(&group_id, &early).unwrap_err();
+        assert_eq!(err.mdk_kind(), Some(MdkErrorKind::Message));
+    }
+
+    #[test]
+    fn dry_run_add_member_leaves_the_group_unchanged() {
+        let alice = new_client();
+        let bob = new_client();
+        let (group_id, epoch) = alice.create_group("preview").unwrap();
+        let before = alice.get_group_info(&group_id).unwrap();
+
+        let preview = alice.dry_run_add_member(&group_id, &key_package_event(&bob)).unwrap();
+        let bob_key = bob.keys.public_key().to_hex();
+        assert_eq!(preview.new_member, bob_key);
+        assert_eq!(preview.epoch, epoch + 1);
+        assert!(preview.members.contains(&bob_key));
+        assert_eq!(preview.members.len(), 2);
+
+        assert_eq!(alice.get_group_info(&group_id).unwrap(), before);
+        assert_eq!(alice.current_epoch(&group_id), Some(epoch));
+    }
+
+    #[test]
+    fn decrypt_to_rumor_json_returns_the_original_rumor() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let event = alice.encrypt_message(&group_id, "raw").unwrap();
+        let rumor: UnsignedEvent = serde_json::from_str(&bob.decrypt_to_rumor_json(&group_id, &event).unwrap()).unwrap();
+
+        assert_eq!(rumor.content, "raw");
+        assert_eq!(rumor.pubkey, alice.keys.public_key());
+        assert_eq!(rumor.kind, nostr::Kind::from(9));
+    }
+
+    #[test]
+    fn add_member_from_bare_key_package() {
+        let alice = new_client();
+        let bob = new_client();
+        let (group_id, _) = alice.create_group("qr invite").unwrap();
+
+        // A QR invite carries the content and tags of Bob's signed key package event
+        let event: Event = serde_json::from_slice(&key_package_event(&bob)).unwrap();
+        event.verify().unwrap();
+        let tags: Vec<&[String]> = event.tags.iter().map(|tag| tag.as_slice()).collect();
+        let tags = serde_json::to_string(&tags).unwrap();
+
+        let truncated = &event.content[..event.content.len() / 2];
+        assert!(alice.add_member_from_key_package(&group_id, truncated, &tags).is_err());
+
+        let added = alice.add_member_from_key_package(&group_id, &event.content, &tags).unwrap();
+        bob.process_welcome(&welcome_input(&added)).unwrap();
+
+        let (_, _, members, _, _) = alice.get_group_info(&group_id).unwrap();
+        assert!(members.contains(&bob.keys.public_key().to_hex()));
+    }
+
+    #[test]
+    fn epoch_authenticator_matches_at_shared_epoch() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let before = alice.epoch_authenticator(&group_id).unwrap();
+        assert!(!before.is_empty());
+        assert_eq!(before, bob.epoch_authenticator(&group_id).unwrap());
+
+        bob.update_keys(&group_id).unwrap();
+        assert_ne!(alice.epoch_authenticator(&group_id).unwrap(), bob.epoch_authenticator(&group_id).unwrap());
+    }
+
+    #[test]
+    fn create_group_with_founding_members() {
+        let alice = new_client();
+        let members = [new_client(), new_client(), new_client()];
+
+        let events: Vec<serde_json::Value> = members
+            .iter()
+            .map(|m| serde_json::from_slice(&key_package_event(m)).unwrap())
+            .collect();
+        let (group_id, _, welcomes) = alice
+            .create_group_with_members("founders", &serde_json::to_vec(&events).unwrap())
+            .unwrap();
+
+        assert_eq!(welcomes.len(), 3);
+        let (_, _, roster, _, _) = alice.get_group_info(&group_id).unwrap();
+        assert_eq!(roster.len(), 4);
+    }
+
+    #[test]
+    fn failed_founding_deactivates_the_group() {
+        use mdk_storage_traits::groups::types::GroupState;
+
+        let alice = new_client();
+        let mut event: serde_json::Value = serde_json::from_slice(&key_package_event(&new_client())).unwrap();
+        event["content"] = "invalid".into();
+
+        assert!(alice.create_group_with_members("broken", &serde_json::to_vec(&[event]).unwrap()).is_err());
+        let groups = alice.mdk.read().get_groups().unwrap();
+        assert_eq!(groups.len(), 1);
+        assert!(matches!(groups[0].state, GroupState::Inactive));
+    }
+
+    #[test]
+    fn members_page_splits_the_sorted_roster() {
+        let alice = new_client();
+        let members = [new_client(), new_client(), new_client(), new_client()];
+        let events: Vec<serde_json::Value> = members
+            .iter()
+            .map(|m| serde_json::from_slice(&key_package_event(m)).unwrap())
+            .collect();
+        let (group_id, _, _) = alice
+            .create_group_with_members("paged", &serde_json::to_vec(&events).unwrap())
+            .unwrap();
+
+        let pages: Vec<(Vec<String>, usize)> =
+            [0, 2, 4].iter().map(|&offset| alice.members_page(&group_id, offset, 2).unwrap()).collect();
+        assert_eq!(pages.iter().map(|(page, _)| page.len()).collect::<Vec<_>>(), vec![2, 2, 1]);
+        assert!(pages.iter().all(|(_, total)| *total == 5));
+
+        let mut roster = alice.get_group_info(&group_id).unwrap().2;
+        roster.sort();
+        assert_eq!(pages.into_iter().flat_map(|(page, _)| page).collect::<Vec<_>>(), roster);
+
+        assert!(alice.members_page(&group_id, 5, 2).unwrap().0.is_empty());
+        assert!(matches!(alice.members_page(&group_id, 0, 0), Err(MarmotError::InvalidState(_))));
+    }
+
+    #[test]
+    fn commit_event_ids_match_the_serialized_events() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let commit = alice.update_keys(&group_id).unwrap();
+        let event: Event = serde_json::from_slice(&commit).unwrap();
+        let computed = EventId::new(&event.pubkey, &event.created_at, &event.kind, &event.tags, &event.content);
+        assert_eq!(event.id, computed);
+        assert_eq!(alice.last_commit_event_id(), Some(computed.to_hex()));
+
+        let added = alice.add_member(&group_id, &key_package_event(&new_client())).unwrap();
+        let added: serde_json::Value = serde_json::from_slice(&added).unwrap();
+        let event: Event = serde_json::from_value(added["commit"].clone()).unwrap();
+        let computed = EventId::new(&event.pubkey, &event.created_at, &event.kind, &event.tags, &event.content);
+        assert_eq!(added["commit_event_id"], computed.to_hex());
+        assert_eq!(alice.last_commit_event_id(), Some(computed.to_hex()));
+
+        assert_eq!(bob.last_commit_event_id(), None);
+    }
+
+    #[test]
+    fn reinit_group_migrates_members_to_a_new_group() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let events = format!("[{}]", String::from_utf8(key_package_event(&bob)).unwrap());
+        let (new_group_id, welcomes) = alice.reinit_group(&group_id, events.as_bytes()).unwrap();
+        assert_ne!(new_group_id, group_id);
+        assert_eq!(welcomes.len(), 1);
+
+        let welcome = serde_json::to_vec(&serde_json::json!({ "welcome": welcomes })).unwrap();
+        let (joined_id, name, _, members) = bob.process_welcome(&welcome_input(&welcome)).unwrap();
+        assert_eq!(joined_id, new_group_id);
+        assert_eq!(name, "test group");
+        assert_eq!(members.len(), 2);
+
+        let err = alice.encrypt_message(&group_id, "hello").unwrap_err();
+        assert!(err.to_string().contains("Group reinitialized"));
+        alice.encrypt_message(&new_group_id, "hello").unwrap();
+    }
+
+    #[test]
+    fn reinit_group_requires_a_key_package_for_every_member() {
+        let (alice, _bob, group_id) = joined_pair();
+
+        assert!(alice.reinit_group(&group_id, b"[]").is_err());
+    }
+
+    #[test]
+    fn subgroups_re_invite_every_parent_member() {
+        let (alice, bob, group_id) = joined_pair();
+        let carol = new_client();
+        let added = alice.add_member(&group_id, &key_package_event(&carol)).unwrap();
+        carol.process_welcome(&welcome_input(&added)).unwrap();
+
+        let events = format!(
+            "[{},{}]",
+            String::from_utf8(key_package_event(&bob)).unwrap(),
+            String::from_utf8(key_package_event(&carol)).unwrap()
+        );
+        let (subgroup_id, welcomes) = alice.derive_subgroup(&group_id, "side chat", events.as_bytes()).unwrap();
+        assert_ne!(subgroup_id, group_id);
+        assert_eq!(welcomes.len(), 2);
+        assert_eq!(alice.group_relays(&subgroup_id).unwrap(), alice.group_relays(&group_id).unwrap());
+
+        let (name, _, members, _, _) = alice.get_group_info(&subgroup_id).unwrap();
+        assert_eq!(name, "side chat");
+        assert_eq!(members.len(), 3);
+
+        // The parent is unaffected
+        alice.encrypt_message(&group_id, "hello").unwrap();
+
+        let bob_only = format!("[{}]", String::from_utf8(key_package_event(&bob)).unwrap());
+        assert!(alice.derive_subgroup(&group_id, "side chat", bob_only.as_bytes()).is_err());
+
+        // Only the parent's members can be carried over
+        let outsider = new_client();
+        let with_outsider = format!(
+            "[{},{},{}]",
+            String::from_utf8(key_package_event(&bob)).unwrap(),
+            String::from_utf8(key_package_event(&carol)).unwrap(),
+            String::from_utf8(key_package_event(&outsider)).unwrap()
+        );
+        let err = alice.derive_subgroup(&group_id, "side chat", with_outsider.as_bytes()).unwrap_err();
+        assert!(matches!(err, MarmotError::InvalidState(msg) if msg.starts_with("Key package from non-member")));
+    }
+
+    #[test]
+    fn process_commit_skips_a_redelivered_commit() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let commit = alice.update_keys(&group_id).unwrap();
+        let effect = bob.process_commit(&group_id, &commit).unwrap();
+        assert_eq!(effect.kind, CommitKind::Commit);
+
+        let replay = bob.process_commit(&group_id, &commit).unwrap();
+        assert_eq!(replay.kind, CommitKind::AlreadyApplied);
+        assert_eq!(replay.epoch, effect.epoch);
+        assert_eq!(replay.member_delta, 0);
+    }
+
+    #[test]
+    fn process_commit_skips_commits_applied_through_other_paths() {
+        let (alice, bob, group_id) = joined_pair();
+
+        // Received through decrypt_message
+        let commit = alice.update_keys(&group_id).unwrap();
+        let (sender, _, epoch, _) = bob.decrypt_message(&group_id, &commit).unwrap();
+        assert_eq!(sender, "commit");
+        let replay = bob.process_commit(&group_id, &commit).unwrap();
+        assert_eq!(replay.kind, CommitKind::AlreadyApplied);
+        assert_eq!(replay.epoch, epoch);
+
+        // Our own commit, echoed back by a relay
+        let own = alice.update_keys(&group_id).unwrap();
+        let echo = alice.process_commit(&group_id, &own).unwrap();
+        assert_eq!(echo.kind, CommitKind::AlreadyApplied);
+        assert!(matches!(alice.decrypt_message(&group_id, &own), Err(MarmotError::DuplicateMessage)));
+    }
+
+    #[test]
+    fn process_commit_rejects_a_commit_of_another_group_before_applying_it() {
+        let (alice, bob, group_id) = joined_pair();
+        let (other_group, _) = bob.create_group("other").unwrap();
+
+        let commit = alice.update_keys(&group_id).unwrap();
+        let err = bob.process_commit(&other_group, &commit).unwrap_err();
+        assert!(matches!(err, MarmotError::InvalidState(ref m) if m == "group id mismatch"));
+
+        // Nothing was applied, so the commit still applies to its own group
+        let effect = bob.process_commit(&group_id, &commit).unwrap();
+        assert_eq!(effect.kind, CommitKind::Commit);
+        assert_eq!(Some(effect.epoch), alice.current_epoch(&group_id));
+    }
+
+    #[test]
+    fn reprocess_commits_applies_shuffled_commits_in_epoch_order() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let commits: Vec<serde_json::Value> = (0..3)
+            .map(|_| serde_json::from_slice(&alice.update_keys(&group_id).unwrap()).unwrap())
+            .collect();
+        let shuffled = serde_json::json!([commits[2], commits[0], commits[1]]);
+
+        let report = bob.reprocess_commits(&group_id, shuffled.to_string().as_bytes()).unwrap();
+        assert_eq!(report.applied, 3);
+        assert!(report.failed.is_empty());
+        assert_eq!(bob.current_epoch(&group_id), alice.current_epoch(&group_id));
+
+        // Already applied commits are skipped
+        let report = bob.reprocess_commits(&group_id, shuffled.to_string().as_bytes()).unwrap();
+        assert_eq!(report.applied, 0);
+        assert!(report.failed.is_empty());
+    }
+
+    #[test]
+    fn reprocess_commits_reports_commits_that_never_apply() {
+        let (alice, bob, group_id) = joined_pair();
+        let epoch = bob.current_epoch(&group_id);
+
+        // The first commit is missing, so the later ones cannot apply
+        let commits: Vec<serde_json::Value> = (0..3)
+            .map(|_| serde_json::from_slice(&alice.update_keys(&group_id).unwrap()).unwrap())
+            .collect();
+        let missing_first = serde_json::json!([commits[2], commits[1]]);
+
+        let report = bob.reprocess_commits(&group_id, missing_first.to_string().as_bytes()).unwrap();
+        assert_eq!(report.applied, 0);
+        let mut failed = report.failed;
+        failed.sort();
+        let mut expected: Vec<String> = commits[1..].iter().map(|c| c["id"].as_str().unwrap().to_string()).collect();
+        expected.sort();
+        assert_eq!(failed, expected);
+        assert_eq!(bob.current_epoch(&group_id), epoch);
+    }
+
+    #[test]
+    fn process_commit_reports_epoch_and_member_delta() {
+        let (alice, bob, group_id) = joined_pair();
+        let carol = new_client();
+        let carol_key = carol.keys.public_key().to_hex();
+
+        let added = alice.add_member(&group_id, &key_package_event(&carol)).unwrap();
+        let added: serde_json::Value = serde_json::from_slice(&added).unwrap();
+        let effect = bob.process_commit(&group_id, added["commit"].to_string().as_bytes()).unwrap();
+        assert_eq!(effect.kind, CommitKind::Commit);
+        assert_eq!(effect.member_delta, 1);
+        assert_eq!(effect.added, vec![carol_key.clone()]);
+        assert!(effect.removed.is_empty());
+
+        let removed = alice.remove_member(&group_id, &carol_key).unwrap();
+        let effect = bob.process_commit(&group_id, &removed).unwrap();
+        assert_eq!(effect.member_delta, -1);
+        assert_eq!(effect.removed, vec![carol_key]);
+        assert_eq!(Some(effect.epoch), alice.current_epoch(&group_id));
+    }
+
+    #[test]
+    fn exported_ratchet_tree_is_a_complete_tls_vector() {
+        let (alice, _bob, group_id) = joined_pair();
+
+        let tree = alice.export_ratchet_tree(&group_id).unwrap();
+        assert!(!tree.is_empty());
+
+        // The tree is a TLS vector prefixed with an MLS variable-length integer
+        let prefix_len = 1usize << (tree[0] >> 6);
+        let body_len = tree[..prefix_len]
+            .iter()
+            .enumerate()
+            .fold(0usize, |acc, (i, b)| (acc << 8) | (if i == 0 { b & 0x3f } else { *b }) as usize);
+        assert!(body_len > 0);
+        assert_eq!(tree.len(), prefix_len + body_len);
+    }
+
+    #[test]
+    fn group_exists_distinguishes_present_and_absent_groups() {
+        let alice = new_client();
+        let (group_id, _) = alice.create_group("exists").unwrap();
+
+        assert!(alice.group_exists(&group_id).unwrap());
+        assert!(!alice.group_exists(&[0u8; 32]).unwrap());
+    }
+
+    #[test]
+    fn group_exists_reports_storage_failures() {
+        let alice = new_client();
+        let (group_id, _) = alice.create_group("exists").unwrap();
+
+        // A failing storage backend is an error, not an absent group
+        fail_storage_reads(&alice, 1);
+        let err = alice.group_exists(&group_id).unwrap_err();
+        assert!(err.is_storage());
+        assert_eq!(err.code(), MarmotError::StorageError(String::new()).code());
+        assert!(alice.group_exists(&group_id).unwrap());
+    }
+
+    #[test]
+    fn failed_storage_writes_in_mdk_are_storage_errors() {
+        let alice = new_client();
+
+        fail_storage_writes(&alice, 1);
+        let err = alice.create_group("unsaved").unwrap_err();
+        assert_eq!(err.mdk_kind(), Some(MdkErrorKind::Storage));
+        assert_eq!(err.code(), MarmotError::StorageError(String::new()).code());
+        assert!(err.is_storage());
+
+        alice.create_group("saved").unwrap();
+    }
+
+    #[test]
+    fn encrypt_reply_tags_the_parent_event() {
+        let (alice, bob, group_id) = joined_pair();
+        let parent = EventId::all_zeros().to_hex();
+
+        let reply = alice.encrypt_reply(&group_id, "agreed", &parent).unwrap();
+        let event: Event = serde_json::from_slice(&reply).unwrap();
+
+        match bob.mdk.write().process_message(&event).unwrap() {
+            mdk_core::messages::MessageProcessingResult::ApplicationMessage(msg) => {
+                assert!(msg.tags.iter().any(|t| t.as_slice() == ["e", parent.as_str(), "", "root"]));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn encrypt_reply_rejects_malformed_parent_ids() {
+        let (alice, _bob, group_id) = joined_pair();
+
+        assert!(alice.encrypt_reply(&group_id, "agreed", "abc").is_err());
+        assert!(alice.encrypt_reply(&group_id, "agreed", &"z".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn current_epoch_advances_after_self_update() {
+        let alice = new_client();
+        let (group_id, created_epoch) = alice.create_group("epochs").unwrap();
+        assert_eq!(alice.current_epoch(&group_id), Some(created_epoch));
+
+        alice.update_keys(&group_id).unwrap();
+        assert_eq!(alice.current_epoch(&group_id), Some(created_epoch + 1));
+        assert_eq!(alice.current_epoch(&[0u8; 32]), None);
+    }
+
+    #[test]
+    fn key_package_lifetime_reflects_requested_days() {
+        use base64::Engine;
+        use openmls::prelude::tls_codec::Deserialize;
+        use openmls::prelude::{KeyPackageIn, OpenMlsProvider, ProtocolVersion};
+
+        let alice = new_client();
+        assert!(alice.generate_key_package_with_lifetime(0).is_err());
+
+        let kp: serde_json::Value =
+            serde_json::from_slice(&alice.generate_key_package_with_lifetime(7).unwrap()).unwrap();
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(kp["content"].as_str().unwrap())
+            .unwrap();
+        let mdk = alice.mdk.read();
+        let key_package = KeyPackageIn::tls_deserialize_exact(&bytes)
+            .unwrap()
+            .validate(mdk.provider.crypto(), ProtocolVersion::Mls10)
+            .unwrap();
+
+        let now = nostr::Timestamp::now().as_u64();
+        let not_after = key_package.life_time().not_after();
+        let seven_days = 7 * 24 * 60 * 60;
+        assert!(not_after > now + seven_days - 60 * 60 && not_after <= now + seven_days + 60);
+        assert!(!key_package.last_resort());
+
+        // The tags describe the returned key package, not another one
+        let tag = |name: &str| {
+            kp["tags"].as_array().unwrap().iter()
+                .find(|tag| tag[0] == name)
+                .map(|tag| tag[1].as_str().unwrap().to_string())
+        };
+        let hash_ref = key_package.hash_ref(mdk.provider.crypto()).unwrap();
+        assert_eq!(tag("i"), Some(hex::encode(hash_ref.as_slice())));
+        assert_eq!(tag("mls_ciphersuite"), Some(format!("0x{:04x}", key_package.ciphersuite() as u16)));
+        assert!(tag("d").is_some());
+    }
+
+    #[test]
+    fn process_message_dispatches_messages_and_commits() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let event = alice.encrypt_message(&group_id, "hello").unwrap();
+        match bob.process_message(&event).unwrap() {
+            ProcessedEvent::Message { group_id: id, sender, content, kind, .. } => {
+                assert_eq!(id, hex::encode(&group_id));
+                assert_eq!(sender, alice.keys.public_key().to_hex());
+                assert_eq!(content, "hello");
+                assert_eq!(kind, 9);
+            }
+            other => panic!("expected a message, got {:?}", other),
+        }
+
+        let commit = alice.update_keys(&group_id).unwrap();
+        assert_eq!(
+            bob.process_message(&commit).unwrap(),
+            ProcessedEvent::Commit {
+                group_id: hex::encode(&group_id),
+                epoch: alice.current_epoch(&group_id).unwrap(),
+            }
+        );
+    }
+
+    #[test]
+    fn process_message_flags_welcomes() {
+        let alice = new_client();
+        let bob = new_client();
+        let (group_id, _) = alice.create_group("welcome").unwrap();
+        let added = alice.add_member(&group_id, &key_package_event(&bob)).unwrap();
+
+        let added: serde_json::Value = serde_json::from_slice(&added).unwrap();
+        let wrap = gift_wrap(&alice.keys, &bob.keys.public_key(), &added["welcome"][0]);
+
+        assert_eq!(
+            bob.process_message(&wrap).unwrap(),
+            ProcessedEvent::WelcomeUnsupported
+        );
+    }
+
+    #[test]
+    fn preview_then_accept_welcome() {
+        let alice = new_client();
+        let bob = new_client();
+        let (group_id, _) = alice.create_group("preview").unwrap();
+        let added = alice.add_member(&group_id, &key_package_event(&bob)).unwrap();
+
+        let preview = bob.preview_welcome(&welcome_input(&added)).unwrap();
+        assert_eq!(preview.group_name, "preview");
+        assert_eq!(preview.group_id, hex::encode(&group_id));
+        assert_eq!(preview.inviter, alice.keys.public_key().to_hex());
+        assert_eq!(preview.members.len(), 2);
+
+        let (joined_id, _, _, _) = bob.accept_welcome(&preview.welcome_id).unwrap();
+        assert_eq!(joined_id, group_id);
+        let event = alice.encrypt_message(&group_id, "welcome").unwrap();
+        assert_eq!(bob.decrypt_message(&group_id, &event).unwrap().1, "welcome");
+    }
+
+    #[test]
+    fn preview_then_decline_welcome() {
+        let alice = new_client();
+        let bob = new_client();
+        let (group_id, _) = alice.create_group("preview").unwrap();
+        let added = alice.add_member(&group_id, &key_package_event(&bob)).unwrap();
+
+        let preview = bob.preview_welcome(&welcome_input(&added)).unwrap();
+        bob.decline_welcome(&preview.welcome_id).unwrap();
+
+        let welcome = bob.mdk.read().get_welcome(&EventId::from_hex(&preview.welcome_id).unwrap()).unwrap().unwrap();
+        assert_eq!(format!("{:?}", welcome.state), "Declined");
+        assert!(bob.pending_welcomes().unwrap().is_empty());
+
+        // Neither the MLS welcome nor the key package it was encrypted to stay stored
+        assert!(welcome.event.content.is_empty());
+        assert!(bob.stored_key_packages().is_empty());
+        assert!(bob.accept_welcome(&preview.welcome_id).is_err());
+    }
+
+    #[test]
+    fn declining_an_unknown_welcome_is_not_found() {
+        let bob = new_client();
+
+        let err = bob.decline_welcome(&EventId::all_zeros().to_hex()).unwrap_err();
+        assert!(matches!(err, MarmotError::WelcomeNotFound(_)));
+    }
+
+    #[test]
+    fn wrapped_welcomes_have_randomized_timestamps() {
+        let alice = new_client();
+        let bob = new_client();
+        let (group_id, _) = alice.create_group("wrapped").unwrap();
+        let added: serde_json::Value =
+            serde_json::from_slice(&alice.add_member(&group_id, &key_package_event(&bob)).unwrap()).unwrap();
+        let rumor = &added["welcome"][0];
+        let rumor_created_at = rumor["created_at"].as_u64().unwrap();
+
+        let recipient = bob.keys.public_key().to_hex();
+        let wraps: Vec<Event> = (0..3)
+            .map(|_| {
+                let wrap = alice.wrap_welcome(rumor.to_string().as_bytes(), &recipient, true).unwrap();
+                serde_json::from_slice(&wrap).unwrap()
+            })
+            .collect();
+        let now = nostr::Timestamp::now().as_u64();
+        for wrap in &wraps {
+            let created_at = wrap.created_at.as_u64();
+            assert!(created_at <= now && created_at + GIFT_WRAP_MAX_TIMESTAMP_TWEAK >= rumor_created_at);
+        }
+        // Three draws all landing on the rumor's second is vanishingly unlikely
+        assert!(wraps.iter().any(|wrap| wrap.created_at.as_u64() != rumor_created_at));
+
+        let exact = alice.wrap_welcome(rumor.to_string().as_bytes(), &recipient, false).unwrap();
+        let exact: Event = serde_json::from_slice(&exact).unwrap();
+        assert!(exact.created_at.as_u64() >= rumor_created_at);
+
+        let (joined, _, _, _) = bob.process_welcome_event(&serde_json::to_vec(&wraps[0]).unwrap()).unwrap();
+        assert_eq!(joined, group_id);
+    }
+
+    #[test]
+    fn process_welcome_event_unwraps_a_gift_wrap() {
+        let alice = new_client();
+        let bob = new_client();
+        let (group_id, _) = alice.create_group("wrapped").unwrap();
+        let added: serde_json::Value =
+            serde_json::from_slice(&alice.add_member(&group_id, &key_package_event(&bob)).unwrap()).unwrap();
+
+        let wrap = gift_wrap(&alice.keys, &bob.keys.public_key(), &added["welcome"][0]);
+        let (joined_id, name, _, members) = bob.process_welcome_event(&wrap).unwrap();
+        assert_eq!(joined_id, group_id);
+        assert_eq!(name, "wrapped");
+        assert_eq!(members.len(), 2);
+
+        // A wrap addressed to someone else cannot be opened
+        let carol = new_client();
+        assert!(matches!(carol.process_welcome_event(&wrap), Err(MarmotError::CryptoError(_))));
+    }
+
+    #[test]
+    fn pending_welcomes_list_unaccepted_invites() {
+        let alice = new_client();
+        let bob = new_client();
+        let (group_id, _) = alice.create_group("pending").unwrap();
+        let added = alice.add_member(&group_id, &key_package_event(&bob)).unwrap();
+
+        let preview = bob.preview_welcome(&welcome_input(&added)).unwrap();
+        let pending = bob.pending_welcomes().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].welcome_id, preview.welcome_id);
+        assert_eq!(pending[0].group_name, "pending");
+        assert_eq!(pending[0].inviter, alice.keys.public_key().to_hex());
+
+        bob.accept_welcome(&preview.welcome_id).unwrap();
+        assert!(bob.pending_welcomes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn sealed_messages_round_trip_and_are_distinguishable() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let sealed = alice.encrypt_message_sealed(&group_id, "secret metadata").unwrap();
+        let (sender, plaintext, _, was_sealed) = bob.decrypt_message_sealed(&group_id, &sealed).unwrap();
+        assert_eq!(sender, alice.keys.public_key().to_hex());
+        assert_eq!(plaintext, "secret metadata");
+        assert!(was_sealed);
+
+        let plain = alice.encrypt_message(&group_id, "hello").unwrap();
+        let (_, plaintext, _, was_sealed) = bob.decrypt_message_sealed(&group_id, &plain).unwrap();
+        assert_eq!(plaintext, "hello");
+        assert!(!was_sealed);
+
+        // The normal path returns the sealed payload untouched
+        let sealed = alice.encrypt_message_sealed(&group_id, "secret metadata").unwrap();
+        let (_, plaintext, _, _) = bob.decrypt_message(&group_id, &sealed).unwrap();
+        assert_ne!(plaintext, "secret metadata");
+    }
+
+    #[test]
+    fn sealed_messages_unseal_with_the_key_of_their_epoch() {
+        let (alice, bob, group_id) = joined_pair();
+
+        // Delivered only after the group moved on
+        let sealed = alice.encrypt_message_sealed(&group_id, "sent before the commit").unwrap();
+        let commit = alice.update_keys(&group_id).unwrap();
+        bob.process_commit(&group_id, &commit).unwrap();
+
+        let (_, plaintext, _, was_sealed) = bob.decrypt_message_sealed(&group_id, &sealed).unwrap();
+        assert_eq!(plaintext, "sent before the commit");
+        assert!(was_sealed);
+
+        // Without the secret of the sealing epoch the content cannot be unsealed
+        let tag = nostr::Tag::parse(["sealed", "nip44", "999"]).unwrap();
+        let unknown = alice.encrypt_with_tags(&group_id, "opaque", vec![tag], None, None).unwrap();
+        assert!(matches!(bob.decrypt_message_sealed(&group_id, &unknown), Err(MarmotError::EpochTooOld(_))));
+    }
+
+    #[test]
+    fn self_removal_is_proposed_not_committed() {
+        let (alice, bob, group_id) = joined_pair();
+        let epoch = alice.current_epoch(&group_id).unwrap();
+
+        let proposal = bob.propose_self_removal(&group_id).unwrap();
+        assert!(!bob.has_pending_commit(&group_id).unwrap());
+
+        match alice.process_message(&proposal).unwrap() {
+            ProcessedEvent::Proposal => {}
+            other => panic!("expected a proposal, got {:?}", other),
+        }
+        assert_eq!(alice.current_epoch(&group_id), Some(epoch));
+        let (_, _, members, _, _) = alice.get_group_info(&group_id).unwrap();
+        assert!(members.contains(&bob.keys.public_key().to_hex()));
+    }
+
+    #[test]
+    fn remove_members_in_a_single_commit() {
+        let alice = new_client();
+        let (bob, carol, dave) = (new_client(), new_client(), new_client());
+        let events: Vec<serde_json::Value> = [&bob, &carol, &dave]
+            .iter()
+            .map(|m| serde_json::from_slice(&key_package_event(m)).unwrap())
+            .collect();
+        let (group_id, _, _) = alice
+            .create_group_with_members("moderated", &serde_json::to_vec(&events).unwrap())
+            .unwrap();
+        let epoch = alice.current_epoch(&group_id).unwrap();
+
+        let kicked = serde_json::to_string(&[bob.keys.public_key().to_hex(), carol.keys.public_key().to_hex()]).unwrap();
+        alice.remove_members(&group_id, &kicked).unwrap();
+
+        assert_eq!(alice.current_epoch(&group_id), Some(epoch + 1));
+        let (_, _, members, _, _) = alice.get_group_info(&group_id).unwrap();
+        assert_eq!(members.len(), 2);
+    }
+
+    #[test]
+    fn remove_members_fails_atomically_on_a_non_member() {
+        let (alice, bob, group_id) = joined_pair();
+        let epoch = alice.current_epoch(&group_id).unwrap();
+
+        let keys = serde_json::to_string(&[bob.keys.public_key().to_hex(), Keys::generate().public_key().to_hex()]).unwrap();
+        let err = alice.remove_members(&group_id, &keys).unwrap_err();
+
+        assert!(matches!(err, MarmotError::MemberNotFound(_)));
+        assert_eq!(alice.current_epoch(&group_id), Some(epoch));
+    }
+
+    static CALLBACK_CONTENT: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    extern "C" fn record_message(_: *const u8, _: c_int, _: *const c_char, content: *const c_char, kind: u16, _: u64) {
+        assert_eq!(kind, 9);
+        let content = unsafe { std::ffi::CStr::from_ptr(content) }.to_str().unwrap().to_string();
+        CALLBACK_CONTENT.lock().push(content);
+    }
+
+    #[test]
+    fn process_events_dispatches_application_messages_to_the_callback() {
+        let (alice, bob, group_id) = joined_pair();
+        bob.set_message_callback(Some(record_message));
+
+        let event: serde_json::Value =
+            serde_json::from_slice(&alice.encrypt_message(&group_id, "via callback").unwrap()).unwrap();
+        let report = bob.process_events(&serde_json::to_vec(&[event]).unwrap()).unwrap();
+
+        assert_eq!(report.dispatched, 1);
+        assert!(report.errors.is_empty());
+        assert_eq!(*CALLBACK_CONTENT.lock(), vec!["via callback".to_string()]);
+    }
+
+    extern "C" fn ignore_message(_: *const u8, _: c_int, _: *const c_char, _: *const c_char, _: u16, _: u64) {}
+
+    #[test]
+    fn process_events_reports_a_failed_event_and_continues_the_batch() {
+        let (alice, bob, group_id) = joined_pair();
+        bob.set_message_callback(Some(ignore_message));
+
+        let message: serde_json::Value =
+            serde_json::from_slice(&alice.encrypt_message(&group_id, "after the bad one").unwrap()).unwrap();
+        let mut foreign = message.clone();
+        foreign["id"] = serde_json::json!("11".repeat(32));
+        foreign["content"] = serde_json::json!("not a group message");
+        let batch = serde_json::json!([{ "not": "an event" }, foreign, message]);
+
+        let report = bob.process_events(batch.to_string().as_bytes()).unwrap();
+        assert_eq!(report.dispatched, 1);
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(report.errors[0].index, 0);
+        assert_eq!(report.errors[0].event_id, None);
+        assert_eq!(report.errors[0].code, MarmotError::SerializationError(String::new()).code());
+        assert_eq!(report.errors[1].index, 1);
+        assert_eq!(report.errors[1].event_id, Some("11".repeat(32)));
+    }
+
+    #[test]
+    fn export_all_restores_every_group_into_a_fresh_client() {
+        let (alice, bob, first) = joined_pair();
+        let (second, _) = alice.create_group("second").unwrap();
+
+        let backup = alice.export_all().unwrap();
+        assert_eq!(backup[0], BACKUP_FORMAT_VERSION);
+
+        let restored = MarmotClient::new(&alice.keys.secret_key().to_secret_hex(), "", None).unwrap();
+        assert_eq!(restored.import_all(&backup).unwrap(), 2);
+        assert!(restored.group_exists(&first).unwrap());
+        assert!(restored.group_exists(&second).unwrap());
+
+        // The restored groups are usable, not just listed
+        let event = bob.encrypt_message(&first, "still here?").unwrap();
+        let (_, plaintext, _, _) = restored.decrypt_message(&first, &event).unwrap();
+        assert_eq!(plaintext, "still here?");
+        assert!(restored.encrypt_message(&second, "hello").is_ok());
+
+        // Groups that already exist are skipped
+        assert_eq!(restored.import_all(&backup).unwrap(), 0);
+
+        // Another identity cannot use the keys
+        assert!(matches!(new_client().import_all(&backup), Err(MarmotError::InvalidState(_))));
+
+        let mut unknown_version = backup.clone();
+        unknown_version[0] = BACKUP_FORMAT_VERSION + 1;
+        assert!(matches!(restored.import_all(&unknown_version), Err(MarmotError::SerializationError(_))));
+    }
+
+    #[test]
+    fn import_group_state_hands_a_group_to_another_client() {
+        let (alice, bob, group_id) = joined_pair();
+        let state = alice.export_group_state(&group_id).unwrap();
+
+        // Another client of alice's identity, e.g. a second process
+        let handoff = MarmotClient::new(&alice.keys.secret_key().to_secret_hex(), "", None).unwrap();
+        handoff.import_group_state(&group_id, &state).unwrap();
+
+        let (name, epoch, members, _, _) = handoff.get_group_info(&group_id).unwrap();
+        assert_eq!(name, "test group");
+        assert_eq!(Some(epoch), alice.current_epoch(&group_id));
+        assert_eq!(members.len(), 2);
+
+        // The imported state can encrypt and decrypt
+        let sent = handoff.encrypt_message(&group_id, "from the new client").unwrap();
+        let (_, plaintext, _, _) = bob.decrypt_message(&group_id, &sent).unwrap();
+        assert_eq!(plaintext, "from the new client");
+        let reply = bob.encrypt_message(&group_id, "welcome back").unwrap();
+        let (sender, plaintext, _, _) = handoff.decrypt_message(&group_id, &reply).unwrap();
+        assert_eq!(sender, bob.keys.public_key().to_hex());
+        assert_eq!(plaintext, "welcome back");
+
+        // Importing over an existing group fails
+        assert!(matches!(handoff.import_group_state(&group_id, &state), Err(MarmotError::InvalidState(_))));
+        assert!(matches!(new_client().import_group_state(&[1; 32], &state), Err(MarmotError::InvalidState(_))));
+
+        // Another identity's keys are useless to a client, and nothing is left behind
+        let stranger = new_client();
+        assert!(matches!(stranger.import_group_state(&group_id, &state), Err(MarmotError::InvalidState(_))));
+        assert!(!stranger.group_exists(&group_id).unwrap());
+        assert!(matches!(load_mls_group(&stranger.mdk.read(), &group_id), Err(MarmotError::GroupNotFound(_))));
+    }
+
+    #[test]
+    fn encrypt_at_epoch_rejects_a_mismatched_expected_epoch() {
+        let (alice, _bob, group_id) = joined_pair();
+        let epoch = alice.current_epoch(&group_id).unwrap();
+
+        let behind = alice.encrypt_message_at_epoch(&group_id, "hi", Some(epoch + 1));
+        assert!(matches!(behind, Err(MarmotError::InvalidState(msg)) if msg.starts_with("epoch behind")));
+
+        let stale = alice.encrypt_message_at_epoch(&group_id, "hi", Some(epoch - 1));
+        assert!(matches!(stale, Err(MarmotError::InvalidState(_))));
+
+        assert!(alice.encrypt_message_at_epoch(&group_id, "hi", Some(epoch)).is_ok());
+    }
+
+    #[test]
+    fn members_at_epoch_reports_rosters_across_a_removal() {
+        let (alice, bob, group_id) = joined_pair();
+        let before = alice.current_epoch(&group_id).unwrap();
+
+        alice.remove_member(&group_id, &bob.keys.public_key().to_hex()).unwrap();
+        let after = alice.current_epoch(&group_id).unwrap();
+
+        let roster_before = alice.members_at_epoch(&group_id, before).unwrap();
+        let roster_after = alice.members_at_epoch(&group_id, after).unwrap();
+        assert!(roster_before.contains(&bob.keys.public_key().to_hex()));
+        assert_eq!(roster_after, vec![alice.keys.public_key().to_hex()]);
+
+        // Bob joined at `before`, so he never observed the creation epoch
+        assert!(matches!(bob.members_at_epoch(&group_id, 0), Err(MarmotError::InvalidState(_))));
+    }
+
+    #[test]
+    fn validate_key_package_reports_owner_and_lifetime() {
+        let (alice, bob) = (new_client(), new_client());
+        let (group_id, _) = alice.create_group("default").unwrap();
+
+        let info = alice.validate_key_package(&group_id, &key_package_event(&bob)).unwrap();
+        assert_eq!(info.public_key, bob.keys.public_key().to_hex());
+        assert!(info.not_before < info.not_after);
+    }
+
+    #[test]
+    fn validate_key_package_rejects_an_expired_package() {
+        let (alice, bob) = (new_client(), new_client());
+        let (group_id, _) = alice.create_group("default").unwrap();
+        let event = key_package_event(&bob);
+
+        let info = alice.validate_key_package(&group_id, &event).unwrap();
+        let expired = alice.validate_key_package_at(&group_id, &event, info.not_after + 1);
+        assert!(matches!(expired, Err(MarmotError::KeyPackageExpired(_))));
+    }
+
+    #[test]
+    fn client_public_key_must_match_the_private_key() {
+        let keys = Keys::generate();
+        let secret = keys.secret_key().to_secret_hex();
+
+        let client = MarmotClient::new(&secret, &keys.public_key().to_hex(), None).unwrap();
+        assert_eq!(client.keys.public_key(), keys.public_key());
+
+        let other = Keys::generate().public_key().to_hex();
+        let result = MarmotClient::new(&secret, &other, None);
+        assert!(matches!(result, Err(MarmotError::InvalidKey(msg)) if msg == "public key does not match private key"));
+    }
+
+    #[test]
+    fn empty_client_public_key_is_derived() {
+        let keys = Keys::generate();
+
+        let client = MarmotClient::new(&keys.secret_key().to_secret_hex(), "", None).unwrap();
+        assert_eq!(client.keys.public_key(), keys.public_key());
+    }
+
+    #[test]
+    fn clients_advertise_their_configured_ciphersuite() {
+        for ciphersuite in [0x0001, 0x0003] {
+            let keys = Keys::generate();
+            let client = MarmotClient::new_with_ciphersuite(
+                &keys.secret_key().to_secret_hex(),
+                &keys.public_key().to_hex(),
+                ciphersuite,
+            )
+            .unwrap();
+            let (group_id, _) = client.create_group("default").unwrap();
+
+            let info = client.validate_key_package(&group_id, &key_package_event(&client)).unwrap();
+            assert_eq!(info.ciphersuite, ciphersuite);
+        }
+    }
+
+    #[test]
+    fn key_packages_are_checked_against_the_group_ciphersuite() {
+        let alice = new_client();
+        let (group_id, epoch) = alice.create_group("default").unwrap();
+
+        let keys = Keys::generate();
+        let bob = MarmotClient::new_with_ciphersuite(&keys.secret_key().to_secret_hex(), &keys.public_key().to_hex(), 0x0003)
+            .unwrap();
+        let event = key_package_event(&bob);
+        assert!(matches!(
+            alice.validate_key_package(&group_id, &event),
+            Err(MarmotError::UnsupportedCiphersuite(_))
+        ));
+        assert!(matches!(
+            alice.dry_run_add_member(&group_id, &event),
+            Err(MarmotError::UnsupportedCiphersuite(_))
+        ));
+        assert!(matches!(
+            alice.validate_key_package(b"no such group", &event),
+            Err(MarmotError::GroupNotFound(_))
+        ));
+        assert_eq!(alice.current_epoch(&group_id), Some(epoch));
+    }
+
+    #[test]
+    fn adding_a_key_package_of_another_ciphersuite_is_a_mismatch() {
+        let alice = new_client();
+        let (group_id, epoch) = alice.create_group("default").unwrap();
+
+        let keys = Keys::generate();
+        let bob = MarmotClient::new_with_ciphersuite(&keys.secret_key().to_secret_hex(), &keys.public_key().to_hex(), 0x0003)
+            .unwrap();
+        let result = alice.add_member(&group_id, &key_package_event(&bob));
+        assert!(matches!(
+            result,
+            Err(MarmotError::InvalidState(msg)) if msg.starts_with("ciphersuite mismatch") && msg.contains("0x0003")
+        ));
+        assert_eq!(alice.current_epoch(&group_id), Some(epoch));
+    }
+
+    #[test]
+    fn wiped_clients_no_longer_hold_their_secret_key() {
+        let mut alice = new_client();
+        let public_key = alice.keys.public_key();
+
+        alice.wipe_secrets();
+        assert_ne!(alice.keys.public_key(), public_key);
+    }
+
+    #[test]
+    fn stored_key_packages_are_listed_until_deleted() {
+        let alice = new_client();
+        assert!(alice.stored_key_packages().is_empty());
+
+        alice.generate_key_package().unwrap();
+        alice.generate_key_package_with_lifetime(7).unwrap();
+        let stored = alice.stored_key_packages();
+        assert_eq!(stored.len(), 2);
+        assert_ne!(stored[0].key_package_ref, stored[1].key_package_ref);
+
+        alice.delete_key_package(&stored[0].key_package_ref).unwrap();
+        assert_eq!(alice.stored_key_packages(), vec![stored[1].clone()]);
+
+        assert!(matches!(alice.delete_key_package(&stored[0].key_package_ref), Err(MarmotError::InvalidState(_))));
+
+        // Key packages removed from MDK's storage by other means are no longer listed
+        let (_, content) = alice.stored_key_packages.read().get(&stored[1].key_package_ref).cloned().unwrap();
+        {
+            let mdk = alice.mdk.write();
+            let key_package = decode_key_package(&mdk, &content).unwrap();
+            mdk.delete_key_package_from_storage(&key_package).unwrap();
+        }
+        assert!(alice.stored_key_packages().is_empty());
+    }
+
+    #[test]
+    fn groups_report_their_ciphersuite() {
+        let keys = Keys::generate();
+        let client = MarmotClient::new_with_ciphersuite(&keys.secret_key().to_secret_hex(), &keys.public_key().to_hex(), 0x0003)
+            .unwrap();
+        let (group_id, _) = client.create_group("chacha").unwrap();
+        assert_eq!(client.group_ciphersuite(&group_id), Some(0x0003));
+
+        let client = new_client();
+        let (group_id, _) = client.create_group("default").unwrap();
+        let default = MDK::builder(MdkMemoryStorage::new()).build().ciphersuite;
+        assert_eq!(client.group_ciphersuite(&group_id), Some(default as u16));
+
+        assert_eq!(client.group_ciphersuite(&[0u8; 32]), None);
+    }
+
+    #[test]
+    fn unsupported_ciphersuites_are_rejected() {
+        let keys = Keys::generate();
+        let result = MarmotClient::new_with_ciphersuite(&keys.secret_key().to_secret_hex(), &keys.public_key().to_hex(), 0xBEEF);
+        assert!(matches!(result, Err(MarmotError::UnsupportedCiphersuite(msg)) if msg.contains("supported: 0x0001")));
+    }
+
+    #[test]
+    fn add_member_leaves_the_group_unchanged_when_the_welcome_cannot_be_serialized() {
+        let (alice, bob) = (new_client(), new_client());
+        let (group_id, epoch) = alice.create_group("test group").unwrap();
+
+        let result = alice.add_member_with(&group_id, &key_package_event(&bob), |_| {
+            Err(MarmotError::SerializationError("welcome".into()))
+        });
+        assert!(matches!(result, Err(MarmotError::SerializationError(_))));
+
+        let (_, epoch_after, members, _, _) = alice.get_group_info(&group_id).unwrap();
+        assert_eq!(epoch_after, epoch);
+        assert_eq!(members, vec![alice.keys.public_key().to_hex()]);
+
+        // The group is still usable for a normal add
+        alice.add_member(&group_id, &key_package_event(&bob)).unwrap();
+    }
+
+    #[test]
+    fn add_member_without_a_welcome_is_a_failure() {
+        assert!(matches!(welcome_json(None), Err(MarmotError::Internal(_))));
+        assert!(matches!(welcome_json(Some(&[])), Err(MarmotError::Internal(_))));
+
+        let (alice, bob) = (new_client(), new_client());
+        let (group_id, epoch) = alice.create_group("test group").unwrap();
+
+        // As if MDK had produced the commit but no welcome
+        let result = alice.add_member_with(&group_id, &key_package_event(&bob), |_| welcome_json(None));
+        assert!(matches!(result, Err(MarmotError::Internal(msg)) if msg == "no welcome produced"));
+
+        let (_, epoch_after, members, _, _) = alice.get_group_info(&group_id).unwrap();
+        assert_eq!(epoch_after, epoch);
+        assert_eq!(members.len(), 1);
+    }
+
+    #[test]
+    fn self_update_with_message_encrypts_at_the_new_epoch() {
+        let (alice, bob, group_id) = joined_pair();
+        let epoch = alice.current_epoch(&group_id).unwrap();
+
+        let result: serde_json::Value =
+            serde_json::from_slice(&alice.self_update_with_message(&group_id, "rekeyed").unwrap()).unwrap();
+
+        let effect = bob.process_commit(&group_id, &serde_json::to_vec(&result["commit"]).unwrap()).unwrap();
+        assert_eq!(effect.epoch, epoch + 1);
+
+        let (_, plaintext, message_epoch, _) =
+            bob.decrypt_message(&group_id, &serde_json::to_vec(&result["message"]).unwrap()).unwrap();
+        assert_eq!(plaintext, "rekeyed");
+        assert_eq!(message_epoch, epoch + 1);
+        assert!(result["message_error"].is_null());
+
+        // A message that cannot be sent fails before anything is committed
+        alice.set_max_message_bytes(4).unwrap();
+        assert!(alice.self_update_with_message(&group_id, "too long").is_err());
+        assert_eq!(alice.current_epoch(&group_id), Some(epoch + 1));
+    }
+
+    #[test]
+    fn default_message_kind_applies_unless_overridden() {
+        let (alice, bob, group_id) = joined_pair();
+        alice.set_default_message_kind(30023);
+
+        let event = alice.encrypt_message(&group_id, "article").unwrap();
+        let message = bob.decrypt_incoming(&group_id, &event).unwrap();
+        assert_eq!(message.kind, Some(nostr::Kind::from(30023)));
+
+        let event = alice.encrypt_message_with_kind(&group_id, "chat", 9).unwrap();
+        let message = bob.decrypt_incoming(&group_id, &event).unwrap();
+        assert_eq!(message.kind, Some(nostr::Kind::from(9)));
+    }
+
+    #[test]
+    fn concurrent_key_packages_are_both_usable() {
+        let alice = new_client();
+        let bob = new_client();
+
+        let events: Vec<Vec<u8>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..2).map(|_| scope.spawn(|| key_package_event(&bob))).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        for event in events {
+            let (group_id, _) = alice.create_group("test group").unwrap();
+            let added = alice.add_member(&group_id, &event).unwrap();
+            let (joined_id, _, _, _) = bob.process_welcome(&welcome_input(&added)).unwrap();
+            assert_eq!(joined_id, group_id);
+        }
+    }
+
+    #[test]
+    fn group_image_round_trips_for_other_members() {
+        let (alice, bob, group_id) = joined_pair();
+        assert_eq!(alice.group_image_key(&group_id).unwrap(), None);
+
+        let image = b"\x89PNG tiny image".to_vec();
+        let (encrypted, commit) = alice.set_group_image(&group_id, &image).unwrap();
+        assert_ne!(encrypted, image);
+        bob.process_commit(&group_id, &commit).unwrap();
+
+        let image_key = bob.group_image_key(&group_id).unwrap().unwrap();
+        assert_eq!(Some(image_key.clone()), alice.group_image_key(&group_id).unwrap());
+        assert_eq!(decrypt_group_image(&encrypted, &image_key).unwrap(), image);
+
+        let mut tampered = encrypted.clone();
+        tampered[0] ^= 1;
+        assert!(matches!(decrypt_group_image(&tampered, &image_key), Err(MarmotError::CryptoError(_))));
+    }
+
+    #[test]
+    fn export_group_info_is_a_tls_encoded_group_info_message() {
+        use openmls::prelude::tls_codec::Deserialize;
+        use openmls::prelude::{MlsMessageBodyIn, MlsMessageIn};
+
+        let (alice, _bob, group_id) = joined_pair();
+        let group_info = alice.export_group_info_mls(&group_id).unwrap();
+
+        let message = MlsMessageIn::tls_deserialize_exact(&group_info).unwrap();
+        assert!(matches!(message.extract(), MlsMessageBodyIn::GroupInfo(_)));
+    }
+
+    #[test]
+    fn members_json_serializes_both_versions() {
+        let (alice, bob, group_id) = joined_pair();
+        let alice_key = alice.keys.public_key().to_hex();
+        let bob_key = bob.keys.public_key().to_hex();
+        let members = vec![alice_key.clone(), bob_key.clone()];
+
+        let v1: serde_json::Value = serde_json::from_str(&alice.members_json(&group_id, &members)).unwrap();
+        assert_eq!(v1, serde_json::json!([alice_key, bob_key]));
+
+        alice.set_members_json_version(2).unwrap();
+        let v2: serde_json::Value = serde_json::from_str(&alice.members_json(&group_id, &members)).unwrap();
+        assert_eq!(
+            v2,
+            serde_json::json!({
+                "v": 2,
+                "members": [
+                    { "pubkey": alice_key, "admin": true },
+                    { "pubkey": bob_key, "admin": false },
+                ],
+            })
+        );
+
+        assert!(matches!(alice.set_members_json_version(3), Err(MarmotError::InvalidState(_))));
+        let config = ClientConfig::from_json(r#"{"members_json_version": 2}"#).unwrap();
+        assert_eq!(config.members_json_version, Some(2));
+    }
+
+    #[test]
+    fn storage_failures_are_retried() {
+        let alice = new_client();
+        let (group_id, _) = alice.create_group("flaky").unwrap();
+
+        // A storage backend that fails twice, then succeeds
+        fail_storage_writes(&alice, 2);
+        alice.reset_group(&group_id).unwrap();
+        assert!(alice.get_group_info(&group_id).is_none());
+        assert!(alice.group_exists(&group_id).unwrap());
+    }
+
+    #[test]
+    fn storage_failures_beyond_the_retry_count_are_reported() {
+        let alice = new_client();
+        let (group_id, _) = alice.create_group("flaky").unwrap();
+
+        alice.set_storage_retry_count(1);
+        fail_storage_writes(&alice, 2);
+        assert!(alice.reset_group(&group_id).unwrap_err().is_storage());
+
+        // The failed attempts left the group ready for another reset
+        alice.reset_group(&group_id).unwrap();
+        assert!(alice.get_group_info(&group_id).is_none());
+    }
+
+    #[test]
+    fn other_errors_are_not_retried() {
+        use openmls::prelude::OpenMlsProvider;
+
+        let alice = new_client();
+        fail_storage_writes(&alice, 1);
+        assert!(matches!(alice.reset_group(&[7; 32]), Err(MarmotError::GroupNotFound(_))));
+        assert_eq!(alice.mdk.read().provider.storage().pending_write_faults(), 1);
+    }
+
+    #[test]
+    fn group_admins_are_deduplicated_and_validated() {
+        let alice = new_client();
+        let bob = new_client().keys.public_key().to_hex();
+
+        let admins = serde_json::json!([bob, bob, alice.keys.public_key().to_hex()]).to_string();
+        let (group_id, _) = alice.create_group_with_admins("admins", &admins).unwrap();
+        let mut stored = alice.group_admins(&group_id).unwrap();
+        stored.sort();
+        let mut expected = vec![alice.keys.public_key().to_hex(), bob.clone()];
+        expected.sort();
+        assert_eq!(stored, expected);
+
+        let invalid = serde_json::json!([bob, "not-a-key"]).to_string();
+        match alice.create_group_with_admins("admins", &invalid) {
+            Err(MarmotError::InvalidKey(msg)) => assert!(msg.contains("not-a-key")),
+            other => panic!("expected an invalid key error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn group_extensions_validate_the_extension_type() {
+        let alice = new_client();
+        let (group_id, _) = alice.create_group("extensions").unwrap();
+
+        assert_eq!(alice.group_extension(&group_id, 0xFF01).unwrap(), None);
+        assert!(alice.group_extension(&group_id, NOSTR_GROUP_DATA_EXTENSION_TYPE).unwrap().is_some());
+
+        for reserved in [0x0000, 0x0002, 0x000A, 0x2A2A, NOSTR_GROUP_DATA_EXTENSION_TYPE] {
+            let result = alice.set_group_extension(&group_id, reserved, b"topic");
+            assert!(matches!(result, Err(MarmotError::InvalidState(_))), "0x{:04x} accepted", reserved);
+        }
+
+        let result = alice.set_group_extension(&group_id, 0xF001, b"topic");
+        assert!(matches!(result, Err(MarmotError::InvalidState(_))));
+        assert!(matches!(alice.group_extension(b"missing", 0xFF01), Err(MarmotError::GroupNotFound(_))));
+    }
+
+    #[test]
+    fn group_extensions_round_trip_through_a_commit() {
+        let (alice, bob, group_id) = joined_pair();
+        let epoch = alice.current_epoch(&group_id).unwrap();
+
+        let commit = alice.set_group_extension(&group_id, 0xFF01, b"topic").unwrap();
+        assert_eq!(alice.current_epoch(&group_id), Some(epoch + 1));
+        assert_eq!(alice.group_extension(&group_id, 0xFF01).unwrap().as_deref(), Some(&b"topic"[..]));
+
+        assert!(matches!(bob.process_message(&commit).unwrap(), ProcessedEvent::Commit { .. }));
+        assert_eq!(bob.group_extension(&group_id, 0xFF01).unwrap().as_deref(), Some(&b"topic"[..]));
+
+        // Setting it again replaces the value rather than adding a second copy
+        let commit = alice.set_group_extension(&group_id, 0xFF01, b"pinned").unwrap();
+        bob.process_message(&commit).unwrap();
+        assert_eq!(bob.group_extension(&group_id, 0xFF01).unwrap().as_deref(), Some(&b"pinned"[..]));
+
+        // The group data extension MDK manages is untouched
+        assert_eq!(bob.get_group_info(&group_id).unwrap().0, alice.get_group_info(&group_id).unwrap().0);
+    }
+
+    #[test]
+    fn binary_payloads_round_trip() {
+        let (alice, bob, group_id) = joined_pair();
+        let payload = vec![0x00, 0xFF, 0x80, b'a', 0x00, 0xFE];
+
+        let event = alice.encrypt_binary(&group_id, &payload).unwrap();
+        let (sender, data, _) = bob.decrypt_binary(&group_id, &event).unwrap();
+        assert_eq!(sender, alice.keys.public_key().to_hex());
+        assert_eq!(data, payload);
+
+        let text = alice.encrypt_message(&group_id, "text").unwrap();
+        assert!(matches!(bob.decrypt_binary(&group_id, &text), Err(MarmotError::InvalidState(_))));
+    }
+
+    #[test]
+    fn stats_count_groups_and_members() {
+        let (alice, _bob, _) = joined_pair();
+        alice.create_group("solo").unwrap();
+
+        assert_eq!(
+            alice.stats().unwrap(),
+            ClientStats { group_count: 2, total_members: 3, pending_commits: 0 }
+        );
+    }
+
+    #[test]
+    fn member_metadata_reads_back_what_was_set() {
+        let (alice, bob) = (new_client(), new_client());
+        let bob_key = bob.keys.public_key().to_hex();
+        assert_eq!(alice.get_member_metadata(&bob_key).unwrap(), None);
+
+        alice.set_member_metadata(&bob_key.to_uppercase(), "Bob", Some("https://example.com/bob.png")).unwrap();
+        assert_eq!(
+            alice.get_member_metadata(&bob_key).unwrap(),
+            Some(MemberMetadata { name: "Bob".into(), picture_url: Some("https://example.com/bob.png".into()) })
+        );
+
+        assert!(matches!(alice.set_member_metadata("not a key", "x", None), Err(MarmotError::InvalidKey(_))));
+    }
+
+    #[test]
+    fn rekey_policy_fires_after_max_messages_and_resets_on_update() {
+        let (alice, _bob, group_id) = joined_pair();
+        alice.set_rekey_policy(&group_id, 5, 0).unwrap();
+
+        for _ in 0..4 {
+            alice.encrypt_message(&group_id, "hi").unwrap();
+        }
+        assert!(!alice.should_rekey(&group_id));
+
+        alice.encrypt_message(&group_id, "hi").unwrap();
+        assert!(alice.should_rekey(&group_id));
+
+        alice.update_keys(&group_id).unwrap();
+        assert!(!alice.should_rekey(&group_id));
+    }
+
+    #[test]
+    fn creation_info_reports_the_creator() {
+        let (alice, bob, group_id) = joined_pair();
+        let alice_key = alice.keys.public_key().to_hex();
+
+        let created = alice.group_creation_info(&group_id).unwrap();
+        assert_eq!(created.creator.as_deref(), Some(alice_key.as_str()));
+        assert!(created.created_at.is_some());
+        assert_eq!(alice.get_group_info(&group_id).unwrap().4, created);
+
+        assert_eq!(bob.group_creation_info(&group_id).unwrap(), created);
+
+        // A later joiner reads it from the group too
+        let carol = new_client();
+        let added = alice.add_member(&group_id, &key_package_event(&carol)).unwrap();
+        carol.process_welcome(&welcome_input(&added)).unwrap();
+        assert_eq!(carol.group_creation_info(&group_id).unwrap(), created);
+        assert_eq!(carol.get_group_info(&group_id).unwrap().4, created);
+
+        let state = alice.export_group_state(&group_id).unwrap();
+        let handoff = MarmotClient::new(&alice.keys.secret_key().to_secret_hex(), &alice_key, None).unwrap();
+        handoff.import_group_state(&group_id, &state).unwrap();
+        assert_eq!(handoff.group_creation_info(&group_id).unwrap(), created);
+    }
+
+    #[test]
+    fn observer_reads_but_cannot_change_groups() {
+        let alice = new_client();
+        let keys = Keys::generate();
+        let observer = MarmotClient::new_observer(&keys.secret_key().to_secret_hex(), &keys.public_key().to_hex()).unwrap();
+
+        let (group_id, _) = alice.create_group("audited").unwrap();
+        let added = alice.add_member(&group_id, &key_package_event(&observer)).unwrap();
+        observer.process_welcome(&welcome_input(&added)).unwrap();
+
+        let event = alice.encrypt_message(&group_id, "on the record").unwrap();
+        let (_, plaintext, _, _) = observer.decrypt_message(&group_id, &event).unwrap();
+        assert_eq!(plaintext, "on the record");
+        assert!(observer.get_group_info(&group_id).is_some());
+
+        let err = observer.update_keys(&group_id).unwrap_err();
+        assert!(matches!(err, MarmotError::InvalidState(ref m) if m == "observer client is read-only"));
+        assert!(observer.remove_member(&group_id, &alice.keys.public_key().to_hex()).is_err());
+        assert!(observer.create_group("mine").is_err());
+
+        // Nor replace or discard group state
+        assert!(observer.reset_group(&group_id).is_err());
+        let state = alice.export_group_state(&group_id).unwrap();
+        assert!(matches!(observer.import_group_state(&group_id, &state), Err(MarmotError::InvalidState(ref m)) if m == "observer client is read-only"));
+        let backup = alice.export_all().unwrap();
+        assert!(matches!(observer.import_all(&backup), Err(MarmotError::InvalidState(ref m)) if m == "observer client is read-only"));
+        assert!(observer.get_group_info(&group_id).is_some());
+    }
+
+    #[test]
+    fn supported_ciphersuites_include_the_default() {
+        let supported = supported_ciphersuites();
+        assert!(!supported.is_empty());
+
+        let default = MDK::builder(MdkMemoryStorage::new()).build().ciphersuite;
+        assert!(supported.iter().any(|cs| cs.code == default as u16));
+    }
+
+    #[test]
+    fn clients_from_raw_secret_keys_match_the_hex_path() {
+        let keys = Keys::generate();
+
+        let secret = hex::decode(keys.secret_key().to_secret_hex()).unwrap();
+        let from_bytes = MarmotClient::new_from_secret_key(&secret).unwrap();
+        let from_hex = MarmotClient::new(&keys.secret_key().to_secret_hex(), &keys.public_key().to_hex(), None).unwrap();
+        assert_eq!(from_bytes.keys.public_key(), from_hex.keys.public_key());
+
+        assert!(matches!(MarmotClient::new_from_secret_key(&[1; 31]), Err(MarmotError::InvalidKey(_))));
+    }
+
+    #[test]
+    fn config_selects_memory_storage_and_relays() {
+        let keys = Keys::generate();
+        let secret = keys.secret_key().to_secret_hex();
+
+        let config = ClientConfig::from_json(
+            r#"{ "storage": "memory", "relays": ["wss://relay.example"], "ciphersuite": 1, "future_option": true }"#,
+        )
+        .unwrap();
+        let client = MarmotClient::new_with_config(&secret, "", &config).unwrap();
+        assert_eq!(client.key_package_relays(), vec![RelayUrl::parse("wss://relay.example").unwrap()]);
+        let (group_id, _) = client.create_group("configured").unwrap();
+        assert_eq!(client.group_relays(&group_id).unwrap(), vec!["wss://relay.example".to_string()]);
+
+        // An empty config is the same as `new`
+        assert!(MarmotClient::new_with_config(&secret, "", &ClientConfig::from_json("{}").unwrap()).is_ok());
+    }
+
+    #[test]
+    fn config_with_sqlite_storage_is_unsupported() {
+        let keys = Keys::generate();
+        let secret = keys.secret_key().to_secret_hex();
+
+        let config = ClientConfig::from_json(
+            r#"{ "storage": "sqlite", "db_path": "/tmp/marmot.db", "relays": ["wss://relay.example"] }"#,
+        )
+        .unwrap();
+        assert!(matches!(MarmotClient::new_with_config(&secret, "", &config), Err(MarmotError::Unsupported(_))));
+
+        let config = ClientConfig::from_json(r#"{ "storage": "sqlite" }"#).unwrap();
+        assert!(matches!(MarmotClient::new_with_config(&secret, "", &config), Err(MarmotError::InvalidState(_))));
+
+        assert!(ClientConfig::from_json(r#"{ "storage": "files" }"#).is_err());
+    }
+
+    #[test]
+    fn key_packages_list_at_most_the_relay_cap() {
+        let mut alice = new_client();
+        alice.default_relays = (0..10)
+            .map(|i| RelayUrl::parse(&format!("wss://relay{}.example", i)).unwrap())
+            .collect();
+
+        let kp: serde_json::Value = serde_json::from_slice(&alice.generate_key_package().unwrap()).unwrap();
+        let relays: Vec<&str> = kp["tags"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|tag| tag[0] == "relays")
+            .unwrap()
+            .as_array()
+            .unwrap()[1..]
+            .iter()
+            .map(|relay| relay.as_str().unwrap())
+            .collect();
+
+        let expected: Vec<String> = (0..5).map(|i| format!("wss://relay{}.example", i)).collect();
+        assert_eq!(relays, expected);
+    }
+
+    #[test]
+    fn identity_backup_round_trips() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let backup = alice.export_identity_backup("correct horse").unwrap();
+        let restored = MarmotClient::import_identity_backup(&backup, "correct horse").unwrap();
+
+        assert_eq!(restored.keys.public_key(), alice.keys.public_key());
+        assert!(restored.group_exists(&group_id).unwrap());
+
+        // The restored device reads the group's messages
+        let event = bob.encrypt_message(&group_id, "on the new device").unwrap();
+        let (sender, plaintext, _, _) = restored.decrypt_message(&group_id, &event).unwrap();
+        assert_eq!(sender, bob.keys.public_key().to_hex());
+        assert_eq!(plaintext, "on the new device");
+    }
+
+    #[test]
+    fn identity_backup_rejects_a_wrong_passphrase() {
+        let backup = new_client().export_identity_backup("correct horse").unwrap();
+
+        let result = MarmotClient::import_identity_backup(&backup, "battery staple");
+        assert!(matches!(result, Err(MarmotError::CryptoError(_))));
+    }
+
+    #[test]
+    fn epoch_history_records_each_commit() {
+        let (alice, bob, group_id) = joined_pair();
+        let alice_key = alice.keys.public_key().to_hex();
+        let carol = new_client();
+        let carol_key = carol.keys.public_key().to_hex();
+
+        let added = alice.add_member(&group_id, &key_package_event(&carol)).unwrap();
+        let added: serde_json::Value = serde_json::from_slice(&added).unwrap();
+        bob.process_commit(&group_id, added["commit"].to_string().as_bytes()).unwrap();
+        let removed = alice.remove_member(&group_id, &carol_key).unwrap();
+        bob.process_commit(&group_id, &removed).unwrap();
+        let updated = alice.update_keys(&group_id).unwrap();
+        bob.process_commit(&group_id, &updated).unwrap();
+
+        let history = bob.epoch_history(&group_id).unwrap();
+        let kinds: Vec<EpochChangeKind> = history.iter().map(|record| record.kind).collect();
+        assert_eq!(kinds, vec![EpochChangeKind::Add, EpochChangeKind::Remove, EpochChangeKind::Update]);
+        assert!(history.iter().all(|record| record.actor.as_deref() == Some(alice_key.as_str())));
+        assert_eq!(history.last().unwrap().epoch, bob.current_epoch(&group_id).unwrap());
+
+        let history = alice.epoch_history(&group_id).unwrap();
+        // Alice's history also includes adding Bob
+        assert_eq!(history.len(), 4);
+        assert!(history.iter().all(|record| record.actor.as_deref() == Some(alice_key.as_str())));
+
+        // A member's key update is attributed to that member
+        let bob_update = bob.update_keys(&group_id).unwrap();
+        alice.process_commit(&group_id, &bob_update).unwrap();
+        let record = alice.epoch_history(&group_id).unwrap().pop().unwrap();
+        assert_eq!(record.kind, EpochChangeKind::Update);
+        assert_eq!(record.actor, Some(bob.keys.public_key().to_hex()));
+    }
+
+    #[test]
+    fn epoch_history_records_a_commit_that_adds_and_removes() {
+        let (alice, bob, group_id) = joined_pair();
+        let (carol, dave) = (new_client(), new_client());
+
+        let added: serde_json::Value =
+            serde_json::from_slice(&alice.add_member(&group_id, &key_package_event(&dave)).unwrap()).unwrap();
+        bob.process_commit(&group_id, added["commit"].to_string().as_bytes()).unwrap();
+        dave.process_welcome(&welcome_input(&serde_json::to_vec(&added).unwrap())).unwrap();
+
+        // Alice's next commit adds Carol and commits Dave's self-removal
+        let proposal = dave.propose_self_removal(&group_id).unwrap();
+        alice.process_message(&proposal).unwrap();
+        bob.process_message(&proposal).unwrap();
+        let commit: serde_json::Value =
+            serde_json::from_slice(&alice.add_member(&group_id, &key_package_event(&carol)).unwrap()).unwrap();
+        bob.process_commit(&group_id, commit["commit"].to_string().as_bytes()).unwrap();
+
+        let alice_key = alice.keys.public_key().to_hex();
+        for client in [&alice, &bob] {
+            let record = client.epoch_history(&group_id).unwrap().pop().unwrap();
+            assert_eq!(record.kind, EpochChangeKind::AddRemove);
+            assert_eq!(record.actor.as_deref(), Some(alice_key.as_str()));
+        }
+    }
+
+    #[test]
+    fn epoch_history_survives_a_state_export() {
+        let (alice, _bob, group_id) = joined_pair();
+        alice.update_keys(&group_id).unwrap();
+        let history = alice.epoch_history(&group_id).unwrap();
+
+        let state = alice.export_group_state(&group_id).unwrap();
+        let restored = MarmotClient::new(&alice.keys.secret_key().to_secret_hex(), "", None).unwrap();
+        restored.import_group_state(&group_id, &state).unwrap();
+
+        let restored_history = restored.epoch_history(&group_id).unwrap();
+        assert_eq!(restored_history.len(), history.len());
+        assert!(restored_history.iter().zip(&history).all(|(a, b)| a.epoch == b.epoch && a.kind == b.kind));
+    }
+
+    #[test]
+    fn reset_group_leaves_other_groups_untouched() {
+        let alice = new_client();
+        let (broken, _) = alice.create_group("broken").unwrap();
+        let (healthy, _) = alice.create_group("healthy").unwrap();
+
+        alice.reset_group(&broken).unwrap();
+
+        assert!(alice.get_group_info(&broken).is_none());
+        assert!(alice.encrypt_message(&broken, "hello").is_err());
+        assert_eq!(alice.get_group_info(&healthy).unwrap().0, "healthy");
+        alice.encrypt_message(&healthy, "hello").unwrap();
+    }
+
+    #[test]
+    fn rotation_count_increments_on_self_updates() {
+        let (alice, _bob, group_id) = joined_pair();
+        let before = alice.rotation_count(&group_id).unwrap();
+
+        alice.update_keys(&group_id).unwrap();
+        alice.update_keys(&group_id).unwrap();
+
+        assert_eq!(alice.rotation_count(&group_id).unwrap(), before + 2);
+
+        // The count is part of the exported state
+        let state = alice.export_group_state(&group_id).unwrap();
+        let restored = MarmotClient::new(&alice.keys.secret_key().to_secret_hex(), "", None).unwrap();
+        restored.import_group_state(&group_id, &state).unwrap();
+        assert_eq!(restored.rotation_count(&group_id).unwrap(), before + 2);
+    }
+
+    #[test]
+    fn the_creator_is_the_only_admin_of_a_new_group() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let admins = alice.group_admins(&group_id).unwrap();
+        assert_eq!(admins, vec![alice.keys.public_key().to_hex()]);
+        assert_eq!(bob.group_admins(&group_id).unwrap(), admins);
+
+        assert!(matches!(alice.group_admins(&[7; 32]), Err(MarmotError::GroupNotFound(_))));
+    }
+
+    #[test]
+    fn a_staged_commit_is_pending_until_merged() {
+        let alice = new_client();
+        let bob = new_client();
+        let (group_id, _) = alice.create_group("staged").unwrap();
+        assert!(!alice.has_pending_commit(&group_id).unwrap());
+
+        let mls_group_id = mdk_core::GroupId::from_slice(&group_id);
+        let event: Event = serde_json::from_slice(&key_package_event(&bob)).unwrap();
+        alice.mdk.write().add_members(&mls_group_id, &[event]).unwrap();
+        assert!(alice.has_pending_commit(&group_id).unwrap());
+
+        alice.mdk.write().merge_pending_commit(&mls_group_id).unwrap();
+        assert!(!alice.has_pending_commit(&group_id).unwrap());
+
+        assert!(matches!(alice.has_pending_commit(&[7; 32]), Err(MarmotError::GroupNotFound(_))));
+    }
+
+    #[test]
+    fn sign_event_signs_with_the_client_key() {
+        let alice = new_client();
+        let unsigned = EventBuilder::new(Kind::Metadata, r#"{"name":"alice"}"#).build(alice.keys.public_key());
+
+        let signed = alice.sign_event(&serde_json::to_string(&unsigned).unwrap()).unwrap();
+        let event: Event = serde_json::from_str(&signed).unwrap();
+        assert_eq!(event.pubkey, alice.keys.public_key());
+        assert_eq!(event.content, r#"{"name":"alice"}"#);
+        assert!(event.verify().is_ok());
+
+        let other = EventBuilder::new(Kind::Metadata, "{}").build(Keys::generate().public_key());
+        assert!(matches!(alice.sign_event(&serde_json::to_string(&other).unwrap()), Err(MarmotError::InvalidKey(_))));
+        assert!(matches!(alice.sign_event("{}"), Err(MarmotError::SerializationError(_))));
+    }
+
+    #[test]
+    fn group_relays_come_from_the_stored_group() {
+        let mut alice = new_client();
+        alice.default_relays = vec![
+            RelayUrl::parse("wss://relay.one.example").unwrap(),
+            RelayUrl::parse("wss://relay.two.example").unwrap(),
+        ];
+        let (group_id, _) = alice.create_group("relays").unwrap();
+
+        let mut relays = alice.group_relays(&group_id).unwrap();
+        relays.sort();
+        assert_eq!(relays, vec!["wss://relay.one.example", "wss://relay.two.example"]);
+    }
+
+    #[test]
+    fn rename_group_updates_the_group_info() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let commit = alice.rename_group(&group_id, "renamed").unwrap();
+        assert_eq!(alice.get_group_info(&group_id).unwrap().0, "renamed");
+
+        bob.process_commit(&group_id, &commit).unwrap();
+        assert_eq!(bob.get_group_info(&group_id).unwrap().0, "renamed");
+
+        assert!(alice.rename_group(&group_id, "  ").is_err());
+        assert_eq!(alice.get_group_info(&group_id).unwrap().0, "renamed");
+    }
+
+    #[test]
+    fn set_group_description_sets_and_clears_the_description() {
+        let (alice, bob, group_id) = joined_pair();
+
+        let commit = alice.set_group_description(&group_id, "weekly planning").unwrap();
+        let (name, _, _, description, _) = alice.get_group_info(&group_id).unwrap();
+        assert_eq!(name, "test group");
+        assert_eq!(description, "weekly planning");
+
+        bob.process_commit(&group_id, &commit).unwrap();
+        assert_eq!(bob.get_group_info(&group_id).unwrap().3, "weekly planning");
+
+        alice.set_group_description(&group_id, "").unwrap();
+        assert_eq!(alice.get_group_info(&group_id).unwrap().3, "");
+    }
+
+    #[test]
+    fn set_group_relays_replaces_the_relay_list() {
+        let alice = new_client();
+        let (group_id, _) = alice.create_group("relays").unwrap();
+
+        alice.set_group_relays(&group_id, r#"["wss://relay.new.example"]"#).unwrap();
+
+        assert_eq!(alice.group_relays(&group_id).unwrap(), vec!["wss://relay.new.example"]);
+    }
+
+    #[test]
+    fn set_group_relays_rejects_empty_and_invalid_lists() {
+        let alice = new_client();
+        let (group_id, _) = alice.create_group("relays").unwrap();
+
+        assert!(alice.set_group_relays(&group_id, "[]").is_err());
+        assert!(alice.set_group_relays(&group_id, r#"["not a url"]"#).is_err());
     }
 }
@@ -1,5 +1,7 @@
 //! Error types for the Marmot native library.
 
+use std::fmt;
+
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -31,10 +33,169 @@ pub enum MarmotError {
     #[error("Not a member of the group")]
     NotMember,
 
+    #[error("Message already processed")]
+    DuplicateMessage,
+
+    #[error("Storage error: {0}")]
+    StorageError(String),
+
+    #[error("Key package expired: {0}")]
+    KeyPackageExpired(String),
+
+    #[error("Unsupported ciphersuite: {0}")]
+    UnsupportedCiphersuite(String),
+
+    #[error("Not supported: {0}")]
+    Unsupported(String),
+
+    /// The message was encrypted at an epoch whose secrets this client no longer
+    /// holds; retrying will not help
+    #[error("Epoch too old: {0}")]
+    EpochTooOld(String),
+
+    #[error("Welcome not found: {0}")]
+    WelcomeNotFound(String),
+
+    #[error("MDK error ({kind:?}): {message}")]
+    Mdk { kind: MdkErrorKind, message: String },
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
+/// Machine-readable category of an MDK failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MdkErrorKind {
+    /// MDK has no group with the given ID
+    GroupNotFound,
+    /// The message or commit is for an epoch the group is not at
+    Epoch,
+    /// A key package was invalid or could not be created
+    KeyPackage,
+    /// A welcome was invalid or could not be processed
+    Welcome,
+    /// An incoming message could not be processed
+    Message,
+    /// MDK's storage backend failed; the operation may succeed if retried
+    Storage,
+    /// Any other MLS protocol failure
+    Other,
+}
+
+impl MdkErrorKind {
+    /// Classify an MDK error by its variant.
+    fn of(err: &mdk_core::Error) -> Self {
+        use mdk_core::Error;
+
+        match err {
+            Error::GroupNotFound { .. } => MdkErrorKind::GroupNotFound,
+            // MDK reports its group storage failing as a group error
+            Error::Group { .. } => MdkErrorKind::Storage,
+            Error::ProcessMessageWrongEpoch { .. } => MdkErrorKind::Epoch,
+            Error::KeyPackage { .. } => MdkErrorKind::KeyPackage,
+            Error::Welcome { .. } | Error::InvalidWelcomeMessage { .. } => MdkErrorKind::Welcome,
+            Error::Message { .. }
+            | Error::MessageFromNonMember { .. }
+            | Error::CannotDecryptOwnMessage { .. }
+            | Error::ProcessMessageWrongGroupId { .. }
+            | Error::ProcessMessageUseAfterEviction { .. }
+            | Error::ProcessMessageOther { .. } => MdkErrorKind::Message,
+            _ => MdkErrorKind::Other,
+        }
+    }
+}
+
+impl MarmotError {
+    /// Stable numeric code for this error, reported via `marmot_get_last_error_code`.
+    /// Zero is reserved for "no error".
+    pub fn code(&self) -> i32 {
+        match self {
+            MarmotError::InvalidKey(_) => 1,
+            MarmotError::GroupNotFound(_) => 2,
+            MarmotError::MlsError(_) => 3,
+            MarmotError::SerializationError(_) => 4,
+            MarmotError::CryptoError(_) => 5,
+            MarmotError::InvalidState(_) => 6,
+            MarmotError::MemberNotFound(_) => 7,
+            MarmotError::AlreadyMember => 8,
+            MarmotError::NotMember => 9,
+            MarmotError::Internal(_) => 10,
+            MarmotError::DuplicateMessage => 11,
+            MarmotError::StorageError(_) => 12,
+            MarmotError::KeyPackageExpired(_) => 17,
+            MarmotError::UnsupportedCiphersuite(_) => 18,
+            MarmotError::Unsupported(_) => 19,
+            MarmotError::EpochTooOld(_) => 20,
+            MarmotError::WelcomeNotFound(_) => 21,
+            // Kinds with an existing equivalent share its code
+            MarmotError::Mdk { kind, .. } => match kind {
+                MdkErrorKind::GroupNotFound => 2,
+                MdkErrorKind::Other => 3,
+                MdkErrorKind::Storage => 12,
+                MdkErrorKind::Epoch => 13,
+                MdkErrorKind::KeyPackage => 14,
+                MdkErrorKind::Welcome => 15,
+                MdkErrorKind::Message => 16,
+            },
+        }
+    }
+
+    /// Map an MDK error, preserving its kind so the host can tell the MLS
+    /// protocol failures apart.
+    pub fn from_mdk(context: &str, err: mdk_core::Error) -> Self {
+        MarmotError::Mdk {
+            kind: MdkErrorKind::of(&err),
+            message: format!("{}: {}", context, err),
+        }
+    }
+
+    /// Map a failure of the storage layer, read or written directly through
+    /// MDK's storage provider. The operation may succeed if retried.
+    pub fn storage(context: &str, err: impl fmt::Display) -> Self {
+        MarmotError::StorageError(format!("{}: {}", context, err))
+    }
+
+    /// Whether the storage layer failed, so the operation may succeed if retried.
+    pub fn is_storage(&self) -> bool {
+        matches!(
+            self,
+            MarmotError::StorageError(_) | MarmotError::Mdk { kind: MdkErrorKind::Storage, .. }
+        )
+    }
+
+    /// Prefix this error's message with `context`, keeping its variant and code.
+    /// Variants without a message are returned unchanged.
+    pub fn context(self, context: &str) -> Self {
+        let prefix = |message: String| format!("{}: {}", context, message);
+        match self {
+            MarmotError::InvalidKey(m) => MarmotError::InvalidKey(prefix(m)),
+            MarmotError::GroupNotFound(m) => MarmotError::GroupNotFound(prefix(m)),
+            MarmotError::MlsError(m) => MarmotError::MlsError(prefix(m)),
+            MarmotError::SerializationError(m) => MarmotError::SerializationError(prefix(m)),
+            MarmotError::CryptoError(m) => MarmotError::CryptoError(prefix(m)),
+            MarmotError::InvalidState(m) => MarmotError::InvalidState(prefix(m)),
+            MarmotError::MemberNotFound(m) => MarmotError::MemberNotFound(prefix(m)),
+            MarmotError::StorageError(m) => MarmotError::StorageError(prefix(m)),
+            MarmotError::KeyPackageExpired(m) => MarmotError::KeyPackageExpired(prefix(m)),
+            MarmotError::UnsupportedCiphersuite(m) => MarmotError::UnsupportedCiphersuite(prefix(m)),
+            MarmotError::Unsupported(m) => MarmotError::Unsupported(prefix(m)),
+            MarmotError::EpochTooOld(m) => MarmotError::EpochTooOld(prefix(m)),
+            MarmotError::WelcomeNotFound(m) => MarmotError::WelcomeNotFound(prefix(m)),
+            MarmotError::Mdk { kind, message } => MarmotError::Mdk { kind, message: prefix(message) },
+            MarmotError::Internal(m) => MarmotError::Internal(prefix(m)),
+            other @ (MarmotError::AlreadyMember | MarmotError::NotMember | MarmotError::DuplicateMessage) => other,
+        }
+    }
+
+    /// The MDK error kind, if this error came from MDK.
+    pub fn mdk_kind(&self) -> Option<MdkErrorKind> {
+        match self {
+            MarmotError::Mdk { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
+}
+
 impl From<std::string::FromUtf8Error> for MarmotError {
     fn from(err: std::string::FromUtf8Error) -> Self {
         MarmotError::SerializationError(err.to_string())
@@ -52,3 +213,40 @@ impl From<hex::FromHexError> for MarmotError {
         MarmotError::InvalidKey(err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mdk_errors_are_classified_by_variant() {
+        let err = MarmotError::from_mdk("Failed to get group", mdk_core::Error::GroupNotFound);
+        assert_eq!(err.mdk_kind(), Some(MdkErrorKind::GroupNotFound));
+        assert_eq!(err.code(), MarmotError::GroupNotFound(String::new()).code());
+
+        let err = MarmotError::from_mdk("Failed to add member", mdk_core::Error::KeyPackage("bad".into()));
+        assert_eq!(err.mdk_kind(), Some(MdkErrorKind::KeyPackage));
+        assert_eq!(err.code(), 14);
+        assert!(err.to_string().contains("Failed to add member"));
+        assert!(!err.is_storage());
+    }
+
+    #[test]
+    fn context_keeps_the_error_code() {
+        let err = MarmotError::storage("Failed to save group", "write failed")
+            .context("Health check failed to create a group");
+        assert!(err.is_storage());
+        assert!(err.to_string().starts_with("Storage error: Health check failed to create a group: "));
+
+        let err = MarmotError::from_mdk("Failed to add member", mdk_core::Error::KeyPackage("bad".into())).context("step");
+        assert_eq!(err.mdk_kind(), Some(MdkErrorKind::KeyPackage));
+        assert_eq!(MarmotError::NotMember.context("step").code(), MarmotError::NotMember.code());
+    }
+
+    #[test]
+    fn unrecognized_mdk_errors_are_other_mls_failures() {
+        let err = MarmotError::from_mdk("Failed to load signer", mdk_core::Error::CantLoadSigner);
+        assert_eq!(err.mdk_kind(), Some(MdkErrorKind::Other));
+        assert_eq!(err.code(), MarmotError::MlsError(String::new()).code());
+    }
+}
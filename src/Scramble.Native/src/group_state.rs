@@ -0,0 +1,245 @@
+//! Export and import of a group's complete state: MDK's group record and relays,
+//! plus the OpenMLS group state and key material this member holds for it.
+//!
+//! Neither MDK nor OpenMLS can serialize a group, so the OpenMLS entities are
+//! copied one by one through the storage provider. Their values are carried as
+//! the JSON the storage holds them in, so a blob is tied to OpenMLS' storage
+//! version.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use mdk_core::MDK;
+use mdk_storage_traits::groups::types::Group;
+use mdk_storage_traits::groups::GroupStorage;
+use nostr::{PublicKey, RelayUrl};
+use openmls::prelude::{BasicCredential, OpenMlsProvider};
+use openmls_basic_credential::SignatureKeyPair;
+use openmls_traits::storage::{traits, Entity, Key, StorageProvider};
+
+use crate::client::{load_mls_group, load_signer, EpochRecord, Storage};
+use crate::error::MarmotError;
+
+/// A stored OpenMLS value in its serialized form.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+struct RawEntity(serde_json::Value);
+
+impl<const VERSION: u16> Entity<VERSION> for RawEntity {}
+impl<const VERSION: u16> Key<VERSION> for RawEntity {}
+
+macro_rules! raw_entity_of {
+    ($($entity:ident),* $(,)?) => {
+        $(impl<const VERSION: u16> traits::$entity<VERSION> for RawEntity {})*
+    };
+}
+
+raw_entity_of!(
+    MlsGroupJoinConfig,
+    TreeSync,
+    GroupContext,
+    InterimTranscriptHash,
+    ConfirmationTag,
+    GroupState,
+    MessageSecrets,
+    ResumptionPskStore,
+    LeafNodeIndex,
+    GroupEpochSecrets,
+    LeafNode,
+    ProposalRef,
+    QueuedProposal,
+    HpkeKeyPair,
+);
+
+/// The OpenMLS state of a group, as this member's storage holds it.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MlsState {
+    join_config: RawEntity,
+    tree: RawEntity,
+    context: RawEntity,
+    interim_transcript_hash: RawEntity,
+    confirmation_tag: RawEntity,
+    group_state: RawEntity,
+    message_secrets: RawEntity,
+    resumption_psk_store: RawEntity,
+    own_leaf_index: RawEntity,
+    epoch_secrets: RawEntity,
+    /// Leaf nodes of this member's pending updates
+    own_leaf_nodes: Vec<RawEntity>,
+    /// Proposals received or sent but not yet committed, with their references
+    queued_proposals: Vec<(RawEntity, RawEntity)>,
+    signer: SignatureKeyPair,
+    /// HPKE key pair of this member's leaf
+    encryption_key_pair: RawEntity,
+    /// HPKE key pairs this member holds for the current epoch
+    epoch_key_pairs: Vec<RawEntity>,
+}
+
+/// Everything needed to restore a group on another client with the same identity.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct GroupSnapshot {
+    pub group: Group,
+    pub relays: BTreeSet<RelayUrl>,
+    mls: MlsState,
+    /// The exporting client's epoch history of the group, which MDK does not keep
+    #[serde(default)]
+    pub epoch_history: Vec<EpochRecord>,
+}
+
+/// Read a group's complete state from MDK's storage.
+pub(crate) fn export_group(
+    mdk: &MDK<Storage>,
+    group_id: &[u8],
+    epoch_history: Vec<EpochRecord>,
+) -> Result<GroupSnapshot, MarmotError> {
+    let mls_group_id = mdk_core::GroupId::from_slice(group_id);
+    let group = mdk.get_group(&mls_group_id)
+        .map_err(|e| MarmotError::from_mdk("Failed to get group", e))?
+        .ok_or_else(|| MarmotError::GroupNotFound(hex::encode(group_id)))?;
+    let relays = mdk.get_relays(&mls_group_id)
+        .map_err(|e| MarmotError::from_mdk("Failed to get relays", e))?;
+
+    let mls_group = load_mls_group(mdk, group_id)?;
+    let own_leaf = mls_group.own_leaf_node().ok_or(MarmotError::NotMember)?;
+    let storage = mdk.provider.storage();
+    let gid = mls_group.group_id();
+
+    let mls = MlsState {
+        join_config: required(storage.mls_group_join_config(gid), "join config")?,
+        tree: required(storage.tree(gid), "tree")?,
+        context: required(storage.group_context(gid), "group context")?,
+        interim_transcript_hash: required(storage.interim_transcript_hash(gid), "interim transcript hash")?,
+        confirmation_tag: required(storage.confirmation_tag(gid), "confirmation tag")?,
+        group_state: required(storage.group_state(gid), "group state")?,
+        message_secrets: required(storage.message_secrets(gid), "message secrets")?,
+        resumption_psk_store: required(storage.resumption_psk_store(gid), "resumption PSK store")?,
+        own_leaf_index: required(storage.own_leaf_index(gid), "own leaf index")?,
+        epoch_secrets: required(storage.group_epoch_secrets(gid), "epoch secrets")?,
+        own_leaf_nodes: storage.own_leaf_nodes(gid).map_err(read_failed)?,
+        queued_proposals: storage.queued_proposals(gid).map_err(read_failed)?,
+        signer: load_signer(mdk, &mls_group)?,
+        encryption_key_pair: required(storage.encryption_key_pair(own_leaf.encryption_key()), "leaf encryption key")?,
+        epoch_key_pairs: storage
+            .encryption_epoch_key_pairs(gid, &mls_group.epoch(), mls_group.own_leaf_index().u32())
+            .map_err(read_failed)?,
+    };
+
+    Ok(GroupSnapshot { group, relays, mls, epoch_history })
+}
+
+/// Write a group's complete state into MDK's storage, except the epoch history,
+/// which the caller keeps. The caller checks that the group does not exist yet.
+/// Fails if the state is not `own_key`'s, as its keys would be useless to this
+/// client, or if a write fails. Either way the OpenMLS state already written is
+/// removed again; only a group record MDK saved before its relays failed to
+/// save is left behind.
+pub(crate) fn import_group(mdk: &MDK<Storage>, own_key: &PublicKey, snapshot: GroupSnapshot) -> Result<(), MarmotError> {
+    let GroupSnapshot { group, relays, mls, .. } = snapshot;
+    let gid = openmls::group::GroupId::from_slice(group.mls_group_id.as_slice());
+    let storage = mdk.provider.storage();
+
+    let mut signer_stored = false;
+    let result = write_mls_state(mdk, own_key, &gid, &mls, &mut signer_stored).and_then(|()| {
+        let mls_group_id = group.mls_group_id.clone();
+        storage.save_group(group)
+            .map_err(|e| MarmotError::storage("Failed to save group", e))?;
+        storage.replace_group_relays(&mls_group_id, relays)
+            .map_err(|e| MarmotError::storage("Failed to save group relays", e))
+    });
+    if result.is_err() {
+        discard_mls_state(mdk, &gid, signer_stored.then_some(&mls.signer));
+    }
+    result
+}
+
+/// Write the OpenMLS part of a snapshot and check it is `own_key`'s.
+/// Sets `signer_stored` once the signature key pair is written.
+fn write_mls_state(
+    mdk: &MDK<Storage>,
+    own_key: &PublicKey,
+    gid: &openmls::group::GroupId,
+    mls: &MlsState,
+    signer_stored: &mut bool,
+) -> Result<(), MarmotError> {
+    let storage = mdk.provider.storage();
+
+    storage.write_mls_join_config(gid, &mls.join_config).map_err(write_failed)?;
+    storage.write_tree(gid, &mls.tree).map_err(write_failed)?;
+    storage.write_context(gid, &mls.context).map_err(write_failed)?;
+    storage.write_interim_transcript_hash(gid, &mls.interim_transcript_hash).map_err(write_failed)?;
+    storage.write_confirmation_tag(gid, &mls.confirmation_tag).map_err(write_failed)?;
+    storage.write_group_state(gid, &mls.group_state).map_err(write_failed)?;
+    storage.write_message_secrets(gid, &mls.message_secrets).map_err(write_failed)?;
+    storage.write_resumption_psk_store(gid, &mls.resumption_psk_store).map_err(write_failed)?;
+    storage.write_own_leaf_index(gid, &mls.own_leaf_index).map_err(write_failed)?;
+    storage.write_group_epoch_secrets(gid, &mls.epoch_secrets).map_err(write_failed)?;
+    for leaf_node in &mls.own_leaf_nodes {
+        storage.append_own_leaf_node(gid, leaf_node).map_err(write_failed)?;
+    }
+    for (proposal_ref, proposal) in &mls.queued_proposals {
+        storage.queue_proposal(gid, proposal_ref, proposal).map_err(write_failed)?;
+    }
+
+    let mls_group = load_mls_group(mdk, gid.as_slice())?;
+    let own_leaf = mls_group.own_leaf_node().ok_or(MarmotError::NotMember)?;
+    let identity = BasicCredential::try_from(own_leaf.credential().clone())
+        .map(|credential| credential.identity().to_vec())
+        .ok();
+    if identity.as_deref() != Some(own_key.to_bytes().as_slice())
+        || own_leaf.signature_key().as_slice() != mls.signer.public()
+    {
+        return Err(MarmotError::InvalidState("Group state belongs to another identity".into()));
+    }
+
+    mls.signer.store(storage).map_err(write_failed)?;
+    *signer_stored = true;
+    storage.write_encryption_key_pair(own_leaf.encryption_key(), &mls.encryption_key_pair).map_err(write_failed)?;
+    storage
+        .write_encryption_epoch_key_pairs(gid, &mls_group.epoch(), mls_group.own_leaf_index().u32(), &mls.epoch_key_pairs)
+        .map_err(write_failed)
+}
+
+/// Remove what `write_mls_state` wrote of a group, including `signer` if it was
+/// stored. Best effort: the import has already failed, so errors are ignored.
+fn discard_mls_state(mdk: &MDK<Storage>, gid: &openmls::group::GroupId, signer: Option<&SignatureKeyPair>) {
+    let storage = mdk.provider.storage();
+
+    if let Ok(mls_group) = load_mls_group(mdk, gid.as_slice()) {
+        if let Some(own_leaf) = mls_group.own_leaf_node() {
+            let _ = storage.delete_encryption_key_pair(own_leaf.encryption_key());
+        }
+        let _ = storage.delete_encryption_epoch_key_pairs(gid, &mls_group.epoch(), mls_group.own_leaf_index().u32());
+    }
+    // Entity by entity, as a partial write may not load as a group
+    let _ = storage.delete_group_config(gid);
+    let _ = storage.delete_tree(gid);
+    let _ = storage.delete_context(gid);
+    let _ = storage.delete_interim_transcript_hash(gid);
+    let _ = storage.delete_confirmation_tag(gid);
+    let _ = storage.delete_group_state(gid);
+    let _ = storage.delete_message_secrets(gid);
+    let _ = storage.delete_all_resumption_psk_secrets(gid);
+    let _ = storage.delete_own_leaf_index(gid);
+    let _ = storage.delete_group_epoch_secrets(gid);
+    let _ = storage.delete_own_leaf_nodes(gid);
+    let _ = storage.clear_proposal_queue::<openmls::group::GroupId, RawEntity>(gid);
+    if let Some(signer) = signer {
+        let _ = SignatureKeyPair::delete(storage, signer.public(), signer.signature_scheme());
+    }
+}
+
+/// Unwrap an entity every group has. A missing one is not a storage failure,
+/// so it is not worth retrying.
+fn required<T, E: fmt::Display>(entity: Result<Option<T>, E>, name: &str) -> Result<T, MarmotError> {
+    entity
+        .map_err(read_failed)?
+        .ok_or_else(|| MarmotError::InvalidState(format!("Group state has no {}", name)))
+}
+
+fn read_failed(err: impl fmt::Display) -> MarmotError {
+    MarmotError::storage("Failed to read group state", err)
+}
+
+fn write_failed(err: impl fmt::Display) -> MarmotError {
+    MarmotError::storage("Failed to write group state", err)
+}
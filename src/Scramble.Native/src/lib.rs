@@ -5,8 +5,15 @@
 
 mod client;
 mod error;
+mod group_state;
 // mod group; // Not needed - using MDK directly
+#[cfg(feature = "test-rng")]
+mod test_rng;
+#[cfg(test)]
+mod test_storage;
 
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::ffi::{c_char, c_int, CStr, CString};
 use std::ptr;
 use std::slice;
@@ -14,35 +21,193 @@ use std::sync::Mutex;
 
 use once_cell::sync::Lazy;
 
-use client::MarmotClient;
+use client::{CommitKind, GroupImageKey, MarmotClient, MessageCallback};
+#[cfg(any(test, feature = "test-transport"))]
+use client::NostrProxyCallback;
+use error::MarmotError;
 
-/// Thread-local storage for the last error message
-static LAST_ERROR: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+/// The last error of the calling thread, with the call that recorded it.
+#[derive(Debug, Clone, serde::Serialize)]
+struct LastErrorRecord {
+    code: i32,
+    message: String,
+    /// The FFI function that failed, without the `marmot_` prefix
+    operation: Option<&'static str>,
+    /// Hex group ID the failed call targeted, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group_id: Option<String>,
+}
+
+/// The FFI call running on the calling thread and the group it targets.
+#[derive(Default)]
+struct CallContext {
+    operation: Option<&'static str>,
+    group_id: Option<String>,
+}
+
+thread_local! {
+    /// Thread-local storage for the last error
+    static LAST_ERROR: RefCell<Option<LastErrorRecord>> = const { RefCell::new(None) };
+    /// Context of the current FFI call, attached to errors it records
+    static CALL_CONTEXT: RefCell<CallContext> = RefCell::new(CallContext::default());
+}
+
+/// Code reported for failures that don't originate from a `MarmotError`
+/// (null pointers, invalid strings passed across the FFI boundary).
+const GENERIC_ERROR_CODE: i32 = -1;
+
+/// An error that can be recorded as the last error.
+trait LastError: ToString {
+    fn code(&self) -> i32 {
+        GENERIC_ERROR_CODE
+    }
+}
+
+impl LastError for &str {}
 
-fn set_last_error(error: impl ToString) {
-    if let Ok(mut guard) = LAST_ERROR.lock() {
-        *guard = Some(error.to_string());
+impl LastError for String {}
+
+impl LastError for MarmotError {
+    fn code(&self) -> i32 {
+        MarmotError::code(self)
     }
 }
 
+/// Addresses of clients created by `marmot_create_client` and not yet destroyed.
+/// Lets managed handles check validity and makes a double destroy a no-op.
+static LIVE_CLIENTS: Lazy<Mutex<HashSet<usize>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+fn record_last_error(code: i32, message: String) {
+    let (operation, group_id) = CALL_CONTEXT.with(|context| {
+        let context = context.borrow();
+        (context.operation, context.group_id.clone())
+    });
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(LastErrorRecord { code, message, operation, group_id }));
+}
+
+fn set_last_error(error: impl LastError) {
+    record_last_error(error.code(), error.to_string());
+}
+
+/// Record an error both on the client and for the calling thread.
+/// The thread's copy keeps `marmot_get_last_error` working for single-client hosts.
+fn set_client_error(client: &MarmotClient, error: impl LastError) {
+    let code = error.code();
+    let message = error.to_string();
+    client.set_last_error(code, message.clone());
+    record_last_error(code, message);
+}
+
 fn clear_last_error() {
-    if let Ok(mut guard) = LAST_ERROR.lock() {
-        *guard = None;
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+}
+
+/// Start an FFI call: clear the last error and remember the operation, so that
+/// errors recorded during the call can be reported with it.
+fn begin_call(operation: &'static str) {
+    clear_last_error();
+    CALL_CONTEXT.with(|context| {
+        *context.borrow_mut() = CallContext { operation: Some(operation), group_id: None };
+    });
+}
+
+/// Borrow a buffer passed across the FFI boundary.
+/// Rejects negative lengths and null pointers with a non-zero length.
+///
+/// # Safety
+/// If `length > 0`, `data` must point to at least `length` readable bytes that
+/// outlive the returned slice.
+unsafe fn ffi_slice<'a>(data: *const u8, length: c_int) -> Result<&'a [u8], MarmotError> {
+    if length < 0 {
+        return Err(MarmotError::InvalidState(format!("Negative buffer length: {}", length)));
+    }
+    if length == 0 {
+        return Ok(&[]);
+    }
+    if data.is_null() {
+        return Err(MarmotError::InvalidState("Buffer is null".into()));
+    }
+
+    Ok(slice::from_raw_parts(data, length as usize))
+}
+
+/// Borrow a group ID passed across the FFI boundary, as `ffi_slice`.
+/// An empty group ID is rejected up front, rather than failing deep inside MDK.
+///
+/// # Safety
+/// As for `ffi_slice`.
+unsafe fn ffi_group_id<'a>(group_id: *const u8, group_id_length: c_int) -> Result<&'a [u8], MarmotError> {
+    let group_id = ffi_slice(group_id, group_id_length)?;
+    if group_id.is_empty() {
+        return Err(MarmotError::InvalidState("empty group id".into()));
+    }
+    CALL_CONTEXT.with(|context| context.borrow_mut().group_id = Some(hex::encode(group_id)));
+    Ok(group_id)
+}
+
+/// Convert a group name for return across the FFI boundary.
+/// Names come from other members (e.g. in a welcome), so a NUL byte is stripped
+/// with a warning rather than blanking the whole name.
+fn group_name_cstring(name: String) -> CString {
+    CString::new(name).unwrap_or_else(|e| {
+        tracing::warn!("Stripping NUL bytes from group name");
+        let mut name = e.into_vec();
+        name.retain(|&b| b != 0);
+        CString::new(name).unwrap_or_default()
+    })
+}
+
+/// A buffer returned by value, so the pointer and its length arrive together.
+/// Free it with `marmot_free_owned_buffer`.
+#[repr(C)]
+pub struct MarmotBuffer {
+    /// The buffer data, or null on failure
+    pub data: *mut u8,
+    /// The number of bytes at `data`
+    pub length: c_int,
+}
+
+impl MarmotBuffer {
+    fn null() -> Self {
+        MarmotBuffer { data: ptr::null_mut(), length: 0 }
+    }
+
+    fn from_vec(data: Vec<u8>) -> Self {
+        let length = data.len() as c_int;
+        MarmotBuffer { data: Box::into_raw(data.into_boxed_slice()) as *mut u8, length }
     }
 }
 
-/// Get the last error message.
+/// Get the last error message of a call made on the calling thread.
 /// Returns null if no error occurred.
 /// The caller must free the returned string using `marmot_free_string`.
 #[no_mangle]
 pub extern "C" fn marmot_get_last_error() -> *mut c_char {
-    let guard = match LAST_ERROR.lock() {
-        Ok(g) => g,
-        Err(_) => return ptr::null_mut(),
-    };
+    LAST_ERROR.with(|last| match &*last.borrow() {
+        Some(error) => match CString::new(error.message.as_str()) {
+            Ok(s) => s.into_raw(),
+            Err(_) => ptr::null_mut(),
+        },
+        None => ptr::null_mut(),
+    })
+}
+
+/// Get the last error message of a specific client.
+/// Unlike `marmot_get_last_error`, this is not affected by operations on other
+/// clients. Failures to create a client are only reported by `marmot_get_last_error`.
+/// Returns null if the client's last operation succeeded, or if the client was
+/// not created by `marmot_create_client` or has been destroyed.
+/// The caller must free the returned string using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_client_get_last_error(client: *mut MarmotClient) -> *mut c_char {
+    if marmot_client_is_valid(client) == 0 {
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &*client };
 
-    match &*guard {
-        Some(error) => match CString::new(error.as_str()) {
+    match client.last_error() {
+        Some(error) => match CString::new(error) {
             Ok(s) => s.into_raw(),
             Err(_) => ptr::null_mut(),
         },
@@ -50,11 +215,60 @@ pub extern "C" fn marmot_get_last_error() -> *mut c_char {
     }
 }
 
+/// Get the code of the last error.
+///
+/// # Returns
+/// 0 if no error occurred, -1 for generic FFI errors (null pointers, invalid
+/// strings), otherwise the `MarmotError` code (e.g. 11 for a duplicate message).
+/// MDK failures report their kind: 12 storage, 2 group not found, 13 epoch,
+/// 14 key package, 15 welcome, 16 message, and 3 for other MLS failures.
+#[no_mangle]
+pub extern "C" fn marmot_get_last_error_code() -> c_int {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map(|error| error.code).unwrap_or(0))
+}
+
+/// Get the last error of a call made on the calling thread as JSON, so that hosts
+/// can build error UIs without parsing messages.
+///
+/// # Returns
+/// `{"code": .., "message": "..", "operation": "add_member", "group_id": "<hex>"}`,
+/// where `operation` is the FFI function without its `marmot_` prefix and
+/// `group_id` is only present if the call targeted a group. Null if no error
+/// occurred. The caller must free the returned string using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_get_last_error_json() -> *mut c_char {
+    LAST_ERROR.with(|last| match &*last.borrow() {
+        Some(error) => serde_json::to_string(error)
+            .ok()
+            .and_then(|json| CString::new(json).ok())
+            .map_or(ptr::null_mut(), CString::into_raw),
+        None => ptr::null_mut(),
+    })
+}
+
+/// Clear the last error of the calling thread, so that hosts can reset error state
+/// between units of work. Errors recorded on other threads or on clients are kept.
+#[no_mangle]
+pub extern "C" fn marmot_clear_last_error() {
+    clear_last_error();
+}
+
+/// Seed the crate's RNG for the calling thread, so that calls made on it are
+/// reproducible. MLS key material and group IDs come from MDK's crypto provider
+/// and cannot be seeded: group IDs still differ between runs.
+/// Only available with the `test-rng` feature; never enable it in release builds.
+#[cfg(feature = "test-rng")]
+#[no_mangle]
+pub extern "C" fn marmot_seed_test_rng(seed: u64) {
+    test_rng::seed(seed);
+}
+
 /// Create a new Marmot client with the given Nostr identity.
 ///
 /// # Arguments
 /// * `private_key_hex` - The Nostr private key in hex format
-/// * `public_key_hex` - The Nostr public key in hex format
+/// * `public_key_hex` - The Nostr public key in hex format; must match the
+///   private key, or be empty to derive it
 /// * `db_path` - Path to SQLite database file for persistent storage, or null for in-memory
 ///
 /// # Returns
@@ -66,7 +280,7 @@ pub extern "C" fn marmot_create_client(
     public_key_hex: *const c_char,
     db_path: *const c_char,
 ) -> *mut MarmotClient {
-    clear_last_error();
+    begin_call("create_client");
 
     let private_key = match unsafe { CStr::from_ptr(private_key_hex) }.to_str() {
         Ok(s) => s,
@@ -93,8 +307,183 @@ pub extern "C" fn marmot_create_client(
         }
     };
 
-    match MarmotClient::new(private_key, public_key, db_path_str) {
-        Ok(client) => Box::into_raw(Box::new(client)),
+    register_client(MarmotClient::new(private_key, public_key, db_path_str))
+}
+
+/// Create a new in-memory Marmot client that uses the given MLS ciphersuite.
+///
+/// # Arguments
+/// * `private_key_hex` - The Nostr private key in hex format
+/// * `public_key_hex` - The Nostr public key in hex format
+/// * `ciphersuite` - IANA ciphersuite value, e.g. 1 for
+///   MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519
+///
+/// # Returns
+/// A pointer to the client, or null on failure. An unsupported ciphersuite
+/// fails with an error listing the supported values.
+/// The caller must free the client using `marmot_destroy_client`.
+#[no_mangle]
+pub extern "C" fn marmot_create_client_with_ciphersuite(
+    private_key_hex: *const c_char,
+    public_key_hex: *const c_char,
+    ciphersuite: u16,
+) -> *mut MarmotClient {
+    begin_call("create_client_with_ciphersuite");
+
+    let private_key = match unsafe { CStr::from_ptr(private_key_hex) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_last_error(format!("Invalid private key string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    let public_key = match unsafe { CStr::from_ptr(public_key_hex) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_last_error(format!("Invalid public key string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    register_client(MarmotClient::new_with_ciphersuite(private_key, public_key, ciphersuite))
+}
+
+/// Create a read-only "observer" client. It can join groups via welcomes,
+/// decrypt messages and read group info, but creating groups, adding or
+/// removing members and rekeying all fail.
+///
+/// # Arguments
+/// * `private_key_hex` - The Nostr private key in hex format
+/// * `public_key_hex` - The Nostr public key in hex format
+///
+/// # Returns
+/// A pointer to the client, or null on failure.
+/// The caller must free the client using `marmot_destroy_client`.
+#[no_mangle]
+pub extern "C" fn marmot_create_observer_client(
+    private_key_hex: *const c_char,
+    public_key_hex: *const c_char,
+) -> *mut MarmotClient {
+    begin_call("create_observer_client");
+
+    let private_key = match unsafe { CStr::from_ptr(private_key_hex) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_last_error(format!("Invalid private key string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    let public_key = match unsafe { CStr::from_ptr(public_key_hex) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_last_error(format!("Invalid public key string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    register_client(MarmotClient::new_observer(private_key, public_key))
+}
+
+/// Create a new in-memory Marmot client from a raw secret key, without passing
+/// it as a hex string. The public key is derived from it.
+///
+/// # Arguments
+/// * `secret_key` - The 32-byte Nostr private key
+///
+/// # Returns
+/// A pointer to the client, or null on failure.
+/// The caller must free the client using `marmot_destroy_client`.
+#[no_mangle]
+pub extern "C" fn marmot_create_client_from_keypair(
+    secret_key: *const u8,
+    secret_key_length: c_int,
+) -> *mut MarmotClient {
+    begin_call("create_client_from_keypair");
+
+    let secret_key = match unsafe { ffi_slice(secret_key, secret_key_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_last_error(e);
+            return ptr::null_mut();
+        }
+    };
+
+    register_client(MarmotClient::new_from_secret_key(secret_key))
+}
+
+/// Create a new Marmot client from a JSON config, the extensible alternative
+/// to the other `marmot_create_client*` functions.
+///
+/// # Arguments
+/// * `private_key_hex` - The Nostr private key in hex format
+/// * `public_key_hex` - The Nostr public key in hex format; must match the
+///   private key, or be empty to derive it
+/// * `config_json` - JSON object `{ "storage": "memory"|"sqlite", "db_path",
+///   "relays": [...], "ciphersuite", "members_json_version": 1|2 }`, every key
+///   optional, or null for the defaults. Unknown keys are ignored with a warning.
+///
+/// # Returns
+/// A pointer to the client, or null on failure. SQLite storage is not
+/// available in this build.
+/// The caller must free the client using `marmot_destroy_client`.
+#[no_mangle]
+pub extern "C" fn marmot_create_client_ex(
+    private_key_hex: *const c_char,
+    public_key_hex: *const c_char,
+    config_json: *const c_char,
+) -> *mut MarmotClient {
+    begin_call("create_client_ex");
+
+    let private_key = match unsafe { CStr::from_ptr(private_key_hex) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_last_error(format!("Invalid private key string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    let public_key = match unsafe { CStr::from_ptr(public_key_hex) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_last_error(format!("Invalid public key string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    let config = if config_json.is_null() {
+        client::ClientConfig::default()
+    } else {
+        let json = match unsafe { CStr::from_ptr(config_json) }.to_str() {
+            Ok(s) => s,
+            Err(e) => {
+                set_last_error(format!("Invalid config string: {}", e));
+                return ptr::null_mut();
+            }
+        };
+        match client::ClientConfig::from_json(json) {
+            Ok(config) => config,
+            Err(e) => {
+                set_last_error(e);
+                return ptr::null_mut();
+            }
+        }
+    };
+
+    register_client(MarmotClient::new_with_config(private_key, public_key, &config))
+}
+
+/// Hand a newly created client to the host, tracking it as live.
+fn register_client(result: Result<MarmotClient, MarmotError>) -> *mut MarmotClient {
+    match result {
+        Ok(client) => {
+            let client = Box::into_raw(Box::new(client));
+            if let Ok(mut live) = LIVE_CLIENTS.lock() {
+                live.insert(client as usize);
+            }
+            client
+        }
         Err(e) => {
             set_last_error(e);
             ptr::null_mut()
@@ -103,26 +492,73 @@ pub extern "C" fn marmot_create_client(
 }
 
 /// Destroy a Marmot client and free its resources.
+/// Destroying a client that was already destroyed is a no-op.
 #[no_mangle]
 pub extern "C" fn marmot_destroy_client(client: *mut MarmotClient) {
-    if !client.is_null() {
+    if client.is_null() {
+        return;
+    }
+
+    let was_live = match LIVE_CLIENTS.lock() {
+        Ok(mut live) => live.remove(&(client as usize)),
+        Err(_) => false,
+    };
+
+    if was_live {
         unsafe {
             drop(Box::from_raw(client));
         }
     }
 }
 
+/// Destroy a Marmot client like `marmot_destroy_client`, first erasing its
+/// Nostr secret key so it does not linger in freed memory. Slower; for hosts
+/// with strict memory-hygiene requirements.
+#[no_mangle]
+pub extern "C" fn marmot_wipe_client(client: *mut MarmotClient) {
+    if client.is_null() {
+        return;
+    }
+
+    let was_live = match LIVE_CLIENTS.lock() {
+        Ok(mut live) => live.remove(&(client as usize)),
+        Err(_) => false,
+    };
+
+    if was_live {
+        let mut client = unsafe { Box::from_raw(client) };
+        client.wipe_secrets();
+        drop(client);
+    }
+}
+
+/// Check whether a client handle is still valid, for use by managed `SafeHandle` wrappers.
+///
+/// # Returns
+/// 1 if the client was created by `marmot_create_client` and not yet destroyed, 0 otherwise.
+#[no_mangle]
+pub extern "C" fn marmot_client_is_valid(client: *mut MarmotClient) -> c_int {
+    if client.is_null() {
+        return 0;
+    }
+
+    match LIVE_CLIENTS.lock() {
+        Ok(live) if live.contains(&(client as usize)) => 1,
+        _ => 0,
+    }
+}
+
 /// Generate a new KeyPackage for group invitations.
 ///
 /// # Returns
 /// A pointer to the KeyPackage data, or null on failure.
-/// The caller must free the buffer using `marmot_free_buffer`.
+/// The caller must free the buffer using `marmot_free_buffer_zeroize`, as it holds key material.
 #[no_mangle]
 pub extern "C" fn marmot_generate_key_package(
     client: *mut MarmotClient,
     data_length: *mut c_int,
 ) -> *mut u8 {
-    clear_last_error();
+    begin_call("generate_key_package");
 
     if client.is_null() {
         set_last_error("Client is null");
@@ -130,471 +566,5343 @@ pub extern "C" fn marmot_generate_key_package(
     }
 
     let client = unsafe { &mut *client };
+    client.clear_last_error();
 
     match client.generate_key_package() {
         Ok(data) => {
             unsafe { *data_length = data.len() as c_int };
-            let boxed = data.into_boxed_slice();
+            // Copied to an exact-size buffer; the client's copy is zeroized on drop
+            let boxed = data.to_vec().into_boxed_slice();
             Box::into_raw(boxed) as *mut u8
         }
         Err(e) => {
-            set_last_error(e);
+            set_client_error(client, e);
             ptr::null_mut()
         }
     }
 }
 
-/// Create a new MLS group.
+/// Generate a new KeyPackage for group invitations, as
+/// `marmot_generate_key_package`.
 ///
 /// # Returns
-/// A pointer to the group ID, or null on failure.
-/// The caller must free the buffer using `marmot_free_buffer`.
+/// The KeyPackage data with its length, or a null buffer on failure.
+/// The caller must free the buffer using `marmot_free_owned_buffer`.
 #[no_mangle]
-pub extern "C" fn marmot_create_group(
-    client: *mut MarmotClient,
-    group_name: *const c_char,
-    group_id_length: *mut c_int,
-    epoch: *mut u64,
-) -> *mut u8 {
-    clear_last_error();
+pub extern "C" fn marmot_generate_key_package_buffer(client: *mut MarmotClient) -> MarmotBuffer {
+    begin_call("generate_key_package_buffer");
 
     if client.is_null() {
         set_last_error("Client is null");
-        return ptr::null_mut();
+        return MarmotBuffer::null();
     }
 
-    let name = match unsafe { CStr::from_ptr(group_name) }.to_str() {
-        Ok(s) => s,
-        Err(e) => {
-            set_last_error(format!("Invalid group name: {}", e));
-            return ptr::null_mut();
-        }
-    };
-
     let client = unsafe { &mut *client };
+    client.clear_last_error();
 
-    match client.create_group(name) {
-        Ok((group_id, group_epoch)) => {
-            unsafe {
-                *group_id_length = group_id.len() as c_int;
-                *epoch = group_epoch;
-            }
-            let boxed = group_id.into_boxed_slice();
-            Box::into_raw(boxed) as *mut u8
-        }
+    match client.generate_key_package() {
+        Ok(data) => MarmotBuffer::from_vec(data.to_vec()),
         Err(e) => {
-            set_last_error(e);
-            ptr::null_mut()
+            set_client_error(client, e);
+            MarmotBuffer::null()
         }
     }
 }
 
-/// Add a member to a group using their KeyPackage.
+/// Generate a new KeyPackage valid for the given number of days.
 ///
 /// # Returns
-/// A pointer to the Welcome message data, or null on failure.
-/// The caller must free the buffer using `marmot_free_buffer`.
+/// A pointer to the KeyPackage data, or null on failure (including a zero lifetime).
+/// The caller must free the buffer using `marmot_free_buffer_zeroize`, as it holds key material.
 #[no_mangle]
-pub extern "C" fn marmot_add_member(
+pub extern "C" fn marmot_generate_key_package_with_lifetime(
     client: *mut MarmotClient,
-    group_id: *const u8,
-    group_id_length: c_int,
-    key_package_data: *const u8,
-    key_package_length: c_int,
-    welcome_length: *mut c_int,
+    valid_days: u32,
+    data_length: *mut c_int,
 ) -> *mut u8 {
-    clear_last_error();
+    begin_call("generate_key_package_with_lifetime");
 
     if client.is_null() {
         set_last_error("Client is null");
         return ptr::null_mut();
     }
 
-    let group_id = unsafe { slice::from_raw_parts(group_id, group_id_length as usize) };
-    let key_package = unsafe { slice::from_raw_parts(key_package_data, key_package_length as usize) };
-
     let client = unsafe { &mut *client };
+    client.clear_last_error();
 
-    match client.add_member(group_id, key_package) {
-        Ok(welcome_data) => {
-            unsafe { *welcome_length = welcome_data.len() as c_int };
-            let boxed = welcome_data.into_boxed_slice();
+    match client.generate_key_package_with_lifetime(valid_days) {
+        Ok(data) => {
+            unsafe { *data_length = data.len() as c_int };
+            let boxed = data.to_vec().into_boxed_slice();
             Box::into_raw(boxed) as *mut u8
         }
         Err(e) => {
-            set_last_error(e);
+            set_client_error(client, e);
             ptr::null_mut()
         }
     }
 }
 
-/// Process a Welcome message to join a group.
+/// List the key packages generated by this client whose private keys are
+/// still stored, to audit outstanding key material after rotation.
 ///
 /// # Returns
-/// A pointer to the group ID, or null on failure.
+/// 0 on success, non-zero on failure.
+/// On success `key_packages_json` receives a JSON array of `{ "key_package_ref",
+/// "created_at" }` objects, oldest first, where `key_package_ref` is the hex MLS
+/// key package reference. The caller must free it using `marmot_free_string`.
 #[no_mangle]
-pub extern "C" fn marmot_process_welcome(
+pub extern "C" fn marmot_list_stored_key_packages(
     client: *mut MarmotClient,
-    welcome_data: *const u8,
-    welcome_length: c_int,
-    group_id_length: *mut c_int,
-    epoch: *mut u64,
-    group_name: *mut *mut c_char,
-    members_json: *mut *mut c_char,
-) -> *mut u8 {
-    clear_last_error();
+    key_packages_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("list_stored_key_packages");
 
     if client.is_null() {
         set_last_error("Client is null");
-        return ptr::null_mut();
+        return -1;
     }
 
-    let welcome = unsafe { slice::from_raw_parts(welcome_data, welcome_length as usize) };
-    let client = unsafe { &mut *client };
+    let client = unsafe { &*client };
+    client.clear_last_error();
 
-    match client.process_welcome(welcome) {
-        Ok((group_id, name, group_epoch, members)) => {
-            unsafe {
-                *group_id_length = group_id.len() as c_int;
-                *epoch = group_epoch;
+    if key_packages_json.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Output pointer must not be null".into()));
+        return -1;
+    }
 
-                *group_name = CString::new(name).unwrap_or_default().into_raw();
+    let json = serde_json::to_string(&client.stored_key_packages()).unwrap_or_else(|_| "[]".to_string());
+    unsafe { *key_packages_json = CString::new(json).unwrap_or_default().into_raw() };
+    0
+}
 
-                let members_str = serde_json::to_string(&members).unwrap_or_else(|_| "[]".to_string());
-                *members_json = CString::new(members_str).unwrap_or_default().into_raw();
-            }
+/// Delete a stored key package's private keys, e.g. once it has been used.
+///
+/// # Arguments
+/// * `key_package_ref` - Hex key package reference, as listed by
+///   `marmot_list_stored_key_packages`
+///
+/// # Returns
+/// 0 on success, non-zero on failure (including an unknown reference).
+#[no_mangle]
+pub extern "C" fn marmot_delete_key_package(client: *mut MarmotClient, key_package_ref: *const c_char) -> c_int {
+    begin_call("delete_key_package");
 
-            let boxed = group_id.into_boxed_slice();
-            Box::into_raw(boxed) as *mut u8
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    if key_package_ref.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Key package reference must not be null".to_string()));
+        return -1;
+    }
+
+    let key_package_ref = match unsafe { CStr::from_ptr(key_package_ref) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid key package reference: {}", e));
+            return -1;
         }
+    };
+
+    match client.delete_key_package(key_package_ref) {
+        Ok(()) => 0,
         Err(e) => {
-            set_last_error(e);
-            ptr::null_mut()
+            set_client_error(client, e);
+            -1
         }
     }
 }
 
-/// Encrypt a message for a group.
+/// Set the maximum number of relays listed in generated key packages (5 by
+/// default). When the client has more relays, only the first `max` are used.
 ///
 /// # Returns
-/// A pointer to the ciphertext, or null on failure.
+/// 0 on success, non-zero on failure (including a zero `max`).
 #[no_mangle]
-pub extern "C" fn marmot_encrypt_message(
+pub extern "C" fn marmot_set_max_key_package_relays(client: *mut MarmotClient, max: u32) -> c_int {
+    begin_call("set_max_key_package_relays");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    match client.set_max_key_package_relays(max as usize) {
+        Ok(()) => 0,
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Create a new MLS group.
+///
+/// # Arguments
+/// * `config_json` - Receives the group's resolved configuration as a JSON
+///   object `{ "name", "description", "relays", "admins" }`, saving a call to
+///   read it back; free using `marmot_free_string`. May be null to skip it.
+///
+/// # Returns
+/// A pointer to the group ID, or null on failure (including a null
+/// `group_id_length` or `epoch`, and a requested configuration that could not
+/// be read back). `epoch` receives the group's actual epoch.
+/// The caller must free the buffer using `marmot_free_buffer`.
+#[no_mangle]
+pub extern "C" fn marmot_create_group(
     client: *mut MarmotClient,
-    group_id: *const u8,
-    group_id_length: c_int,
-    plaintext: *const c_char,
-    ciphertext_length: *mut c_int,
+    group_name: *const c_char,
+    group_id_length: *mut c_int,
+    epoch: *mut u64,
+    config_json: *mut *mut c_char,
 ) -> *mut u8 {
-    clear_last_error();
+    begin_call("create_group");
 
     if client.is_null() {
         set_last_error("Client is null");
         return ptr::null_mut();
     }
 
-    let group_id = unsafe { slice::from_raw_parts(group_id, group_id_length as usize) };
-    let plaintext = match unsafe { CStr::from_ptr(plaintext) }.to_str() {
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    if group_name.is_null() || group_id_length.is_null() || epoch.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Group name and out-parameters must not be null".to_string()));
+        return ptr::null_mut();
+    }
+
+    let name = match unsafe { CStr::from_ptr(group_name) }.to_str() {
         Ok(s) => s,
         Err(e) => {
-            set_last_error(format!("Invalid plaintext: {}", e));
+            set_client_error(client, format!("Invalid group name: {}", e));
             return ptr::null_mut();
         }
     };
 
-    let client = unsafe { &mut *client };
-
-    match client.encrypt_message(group_id, plaintext) {
-        Ok(ciphertext) => {
-            unsafe { *ciphertext_length = ciphertext.len() as c_int };
-            let boxed = ciphertext.into_boxed_slice();
+    match client.create_group(name) {
+        Ok((group_id, group_epoch)) => {
+            if !config_json.is_null() {
+                let config = client
+                    .resolved_group_config(&group_id)
+                    .and_then(|config| serde_json::to_string(&config).map_err(MarmotError::from));
+                match config {
+                    Ok(config_str) => unsafe {
+                        *config_json = CString::new(config_str).unwrap_or_default().into_raw();
+                    },
+                    Err(e) => {
+                        set_client_error(client, e);
+                        return ptr::null_mut();
+                    }
+                }
+            }
+            unsafe {
+                *group_id_length = group_id.len() as c_int;
+                *epoch = group_epoch;
+            }
+            let boxed = group_id.into_boxed_slice();
             Box::into_raw(boxed) as *mut u8
         }
         Err(e) => {
-            set_last_error(e);
+            set_client_error(client, e);
             ptr::null_mut()
         }
     }
 }
 
-/// Decrypt a message from a group.
+/// Create a new MLS group with additional admins.
+///
+/// # Arguments
+/// * `admins_json` - JSON array of hex public keys. Duplicates are dropped and
+///   the creator is always an admin; an invalid key fails with the invalid key
+///   code and names the entry.
 ///
 /// # Returns
-/// A pointer to the plaintext string, or null on failure.
+/// A pointer to the group ID, or null on failure. `epoch` receives the group's
+/// actual epoch. The caller must free the buffer using `marmot_free_buffer`.
 #[no_mangle]
-pub extern "C" fn marmot_decrypt_message(
+pub extern "C" fn marmot_create_group_with_admins(
     client: *mut MarmotClient,
-    group_id: *const u8,
-    group_id_length: c_int,
-    ciphertext: *const u8,
-    ciphertext_length: c_int,
-    sender_public_key: *mut *mut c_char,
+    group_name: *const c_char,
+    admins_json: *const c_char,
+    group_id_length: *mut c_int,
     epoch: *mut u64,
-) -> *mut c_char {
-    clear_last_error();
+) -> *mut u8 {
+    begin_call("create_group_with_admins");
 
     if client.is_null() {
         set_last_error("Client is null");
         return ptr::null_mut();
     }
 
-    let group_id = unsafe { slice::from_raw_parts(group_id, group_id_length as usize) };
-    let ciphertext = unsafe { slice::from_raw_parts(ciphertext, ciphertext_length as usize) };
-
     let client = unsafe { &mut *client };
+    client.clear_last_error();
 
-    match client.decrypt_message(group_id, ciphertext) {
-        Ok((sender, plaintext, msg_epoch)) => {
+    if group_name.is_null() || admins_json.is_null() || group_id_length.is_null() || epoch.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Arguments and out-parameters must not be null".to_string()));
+        return ptr::null_mut();
+    }
+
+    let name = match unsafe { CStr::from_ptr(group_name) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid group name: {}", e));
+            return ptr::null_mut();
+        }
+    };
+    let admins = match unsafe { CStr::from_ptr(admins_json) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid admins JSON: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.create_group_with_admins(name, admins) {
+        Ok((group_id, group_epoch)) => {
             unsafe {
-                *sender_public_key = CString::new(sender).unwrap_or_default().into_raw();
-                *epoch = msg_epoch;
+                *group_id_length = group_id.len() as c_int;
+                *epoch = group_epoch;
             }
-
-            CString::new(plaintext).unwrap_or_default().into_raw()
+            Box::into_raw(group_id.into_boxed_slice()) as *mut u8
         }
         Err(e) => {
-            set_last_error(e);
+            set_client_error(client, e);
             ptr::null_mut()
         }
     }
 }
 
-/// Process a commit message.
+/// Create a new MLS group, as `marmot_create_group`.
 ///
 /// # Returns
-/// 0 on success, non-zero on failure.
+/// The group ID with its length, or a null buffer on failure (including a
+/// null `epoch`). `epoch` receives the group's actual epoch.
+/// The caller must free the buffer using `marmot_free_owned_buffer`.
 #[no_mangle]
-pub extern "C" fn marmot_process_commit(
+pub extern "C" fn marmot_create_group_buffer(
     client: *mut MarmotClient,
-    group_id: *const u8,
-    group_id_length: c_int,
-    commit_data: *const u8,
-    commit_length: c_int,
-) -> c_int {
-    clear_last_error();
+    group_name: *const c_char,
+    epoch: *mut u64,
+) -> MarmotBuffer {
+    begin_call("create_group_buffer");
 
     if client.is_null() {
         set_last_error("Client is null");
-        return -1;
+        return MarmotBuffer::null();
     }
 
-    let group_id = unsafe { slice::from_raw_parts(group_id, group_id_length as usize) };
-    let commit = unsafe { slice::from_raw_parts(commit_data, commit_length as usize) };
-
     let client = unsafe { &mut *client };
+    client.clear_last_error();
 
-    match client.process_commit(group_id, commit) {
-        Ok(_) => 0,
+    if group_name.is_null() || epoch.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Group name and out-parameters must not be null".to_string()));
+        return MarmotBuffer::null();
+    }
+
+    let name = match unsafe { CStr::from_ptr(group_name) }.to_str() {
+        Ok(s) => s,
         Err(e) => {
-            set_last_error(e);
-            -1
+            set_client_error(client, format!("Invalid group name: {}", e));
+            return MarmotBuffer::null();
+        }
+    };
+
+    match client.create_group(name) {
+        Ok((group_id, group_epoch)) => {
+            unsafe { *epoch = group_epoch };
+            MarmotBuffer::from_vec(group_id)
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            MarmotBuffer::null()
         }
     }
 }
 
-/// Update keys for forward secrecy.
+/// Create a new MLS group with its founding members in a single commit.
+///
+/// # Arguments
+/// * `key_package_events_json` - JSON array of the members' KeyPackage events
 ///
 /// # Returns
-/// A pointer to the commit data, or null on failure.
+/// A pointer to the group ID, or null on failure.
+/// On success `welcomes_json` receives a JSON array with one welcome rumor per
+/// founding member, which the caller must free using `marmot_free_string`.
+/// The caller must free the group ID using `marmot_free_buffer`.
 #[no_mangle]
-pub extern "C" fn marmot_update_keys(
+pub extern "C" fn marmot_create_group_with_members(
     client: *mut MarmotClient,
-    group_id: *const u8,
-    group_id_length: c_int,
-    commit_length: *mut c_int,
+    group_name: *const c_char,
+    key_package_events_json: *const u8,
+    key_package_events_length: c_int,
+    group_id_length: *mut c_int,
+    epoch: *mut u64,
+    welcomes_json: *mut *mut c_char,
 ) -> *mut u8 {
-    clear_last_error();
+    begin_call("create_group_with_members");
 
     if client.is_null() {
         set_last_error("Client is null");
         return ptr::null_mut();
     }
 
-    let group_id = unsafe { slice::from_raw_parts(group_id, group_id_length as usize) };
     let client = unsafe { &mut *client };
+    client.clear_last_error();
 
-    match client.update_keys(group_id) {
-        Ok(commit_data) => {
-            unsafe { *commit_length = commit_data.len() as c_int };
-            let boxed = commit_data.into_boxed_slice();
+    let name = match unsafe { CStr::from_ptr(group_name) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid group name: {}", e));
+            return ptr::null_mut();
+        }
+    };
+    let key_packages = match unsafe { ffi_slice(key_package_events_json, key_package_events_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.create_group_with_members(name, key_packages) {
+        Ok((group_id, group_epoch, welcomes)) => {
+            unsafe {
+                *group_id_length = group_id.len() as c_int;
+                *epoch = group_epoch;
+
+                let welcomes_str = serde_json::to_string(&welcomes).unwrap_or_else(|_| "[]".to_string());
+                *welcomes_json = CString::new(welcomes_str).unwrap_or_default().into_raw();
+            }
+            let boxed = group_id.into_boxed_slice();
             Box::into_raw(boxed) as *mut u8
         }
         Err(e) => {
-            set_last_error(e);
+            set_client_error(client, e);
             ptr::null_mut()
         }
     }
 }
 
-/// Remove a member from a group.
+/// Re-initialize a group after a suspected compromise, migrating its members
+/// to a fresh group. The old group can no longer be used to send messages
+/// from this client. That is held in memory only: it is not part of exported
+/// group state, so a client restored from a backup can send to the old group.
+///
+/// # Arguments
+/// * `key_package_events_json` - JSON array of fresh KeyPackage events, one per other member
 ///
 /// # Returns
-/// A pointer to the commit data, or null on failure.
+/// A pointer to the new group ID, or null on failure.
+/// On success `welcomes_json` receives a JSON array of welcome rumors, which the
+/// caller must free using `marmot_free_string`.
+/// The caller must free the group ID using `marmot_free_buffer`.
 #[no_mangle]
-pub extern "C" fn marmot_remove_member(
+pub extern "C" fn marmot_reinit_group(
     client: *mut MarmotClient,
     group_id: *const u8,
     group_id_length: c_int,
-    member_public_key: *const c_char,
-    commit_length: *mut c_int,
+    key_package_events_json: *const u8,
+    key_package_events_length: c_int,
+    new_group_id_length: *mut c_int,
+    welcomes_json: *mut *mut c_char,
 ) -> *mut u8 {
-    clear_last_error();
+    begin_call("reinit_group");
 
     if client.is_null() {
         set_last_error("Client is null");
         return ptr::null_mut();
     }
 
-    let group_id = unsafe { slice::from_raw_parts(group_id, group_id_length as usize) };
-    let member_key = match unsafe { CStr::from_ptr(member_public_key) }.to_str() {
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let key_packages = match unsafe { ffi_slice(key_package_events_json, key_package_events_length) } {
         Ok(s) => s,
         Err(e) => {
-            set_last_error(format!("Invalid member public key: {}", e));
+            set_client_error(client, e);
             return ptr::null_mut();
         }
     };
 
-    let client = unsafe { &mut *client };
+    match client.reinit_group(group_id, key_packages) {
+        Ok((new_group_id, welcomes)) => {
+            unsafe {
+                *new_group_id_length = new_group_id.len() as c_int;
 
-    match client.remove_member(group_id, member_key) {
-        Ok(commit_data) => {
-            unsafe { *commit_length = commit_data.len() as c_int };
-            let boxed = commit_data.into_boxed_slice();
+                let welcomes_str = serde_json::to_string(&welcomes).unwrap_or_else(|_| "[]".to_string());
+                *welcomes_json = CString::new(welcomes_str).unwrap_or_default().into_raw();
+            }
+            let boxed = new_group_id.into_boxed_slice();
             Box::into_raw(boxed) as *mut u8
         }
         Err(e) => {
-            set_last_error(e);
+            set_client_error(client, e);
             ptr::null_mut()
         }
     }
 }
 
-/// Get information about a group.
+/// Create a sub-conversation of a group: a new group with the parent's members
+/// and relays and its own message stream. Every other member is re-invited.
+///
+/// # Arguments
+/// * `key_package_events_json` - JSON array of KeyPackage events, one per other member
 ///
 /// # Returns
-/// 0 on success, non-zero if group not found.
+/// A pointer to the subgroup ID, or null on failure.
+/// On success `welcomes_json` receives a JSON array of welcome rumors, which the
+/// caller must free using `marmot_free_string`.
+/// The caller must free the group ID using `marmot_free_buffer`.
 #[no_mangle]
-pub extern "C" fn marmot_get_group_info(
+pub extern "C" fn marmot_create_subgroup(
     client: *mut MarmotClient,
-    group_id: *const u8,
-    group_id_length: c_int,
-    group_name: *mut *mut c_char,
-    epoch: *mut u64,
-    members_json: *mut *mut c_char,
-) -> c_int {
-    clear_last_error();
-
+    parent_group_id: *const u8,
+    parent_group_id_length: c_int,
+    group_name: *const c_char,
+    key_package_events_json: *const u8,
+    key_package_events_length: c_int,
+    subgroup_id_length: *mut c_int,
+    welcomes_json: *mut *mut c_char,
+) -> *mut u8 {
+    begin_call("create_subgroup");
+
     if client.is_null() {
         set_last_error("Client is null");
-        return -1;
+        return ptr::null_mut();
     }
 
-    let group_id = unsafe { slice::from_raw_parts(group_id, group_id_length as usize) };
-    let client = unsafe { &*client };
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
 
-    match client.get_group_info(group_id) {
-        Some((name, group_epoch, members)) => {
+    let parent_group_id = match unsafe { ffi_group_id(parent_group_id, parent_group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let name = match unsafe { CStr::from_ptr(group_name) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid group name: {}", e));
+            return ptr::null_mut();
+        }
+    };
+    let key_packages = match unsafe { ffi_slice(key_package_events_json, key_package_events_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.derive_subgroup(parent_group_id, name, key_packages) {
+        Ok((subgroup_id, welcomes)) => {
             unsafe {
-                *group_name = CString::new(name).unwrap_or_default().into_raw();
-                *epoch = group_epoch;
+                *subgroup_id_length = subgroup_id.len() as c_int;
 
-                let members_str = serde_json::to_string(&members).unwrap_or_else(|_| "[]".to_string());
-                *members_json = CString::new(members_str).unwrap_or_default().into_raw();
+                let welcomes_str = serde_json::to_string(&welcomes).unwrap_or_else(|_| "[]".to_string());
+                *welcomes_json = CString::new(welcomes_str).unwrap_or_default().into_raw();
             }
-            0
+            let boxed = subgroup_id.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
         }
-        None => {
-            set_last_error("Group not found");
-            -1
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
         }
     }
 }
 
-/// Export group state for persistence.
+/// Add a member to a group using their KeyPackage.
 ///
 /// # Returns
-/// A pointer to the state data, or null on failure.
+/// A pointer to the Welcome message data, or null on failure.
+/// The caller must free the buffer using `marmot_free_buffer`.
 #[no_mangle]
-pub extern "C" fn marmot_export_group_state(
+pub extern "C" fn marmot_add_member(
     client: *mut MarmotClient,
     group_id: *const u8,
     group_id_length: c_int,
-    state_length: *mut c_int,
+    key_package_data: *const u8,
+    key_package_length: c_int,
+    welcome_length: *mut c_int,
 ) -> *mut u8 {
-    clear_last_error();
+    begin_call("add_member");
 
     if client.is_null() {
         set_last_error("Client is null");
         return ptr::null_mut();
     }
 
-    let group_id = unsafe { slice::from_raw_parts(group_id, group_id_length as usize) };
-    let client = unsafe { &*client };
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
 
-    match client.export_group_state(group_id) {
-        Ok(state) => {
-            unsafe { *state_length = state.len() as c_int };
-            let boxed = state.into_boxed_slice();
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let key_package = match unsafe { ffi_slice(key_package_data, key_package_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.add_member(group_id, key_package) {
+        Ok(welcome_data) => {
+            unsafe { *welcome_length = welcome_data.len() as c_int };
+            let boxed = welcome_data.into_boxed_slice();
             Box::into_raw(boxed) as *mut u8
         }
         Err(e) => {
-            set_last_error(e);
+            set_client_error(client, e);
             ptr::null_mut()
         }
     }
 }
 
-/// Import group state from persistence.
+/// Check a KeyPackage event before adding it to a group, without committing anything.
+///
+/// # Arguments
+/// * `group_id` - The group the key package would be added to
+/// * `key_package_event_json` - JSON-serialized KeyPackage event
 ///
 /// # Returns
-/// 0 on success, non-zero on failure.
+/// 0 on success, non-zero on failure. Expired packages fail with the
+/// key-package-expired code (17) and packages for a ciphersuite other than
+/// the group's with the unsupported-ciphersuite code (18).
+/// On success `info_json` receives `{ "public_key", "ciphersuite", "not_before",
+/// "not_after" }`, which the caller must free using `marmot_free_string`.
 #[no_mangle]
-pub extern "C" fn marmot_import_group_state(
+pub extern "C" fn marmot_validate_key_package(
     client: *mut MarmotClient,
     group_id: *const u8,
     group_id_length: c_int,
-    state: *const u8,
-    state_length: c_int,
+    key_package_event_json: *const u8,
+    key_package_length: c_int,
+    info_json: *mut *mut c_char,
 ) -> c_int {
-    clear_last_error();
+    begin_call("validate_key_package");
 
     if client.is_null() {
         set_last_error("Client is null");
         return -1;
     }
 
-    let group_id = unsafe { slice::from_raw_parts(group_id, group_id_length as usize) };
-    let state = unsafe { slice::from_raw_parts(state, state_length as usize) };
+    let client = unsafe { &*client };
+    client.clear_last_error();
 
-    let client = unsafe { &mut *client };
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+    let key_package = match unsafe { ffi_slice(key_package_event_json, key_package_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
 
-    match client.import_group_state(group_id, state) {
-        Ok(_) => 0,
+    match client.validate_key_package(group_id, key_package) {
+        Ok(info) => {
+            let info_str = serde_json::to_string(&info).unwrap_or_else(|_| "{}".to_string());
+            unsafe { *info_json = CString::new(info_str).unwrap_or_default().into_raw() };
+            0
+        }
         Err(e) => {
-            set_last_error(e);
+            set_client_error(client, e);
             -1
         }
     }
 }
 
-/// Free a buffer allocated by this library.
+/// Add a member to a group from a bare KeyPackage, without a wrapping Nostr event.
+///
+/// # Arguments
+/// * `content_b64` - The base64 KeyPackage (`content` from `marmot_generate_key_package`)
+/// * `tags_json` - JSON array of tags (`tags` from `marmot_generate_key_package`)
+///
+/// # Returns
+/// A pointer to the Welcome message data, or null on failure.
+/// The caller must free the buffer using `marmot_free_buffer`.
 #[no_mangle]
-pub extern "C" fn marmot_free_buffer(buffer: *mut u8) {
-    if !buffer.is_null() {
-        unsafe {
-            // We don't know the length, so we rely on Box's drop implementation
-            // This is safe because we always allocate with Box::into_raw
-            drop(Box::from_raw(buffer));
+pub extern "C" fn marmot_add_member_from_key_package(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    content_b64: *const c_char,
+    tags_json: *const c_char,
+    welcome_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("add_member_from_key_package");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let content = match unsafe { CStr::from_ptr(content_b64) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid key package content: {}", e));
+            return ptr::null_mut();
+        }
+    };
+    let tags = match unsafe { CStr::from_ptr(tags_json) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid key package tags: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.add_member_from_key_package(group_id, content, tags) {
+        Ok(welcome_data) => {
+            unsafe { *welcome_length = welcome_data.len() as c_int };
+            let boxed = welcome_data.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
         }
     }
 }
 
-/// Free a string allocated by this library.
+/// Process a Welcome message to join a group.
+///
+/// # Returns
+/// A pointer to the group ID, or null on failure.
 #[no_mangle]
-pub extern "C" fn marmot_free_string(s: *mut c_char) {
-    if !s.is_null() {
-        unsafe {
-            drop(CString::from_raw(s));
+pub extern "C" fn marmot_process_welcome(
+    client: *mut MarmotClient,
+    welcome_data: *const u8,
+    welcome_length: c_int,
+    group_id_length: *mut c_int,
+    epoch: *mut u64,
+    group_name: *mut *mut c_char,
+    members_json: *mut *mut c_char,
+) -> *mut u8 {
+    begin_call("process_welcome");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let welcome = match unsafe { ffi_slice(welcome_data, welcome_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.process_welcome(welcome) {
+        Ok((group_id, name, group_epoch, members)) => {
+            unsafe {
+                *group_id_length = group_id.len() as c_int;
+                *epoch = group_epoch;
+
+                *group_name = group_name_cstring(name).into_raw();
+
+                let members_str = client.members_json(&group_id, &members);
+                *members_json = CString::new(members_str).unwrap_or_default().into_raw();
+            }
+
+            let boxed = group_id.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// NIP-59 gift wrap a welcome rumor from `marmot_add_member` for its recipient,
+/// ready to publish as a kind 1059 event.
+///
+/// # Arguments
+/// * `welcome_rumor_json` - One welcome rumor produced by this client
+/// * `recipient_public_key_hex` - The invitee's Nostr public key
+/// * `randomize_timestamp` - Non-zero (recommended) to shift the seal and wrapper
+///   timestamps up to 48 hours into the past, hiding when the invite was sent
+///
+/// # Returns
+/// A pointer to the gift wrap event JSON, or null on failure.
+/// The caller must free the string using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_wrap_welcome(
+    client: *mut MarmotClient,
+    welcome_rumor_json: *const c_char,
+    recipient_public_key_hex: *const c_char,
+    randomize_timestamp: c_int,
+) -> *mut c_char {
+    begin_call("wrap_welcome");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let rumor = match unsafe { CStr::from_ptr(welcome_rumor_json) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid welcome rumor string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+    let recipient = match unsafe { CStr::from_ptr(recipient_public_key_hex) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid public key string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.wrap_welcome(rumor.as_bytes(), recipient, randomize_timestamp != 0) {
+        Ok(wrap) => CString::new(wrap).unwrap_or_default().into_raw(),
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Process a Welcome delivered as a signed NIP-59 gift wrap (kind 1059) and
+/// join its group. Unlike `marmot_process_welcome`, the host passes the gift
+/// wrap event exactly as received from a relay.
+///
+/// # Returns
+/// A pointer to the group ID, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_process_welcome_event(
+    client: *mut MarmotClient,
+    gift_wrap_event: *const u8,
+    gift_wrap_length: c_int,
+    group_id_length: *mut c_int,
+    epoch: *mut u64,
+    group_name: *mut *mut c_char,
+    members_json: *mut *mut c_char,
+) -> *mut u8 {
+    begin_call("process_welcome_event");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let gift_wrap = match unsafe { ffi_slice(gift_wrap_event, gift_wrap_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.process_welcome_event(gift_wrap) {
+        Ok((group_id, name, group_epoch, members)) => {
+            unsafe {
+                *group_id_length = group_id.len() as c_int;
+                *epoch = group_epoch;
+
+                *group_name = group_name_cstring(name).into_raw();
+
+                let members_str = client.members_json(&group_id, &members);
+                *members_json = CString::new(members_str).unwrap_or_default().into_raw();
+            }
+
+            let boxed = group_id.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
         }
     }
 }
+
+/// Process a Welcome message without joining the group, so the user can review the invite.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+/// On success `preview_json` receives a JSON object with `welcome_id`, `group_id`,
+/// `group_name`, `inviter` and `members`, which the caller must free using
+/// `marmot_free_string`. Pass `welcome_id` to `marmot_accept_welcome` or
+/// `marmot_decline_welcome`.
+#[no_mangle]
+pub extern "C" fn marmot_preview_welcome(
+    client: *mut MarmotClient,
+    welcome_data: *const u8,
+    welcome_length: c_int,
+    preview_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("preview_welcome");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let welcome = match unsafe { ffi_slice(welcome_data, welcome_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.preview_welcome(welcome) {
+        Ok(preview) => {
+            let preview_str = serde_json::to_string(&preview).unwrap_or_else(|_| "{}".to_string());
+            unsafe { *preview_json = CString::new(preview_str).unwrap_or_default().into_raw() };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Accept a welcome returned by `marmot_preview_welcome` and join its group.
+///
+/// # Returns
+/// A pointer to the group ID, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_accept_welcome(
+    client: *mut MarmotClient,
+    welcome_id: *const c_char,
+    group_id_length: *mut c_int,
+    epoch: *mut u64,
+    group_name: *mut *mut c_char,
+    members_json: *mut *mut c_char,
+) -> *mut u8 {
+    begin_call("accept_welcome");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let welcome_id = match unsafe { CStr::from_ptr(welcome_id) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid welcome ID: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.accept_welcome(welcome_id) {
+        Ok((group_id, name, group_epoch, members)) => {
+            unsafe {
+                *group_id_length = group_id.len() as c_int;
+                *epoch = group_epoch;
+
+                *group_name = group_name_cstring(name).into_raw();
+
+                let members_str = client.members_json(&group_id, &members);
+                *members_json = CString::new(members_str).unwrap_or_default().into_raw();
+            }
+
+            let boxed = group_id.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Decline a welcome returned by `marmot_preview_welcome`, without joining.
+/// A declined welcome no longer appears in `marmot_pending_welcomes`.
+///
+/// # Returns
+/// 0 on success, non-zero on failure. An unknown welcome ID fails with the
+/// welcome-not-found error code.
+#[no_mangle]
+pub extern "C" fn marmot_decline_welcome(client: *mut MarmotClient, welcome_id: *const c_char) -> c_int {
+    begin_call("decline_welcome");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let welcome_id = match unsafe { CStr::from_ptr(welcome_id) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid welcome ID: {}", e));
+            return -1;
+        }
+    };
+
+    match client.decline_welcome(welcome_id) {
+        Ok(_) => 0,
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// List welcomes that have been processed but neither accepted nor declined.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+/// On success `welcomes_json` receives a JSON array of `{ "welcome_id",
+/// "group_id", "group_name", "inviter" }`, which the caller must free using
+/// `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_pending_welcomes(client: *mut MarmotClient, welcomes_json: *mut *mut c_char) -> c_int {
+    begin_call("pending_welcomes");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    match client.pending_welcomes() {
+        Ok(welcomes) => {
+            let welcomes_str = serde_json::to_string(&welcomes).unwrap_or_else(|_| "[]".to_string());
+            unsafe { *welcomes_json = CString::new(welcomes_str).unwrap_or_default().into_raw() };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Encrypt a message for a group.
+///
+/// # Returns
+/// A pointer to the ciphertext, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_encrypt_message(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    plaintext: *const c_char,
+    ciphertext_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("encrypt_message");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let plaintext = match unsafe { CStr::from_ptr(plaintext) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid plaintext: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.encrypt_message(group_id, plaintext) {
+        Ok(ciphertext) => {
+            unsafe { *ciphertext_length = ciphertext.len() as c_int };
+            let boxed = ciphertext.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Encrypt a message for a group, as `marmot_encrypt_message`, also reporting
+/// the event's ID.
+///
+/// # Arguments
+/// * `event_id` - Receives the event ID as 64-char hex, the ID relays compute
+///   and echo in their OK response; free using `marmot_free_string`.
+///
+/// # Returns
+/// A pointer to the ciphertext, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_encrypt_message_ex(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    plaintext: *const c_char,
+    ciphertext_length: *mut c_int,
+    event_id: *mut *mut c_char,
+) -> *mut u8 {
+    begin_call("encrypt_message_ex");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    if plaintext.is_null() || ciphertext_length.is_null() || event_id.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Plaintext and out-parameters must not be null".to_string()));
+        return ptr::null_mut();
+    }
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let plaintext = match unsafe { CStr::from_ptr(plaintext) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid plaintext: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.encrypt_message_with_id(group_id, plaintext) {
+        Ok((ciphertext, id)) => {
+            unsafe {
+                *ciphertext_length = ciphertext.len() as c_int;
+                *event_id = CString::new(id).unwrap_or_default().into_raw();
+            }
+            Box::into_raw(ciphertext.into_boxed_slice()) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Encrypt a message for a group, as `marmot_encrypt_message`.
+///
+/// # Returns
+/// The ciphertext with its length, or a null buffer on failure.
+/// The caller must free the buffer using `marmot_free_owned_buffer`.
+#[no_mangle]
+pub extern "C" fn marmot_encrypt_message_buffer(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    plaintext: *const c_char,
+) -> MarmotBuffer {
+    begin_call("encrypt_message_buffer");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return MarmotBuffer::null();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return MarmotBuffer::null();
+        }
+    };
+    let plaintext = match unsafe { CStr::from_ptr(plaintext) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid plaintext: {}", e));
+            return MarmotBuffer::null();
+        }
+    };
+
+    match client.encrypt_message(group_id, plaintext) {
+        Ok(ciphertext) => MarmotBuffer::from_vec(ciphertext),
+        Err(e) => {
+            set_client_error(client, e);
+            MarmotBuffer::null()
+        }
+    }
+}
+
+/// Encrypt a message for a group, failing if the group is not at the epoch
+/// the host expects (for example because a commit has not been processed).
+///
+/// # Arguments
+/// * `expected_epoch` - Epoch the host believes the group is at
+///
+/// # Returns
+/// A pointer to the ciphertext, or null on failure.
+/// An epoch mismatch fails with an invalid state error before encrypting.
+#[no_mangle]
+pub extern "C" fn marmot_encrypt_message_at_epoch(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    plaintext: *const c_char,
+    expected_epoch: u64,
+    ciphertext_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("encrypt_message_at_epoch");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let plaintext = match unsafe { CStr::from_ptr(plaintext) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid plaintext: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.encrypt_message_at_epoch(group_id, plaintext, Some(expected_epoch)) {
+        Ok(ciphertext) => {
+            unsafe { *ciphertext_length = ciphertext.len() as c_int };
+            let boxed = ciphertext.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Set the rumor kind used for outgoing messages when no kind is given
+/// (9, a chat message, by default).
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+#[no_mangle]
+pub extern "C" fn marmot_set_default_message_kind(client: *mut MarmotClient, kind: u16) -> c_int {
+    begin_call("set_default_message_kind");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    client.set_default_message_kind(kind);
+    0
+}
+
+/// Set the maximum size of outgoing message content, in bytes (65536 by
+/// default). Larger messages fail with "message too large" before encryption.
+/// Binary payloads count with their base64 encoding.
+///
+/// # Returns
+/// 0 on success, non-zero on failure (including a zero limit).
+#[no_mangle]
+pub extern "C" fn marmot_set_max_message_bytes(client: *mut MarmotClient, max_bytes: u32) -> c_int {
+    begin_call("set_max_message_bytes");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    match client.set_max_message_bytes(max_bytes as usize) {
+        Ok(()) => 0,
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Set how many times a failed storage operation (e.g. a locked database) is
+/// retried, with a short backoff, before its error is reported. Defaults to 3;
+/// zero disables retries. Only operations that are safe to repeat, deleting a
+/// key package and resetting a group, are retried; MLS protocol errors never are.
+///
+/// # Returns
+/// 0 on success, non-zero if the client is null.
+#[no_mangle]
+pub extern "C" fn marmot_set_storage_retry_count(client: *mut MarmotClient, count: u32) -> c_int {
+    begin_call("set_storage_retry_count");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    client.set_storage_retry_count(count);
+    0
+}
+
+/// Choose the shape of every `members_json` this client returns (group info,
+/// welcomes, member pages and past rosters):
+/// * 1 (default) - a flat JSON array of hex public keys
+/// * 2 - `{ "v": 2, "members": [{ "pubkey": "<hex>", "admin": bool }, ...] }`,
+///   marking the group's current admins
+///
+/// # Returns
+/// 0 on success, non-zero on failure (including an unknown version).
+#[no_mangle]
+pub extern "C" fn marmot_set_members_json_version(client: *mut MarmotClient, version: u32) -> c_int {
+    begin_call("set_members_json_version");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    match client.set_members_json_version(version) {
+        Ok(()) => 0,
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Encrypt a reply to an earlier message in a group.
+///
+/// # Arguments
+/// * `parent_event_id` - ID of the message being replied to, as 64-char hex
+///
+/// # Returns
+/// A pointer to the ciphertext, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_encrypt_reply(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    plaintext: *const c_char,
+    parent_event_id: *const c_char,
+    ciphertext_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("encrypt_reply");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let plaintext = match unsafe { CStr::from_ptr(plaintext) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid plaintext: {}", e));
+            return ptr::null_mut();
+        }
+    };
+    let parent = match unsafe { CStr::from_ptr(parent_event_id) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid parent event ID: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.encrypt_reply(group_id, plaintext, parent) {
+        Ok(ciphertext) => {
+            unsafe { *ciphertext_length = ciphertext.len() as c_int };
+            let boxed = ciphertext.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Decrypt a message from a group.
+///
+/// # Arguments
+/// * `timestamp` - Receives the sender's `created_at` from the message rumor
+///   (seconds since the Unix epoch), for ordering backfilled history
+///
+/// # Returns
+/// A pointer to the plaintext string, or null on failure. A message that
+/// belongs to a different group than `group_id` fails with "group id mismatch".
+/// Replayed events that were already processed fail with the duplicate
+/// message error code, which hosts can safely ignore. Content containing a NUL
+/// byte fails with an invalid state error; pass the same event to
+/// `marmot_decrypt_message_raw` to get it. Only the 32 most recent such
+/// messages are held; older ones are lost.
+#[no_mangle]
+pub extern "C" fn marmot_decrypt_message(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    ciphertext: *const u8,
+    ciphertext_length: c_int,
+    sender_public_key: *mut *mut c_char,
+    epoch: *mut u64,
+    timestamp: *mut u64,
+) -> *mut c_char {
+    begin_call("decrypt_message");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    if sender_public_key.is_null() || epoch.is_null() || timestamp.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Output pointer must not be null".to_string()));
+        return ptr::null_mut();
+    }
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let ciphertext = match unsafe { ffi_slice(ciphertext, ciphertext_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.decrypt_message_text(group_id, ciphertext) {
+        Ok((sender, plaintext, msg_epoch, created_at)) => {
+            unsafe {
+                *sender_public_key = CString::new(sender).unwrap_or_default().into_raw();
+                *epoch = msg_epoch;
+                *timestamp = created_at;
+            }
+
+            CString::new(plaintext).unwrap_or_default().into_raw()
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Decrypt a message, returning the decrypted rumor as its original JSON
+/// (with pubkey, kind, tags, content and created_at), for hosts that want
+/// more than `marmot_decrypt_message` reports.
+///
+/// # Returns
+/// A pointer to the rumor JSON, or null on failure (including a commit or
+/// proposal). The caller must free the string using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_decrypt_to_rumor_json(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    ciphertext: *const u8,
+    ciphertext_length: c_int,
+) -> *mut c_char {
+    begin_call("decrypt_to_rumor_json");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let ciphertext = match unsafe { ffi_slice(ciphertext, ciphertext_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.decrypt_to_rumor_json(group_id, ciphertext) {
+        Ok(rumor) => CString::new(rumor).unwrap_or_default().into_raw(),
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Decrypt a page of message events in one call.
+///
+/// # Arguments
+/// * `events_json` - JSON array of Nostr events
+/// * `results_json` - Receives a JSON array with one `{ "ok", "sender",
+///   "content", "epoch", "error" }` object per event, in input order.
+///   Free using `marmot_free_string`.
+///
+/// # Returns
+/// 0 on success, non-zero if the batch itself is invalid. Events that fail
+/// to decrypt are reported in `results_json` and do not stop the rest.
+#[no_mangle]
+pub extern "C" fn marmot_decrypt_messages(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    events_json: *const u8,
+    events_length: c_int,
+    results_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("decrypt_messages");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+    let events = match unsafe { ffi_slice(events_json, events_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.decrypt_messages(group_id, events) {
+        Ok(results) => {
+            let results_str = serde_json::to_string(&results).unwrap_or_else(|_| "[]".to_string());
+            unsafe { *results_json = CString::new(results_str).unwrap_or_default().into_raw() };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Decrypt a message from a group, returning the content as raw bytes.
+/// Unlike `marmot_decrypt_message`, content containing NUL bytes is preserved,
+/// including for an event `marmot_decrypt_message` already rejected for it.
+///
+/// # Returns
+/// A pointer to the plaintext bytes, or null on failure.
+/// The caller must free the buffer using `marmot_free_buffer`.
+#[no_mangle]
+pub extern "C" fn marmot_decrypt_message_raw(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    ciphertext: *const u8,
+    ciphertext_length: c_int,
+    sender_public_key: *mut *mut c_char,
+    epoch: *mut u64,
+    plaintext_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("decrypt_message_raw");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let ciphertext = match unsafe { ffi_slice(ciphertext, ciphertext_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.decrypt_message_raw(group_id, ciphertext) {
+        Ok((sender, plaintext, msg_epoch)) => {
+            unsafe {
+                *sender_public_key = CString::new(sender).unwrap_or_default().into_raw();
+                *epoch = msg_epoch;
+                *plaintext_length = plaintext.len() as c_int;
+            }
+
+            let boxed = plaintext.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Encrypt a binary payload for a group. Unlike `marmot_encrypt_message`,
+/// the data need not be UTF-8 and may contain NUL bytes.
+///
+/// # Returns
+/// A pointer to the ciphertext, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_encrypt_binary(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    data: *const u8,
+    data_length: c_int,
+    ciphertext_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("encrypt_binary");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let data = match unsafe { ffi_slice(data, data_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.encrypt_binary(group_id, data) {
+        Ok(ciphertext) => {
+            unsafe { *ciphertext_length = ciphertext.len() as c_int };
+            let boxed = ciphertext.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Decrypt a message sent with `marmot_encrypt_binary`.
+///
+/// # Returns
+/// A pointer to the payload bytes, or null on failure (including a message
+/// that does not carry binary content).
+/// The caller must free the buffer using `marmot_free_buffer`.
+#[no_mangle]
+pub extern "C" fn marmot_decrypt_binary(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    ciphertext: *const u8,
+    ciphertext_length: c_int,
+    sender_public_key: *mut *mut c_char,
+    epoch: *mut u64,
+    data_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("decrypt_binary");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let ciphertext = match unsafe { ffi_slice(ciphertext, ciphertext_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.decrypt_binary(group_id, ciphertext) {
+        Ok((sender, data, msg_epoch)) => {
+            unsafe {
+                *sender_public_key = CString::new(sender).unwrap_or_default().into_raw();
+                *epoch = msg_epoch;
+                *data_length = data.len() as c_int;
+            }
+
+            let boxed = data.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Encrypt a message whose content is additionally NIP-44 sealed to the group.
+///
+/// # Returns
+/// A pointer to the ciphertext, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_encrypt_message_sealed(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    plaintext: *const c_char,
+    ciphertext_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("encrypt_message_sealed");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let plaintext = match unsafe { CStr::from_ptr(plaintext) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid plaintext: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.encrypt_message_sealed(group_id, plaintext) {
+        Ok(ciphertext) => {
+            unsafe { *ciphertext_length = ciphertext.len() as c_int };
+            let boxed = ciphertext.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Decrypt a message from a group, unsealing content sent with
+/// `marmot_encrypt_message_sealed`. Plain messages are returned unchanged.
+///
+/// # Arguments
+/// * `sealed` - Receives 1 if the content was sealed, 0 otherwise
+///
+/// # Returns
+/// A pointer to the plaintext string, or null on failure. Content sealed in an
+/// epoch whose exporter secret MDK no longer keeps fails with the epoch-too-old
+/// code (20).
+#[no_mangle]
+pub extern "C" fn marmot_decrypt_message_sealed(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    ciphertext: *const u8,
+    ciphertext_length: c_int,
+    sender_public_key: *mut *mut c_char,
+    epoch: *mut u64,
+    sealed: *mut c_int,
+) -> *mut c_char {
+    begin_call("decrypt_message_sealed");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let ciphertext = match unsafe { ffi_slice(ciphertext, ciphertext_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.decrypt_message_sealed(group_id, ciphertext) {
+        Ok((sender, plaintext, msg_epoch, was_sealed)) => {
+            let plaintext = match CString::new(plaintext) {
+                Ok(s) => s,
+                Err(_) => {
+                    let error = MarmotError::InvalidState("Message content contains a NUL byte".into());
+                    set_client_error(client, error);
+                    return ptr::null_mut();
+                }
+            };
+
+            unsafe {
+                *sender_public_key = CString::new(sender).unwrap_or_default().into_raw();
+                *epoch = msg_epoch;
+                *sealed = was_sealed as c_int;
+            }
+
+            plaintext.into_raw()
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Set the callback invoked for each application message handled by
+/// `marmot_process_events`. Pass null to clear it.
+/// The callback receives (group_id, group_id_length, sender_public_key, content,
+/// kind, epoch); its pointers are only valid for the duration of the call.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+#[no_mangle]
+pub extern "C" fn marmot_set_message_callback(
+    client: *mut MarmotClient,
+    callback: Option<MessageCallback>,
+) -> c_int {
+    begin_call("set_message_callback");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    client.set_message_callback(callback);
+    0
+}
+
+/// Set a proxy that receives the JSON of every event the client would publish
+/// (encrypted messages, commits and unwrapped welcome rumors), so tests can
+/// pass them between in-process clients without relays. Pass null to clear it.
+/// Only available with the `test-transport` feature; never enable it in release builds.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+#[cfg(any(test, feature = "test-transport"))]
+#[no_mangle]
+pub extern "C" fn marmot_set_nostr_proxy(
+    client: *mut MarmotClient,
+    callback: Option<NostrProxyCallback>,
+) -> c_int {
+    begin_call("set_nostr_proxy");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    client.set_nostr_proxy(callback);
+    0
+}
+
+/// Feed back an event captured by the proxy set with `marmot_set_nostr_proxy`,
+/// as if it came from a relay. Welcome rumors are accepted; any other event is
+/// handled like `marmot_process_message`.
+/// Only available with the `test-transport` feature; never enable it in release builds.
+///
+/// # Arguments
+/// * `result_json` - Receives the `marmot_process_message` result, or a `joined`
+///   object (with `group_id` and `epoch`) for a welcome rumor.
+///   Free using `marmot_free_string`.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+#[cfg(any(test, feature = "test-transport"))]
+#[no_mangle]
+pub extern "C" fn marmot_replay_published_event(
+    client: *mut MarmotClient,
+    event_json: *const u8,
+    event_length: c_int,
+    result_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("replay_published_event");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    if result_json.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Output pointer must not be null".to_string()));
+        return -1;
+    }
+
+    let event = match unsafe { ffi_slice(event_json, event_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.replay_published(event) {
+        Ok(result) => {
+            let result_str = serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string());
+            unsafe { *result_json = CString::new(result_str).unwrap_or_default().into_raw() };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Process a batch of incoming message events, dispatching application
+/// messages to the callback set by `marmot_set_message_callback`.
+/// Commits in the batch are applied; replayed events are skipped. An event
+/// that fails does not stop the rest of the batch.
+///
+/// # Arguments
+/// * `events_json` - JSON array of Nostr events
+/// * `errors_json` - Receives a JSON array of `{ "index", "event_id", "code",
+///   "message" }` objects for the events that failed, in batch order.
+///   Must be freed using `marmot_free_string`.
+///
+/// # Returns
+/// The number of application messages dispatched, or -1 if the batch could
+/// not be read.
+#[no_mangle]
+pub extern "C" fn marmot_process_events(
+    client: *mut MarmotClient,
+    events_json: *const u8,
+    events_length: c_int,
+    errors_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("process_events");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    if errors_json.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Output pointer must not be null".to_string()));
+        return -1;
+    }
+
+    let events = match unsafe { ffi_slice(events_json, events_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.process_events(events) {
+        Ok(report) => {
+            let errors = serde_json::to_string(&report.errors).unwrap_or_else(|_| "[]".to_string());
+            unsafe { *errors_json = CString::new(errors).unwrap_or_default().into_raw() };
+            report.dispatched as c_int
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Process any relay-delivered group event, without the caller having to know
+/// whether it carries a message, a commit or a proposal.
+///
+/// # Arguments
+/// * `result_json` - Receives a JSON object tagged by `type`: `message` (with
+///   `group_id`, `sender`, `content`, `epoch` and `kind`), `commit` (with
+///   `group_id` and the new `epoch`), `proposal`, or `welcome_unsupported` for
+///   welcomes, which go through `marmot_process_welcome_event`.
+///   Free using `marmot_free_string`.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+#[no_mangle]
+pub extern "C" fn marmot_process_message(
+    client: *mut MarmotClient,
+    event_json: *const u8,
+    event_length: c_int,
+    result_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("process_message");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    if result_json.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Output pointer must not be null".to_string()));
+        return -1;
+    }
+
+    let event = match unsafe { ffi_slice(event_json, event_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.process_message(event) {
+        Ok(result) => {
+            let result_str = serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string());
+            unsafe { *result_json = CString::new(result_str).unwrap_or_default().into_raw() };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Process a commit message.
+///
+/// # Returns
+/// 0 on success, including for a commit that was already applied (e.g.
+/// redelivered by a relay), non-zero on failure.
+#[no_mangle]
+pub extern "C" fn marmot_process_commit(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    commit_data: *const u8,
+    commit_length: c_int,
+) -> c_int {
+    begin_call("process_commit");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+    let commit = match unsafe { ffi_slice(commit_data, commit_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.process_commit(group_id, commit) {
+        Ok(effect) if effect.kind == CommitKind::Unprocessable => {
+            set_client_error(client, MarmotError::Internal("Commit was unprocessable by MLS layer".into()));
+            -1
+        }
+        Ok(_) => 0,
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Process a commit message, reporting its effect on the group.
+///
+/// # Arguments
+/// * `new_epoch` - Receives the group's epoch after the commit
+/// * `member_delta` - Receives the net change in member count (e.g. -1 for a removal)
+/// * `effect_json` - Receives a JSON object with `kind` (commit, proposal,
+///   external_join, unprocessable or other), `epoch`, `member_delta`, and the
+///   `added` and `removed` member public keys. Free using `marmot_free_string`.
+///
+/// # Returns
+/// 0 on success, non-zero on failure. An unprocessable commit is reported
+/// through `effect_json` rather than as a failure.
+#[no_mangle]
+pub extern "C" fn marmot_process_commit_ex(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    commit_data: *const u8,
+    commit_length: c_int,
+    new_epoch: *mut u64,
+    member_delta: *mut c_int,
+    effect_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("process_commit_ex");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+    let commit = match unsafe { ffi_slice(commit_data, commit_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.process_commit(group_id, commit) {
+        Ok(effect) => {
+            unsafe {
+                *new_epoch = effect.epoch;
+                *member_delta = effect.member_delta;
+
+                let effect_str = serde_json::to_string(&effect).unwrap_or_else(|_| "{}".to_string());
+                *effect_json = CString::new(effect_str).unwrap_or_default().into_raw();
+            }
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Apply commits missed while offline. The commits may be supplied in any order;
+/// they are applied in epoch order and those already applied are skipped.
+///
+/// # Arguments
+/// * `events_json` - JSON array of commit events
+/// * `failed_json` - Receives a JSON array of the IDs of the events that could
+///   not be applied. Must be freed using `marmot_free_string`.
+///
+/// # Returns
+/// The number of commits that advanced the group state, or -1 on failure.
+#[no_mangle]
+pub extern "C" fn marmot_reprocess_commits(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    events_json: *const u8,
+    events_length: c_int,
+    failed_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("reprocess_commits");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    if failed_json.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Output pointer must not be null".to_string()));
+        return -1;
+    }
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+    let events = match unsafe { ffi_slice(events_json, events_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.reprocess_commits(group_id, events) {
+        Ok(report) => {
+            let failed = serde_json::to_string(&report.failed).unwrap_or_else(|_| "[]".to_string());
+            unsafe { *failed_json = CString::new(failed).unwrap_or_default().into_raw() };
+            report.applied as c_int
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Process a commit message, reporting only the members it added and removed,
+/// so the host can update its member list without re-reading the roster.
+///
+/// # Arguments
+/// * `added_json` - Receives a JSON array of the added members' public keys
+/// * `removed_json` - Receives a JSON array of the removed members' public keys
+///
+/// Both strings must be freed using `marmot_free_string`.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+#[no_mangle]
+pub extern "C" fn marmot_process_commit_diff(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    commit_data: *const u8,
+    commit_length: c_int,
+    added_json: *mut *mut c_char,
+    removed_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("process_commit_diff");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    if added_json.is_null() || removed_json.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Output pointer must not be null".to_string()));
+        return -1;
+    }
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+    let commit = match unsafe { ffi_slice(commit_data, commit_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.process_commit(group_id, commit) {
+        Ok(effect) => {
+            let added_str = serde_json::to_string(&effect.added).unwrap_or_else(|_| "[]".to_string());
+            let removed_str = serde_json::to_string(&effect.removed).unwrap_or_else(|_| "[]".to_string());
+            unsafe {
+                *added_json = CString::new(added_str).unwrap_or_default().into_raw();
+                *removed_json = CString::new(removed_str).unwrap_or_default().into_raw();
+            }
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Update keys for forward secrecy.
+///
+/// # Returns
+/// A pointer to the commit data, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_update_keys(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    commit_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("update_keys");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.update_keys(group_id) {
+        Ok(commit_data) => {
+            unsafe { *commit_length = commit_data.len() as c_int };
+            let boxed = commit_data.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Rotate this client's keys and encrypt a message under the new epoch.
+///
+/// # Arguments
+/// * `plaintext` - Message to deliver alongside the rotation
+///
+/// # Returns
+/// A pointer to a JSON object `{ "commit": {...}, "message": {...},
+/// "message_error": null }`, or null on failure. Members must process the
+/// commit before the message. Once the commit is merged it is always returned;
+/// if the message then fails, `message` is null and `message_error` says why.
+#[no_mangle]
+pub extern "C" fn marmot_self_update_with_message(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    plaintext: *const c_char,
+    result_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("self_update_with_message");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let plaintext = match unsafe { CStr::from_ptr(plaintext) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid plaintext: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.self_update_with_message(group_id, plaintext) {
+        Ok(result) => {
+            unsafe { *result_length = result.len() as c_int };
+            Box::into_raw(result.into_boxed_slice()) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Set a group's rekey policy, checked with `marmot_should_rekey`.
+///
+/// # Arguments
+/// * `max_messages` - Messages sent since the last key update, 0 for no limit
+/// * `max_seconds` - Seconds since the last key update, 0 for no limit
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+#[no_mangle]
+pub extern "C" fn marmot_set_rekey_policy(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    max_messages: u32,
+    max_seconds: u64,
+) -> c_int {
+    begin_call("set_rekey_policy");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.set_rekey_policy(group_id, max_messages, max_seconds) {
+        Ok(_) => 0,
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Check whether a group's rekey policy has fired, meaning the host should
+/// call `marmot_update_keys`.
+///
+/// # Returns
+/// 1 if a rekey is due, 0 if not (or no policy is set), -1 on failure.
+#[no_mangle]
+pub extern "C" fn marmot_should_rekey(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+) -> c_int {
+    begin_call("should_rekey");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    if client.should_rekey(group_id) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Remove a member from a group.
+///
+/// # Returns
+/// A pointer to the commit data, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_remove_member(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    member_public_key: *const c_char,
+    commit_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("remove_member");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let member_key = match unsafe { CStr::from_ptr(member_public_key) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid member public key: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.remove_member(group_id, member_key) {
+        Ok(commit_data) => {
+            unsafe { *commit_length = commit_data.len() as c_int };
+            let boxed = commit_data.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Propose removing the client's own member from a group.
+/// A member can't commit its own removal, so another member must commit the
+/// returned proposal; use `marmot_remove_member` to remove someone else, which
+/// commits immediately.
+///
+/// # Returns
+/// A pointer to the proposal event data, or null on failure.
+/// The caller must free the buffer using `marmot_free_buffer`.
+#[no_mangle]
+pub extern "C" fn marmot_propose_self_removal(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    proposal_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("propose_self_removal");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.propose_self_removal(group_id) {
+        Ok(proposal_data) => {
+            unsafe { *proposal_length = proposal_data.len() as c_int };
+            let boxed = proposal_data.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Get the ID of the most recent commit event produced by this client
+/// (by adding or removing members, updating keys or changing group data).
+///
+/// # Returns
+/// The event ID in hex, or null if the client has not produced a commit yet.
+/// The caller must free the returned string using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_last_commit_event_id(client: *mut MarmotClient) -> *mut c_char {
+    begin_call("last_commit_event_id");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    match client.last_commit_event_id() {
+        Some(event_id) => CString::new(event_id).unwrap_or_default().into_raw(),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Check whether a group exists. Cheaper than `marmot_get_group_info`.
+///
+/// # Returns
+/// 1 if the group exists, 0 if it does not, -1 on failure (e.g. a storage error).
+#[no_mangle]
+pub extern "C" fn marmot_group_exists(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+) -> c_int {
+    begin_call("group_exists");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.group_exists(group_id) {
+        Ok(true) => 1,
+        Ok(false) => 0,
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Check whether a group has a commit that was created but not yet merged.
+/// MLS forbids creating another commit for the group until it is resolved.
+///
+/// # Returns
+/// 1 if a commit is pending, 0 if not, -1 on failure (e.g. the group was not found).
+#[no_mangle]
+pub extern "C" fn marmot_has_pending_commit(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+) -> c_int {
+    begin_call("has_pending_commit");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.has_pending_commit(group_id) {
+        Ok(true) => 1,
+        Ok(false) => 0,
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Cache a member's display metadata (from their kind 0 profile) on the client.
+///
+/// # Arguments
+/// * `member_public_key` - The member's public key in hex format
+/// * `name` - Display name
+/// * `picture_url` - Profile picture URL, or null for none
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+#[no_mangle]
+pub extern "C" fn marmot_set_member_metadata(
+    client: *mut MarmotClient,
+    member_public_key: *const c_char,
+    name: *const c_char,
+    picture_url: *const c_char,
+) -> c_int {
+    begin_call("set_member_metadata");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let pubkey = match unsafe { CStr::from_ptr(member_public_key) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid public key: {}", e));
+            return -1;
+        }
+    };
+    let name = match unsafe { CStr::from_ptr(name) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid name: {}", e));
+            return -1;
+        }
+    };
+    let picture_url = if picture_url.is_null() {
+        None
+    } else {
+        match unsafe { CStr::from_ptr(picture_url) }.to_str() {
+            Ok(s) => Some(s),
+            Err(e) => {
+                set_client_error(client, format!("Invalid picture URL: {}", e));
+                return -1;
+            }
+        }
+    };
+
+    match client.set_member_metadata(pubkey, name, picture_url) {
+        Ok(_) => 0,
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Get a member's cached display metadata.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+/// On success `metadata_json` receives `{ "name", "picture_url" }`, or null if
+/// no metadata was set. Free using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_get_member_metadata(
+    client: *mut MarmotClient,
+    member_public_key: *const c_char,
+    metadata_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("get_member_metadata");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let pubkey = match unsafe { CStr::from_ptr(member_public_key) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid public key: {}", e));
+            return -1;
+        }
+    };
+
+    match client.get_member_metadata(pubkey) {
+        Ok(metadata) => {
+            let metadata_ptr = match metadata {
+                Some(metadata) => {
+                    let metadata_str = serde_json::to_string(&metadata).unwrap_or_else(|_| "{}".to_string());
+                    CString::new(metadata_str).unwrap_or_default().into_raw()
+                }
+                None => ptr::null_mut(),
+            };
+            unsafe { *metadata_json = metadata_ptr };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Get diagnostic counts for a client. Read-only.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+/// On success `stats_json` receives `{ "group_count", "total_members",
+/// "pending_commits" }`, which the caller must free using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_get_client_stats(client: *mut MarmotClient, stats_json: *mut *mut c_char) -> c_int {
+    begin_call("get_client_stats");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    match client.stats() {
+        Ok(stats) => {
+            let stats_str = serde_json::to_string(&stats).unwrap_or_else(|_| "{}".to_string());
+            unsafe { *stats_json = CString::new(stats_str).unwrap_or_default().into_raw() };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Get the current epoch of a group. Cheaper than `marmot_get_group_info`.
+///
+/// # Returns
+/// 0 on success, non-zero if group not found.
+#[no_mangle]
+pub extern "C" fn marmot_get_epoch(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    epoch: *mut u64,
+) -> c_int {
+    begin_call("get_epoch");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.current_epoch(group_id) {
+        Some(group_epoch) => {
+            unsafe { *epoch = group_epoch };
+            0
+        }
+        None => {
+            set_client_error(client, "Group not found");
+            -1
+        }
+    }
+}
+
+/// Get the MLS ciphersuite of a group, as its IANA value.
+///
+/// # Returns
+/// 0 on success, non-zero if group not found.
+#[no_mangle]
+pub extern "C" fn marmot_get_group_ciphersuite(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    ciphersuite: *mut u16,
+) -> c_int {
+    begin_call("get_group_ciphersuite");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.group_ciphersuite(group_id) {
+        Some(value) => {
+            unsafe { *ciphersuite = value };
+            0
+        }
+        None => {
+            set_client_error(client, "Group not found");
+            -1
+        }
+    }
+}
+
+/// Preview adding a member without creating a commit or changing the group.
+///
+/// # Arguments
+/// * `key_package_event` - JSON-serialized key package event, as for `marmot_add_member`
+/// * `preview_json` - Receives `{ "new_member", "epoch", "members" }` describing
+///   the group after the add. Free using `marmot_free_string`.
+///
+/// # Returns
+/// 0 on success, non-zero on failure (including an invalid key package or an
+/// existing member).
+#[no_mangle]
+pub extern "C" fn marmot_dry_run_add_member(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    key_package_event: *const u8,
+    key_package_length: c_int,
+    preview_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("dry_run_add_member");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+    let key_package = match unsafe { ffi_slice(key_package_event, key_package_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.dry_run_add_member(group_id, key_package) {
+        Ok(preview) => {
+            let preview_str = serde_json::to_string(&preview).unwrap_or_else(|_| "{}".to_string());
+            unsafe { *preview_json = CString::new(preview_str).unwrap_or_default().into_raw() };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Remove several members from a group in a single commit.
+///
+/// # Arguments
+/// * `member_public_keys_json` - JSON array of hex public keys; if any is invalid
+///   or not a member, nothing is removed
+///
+/// # Returns
+/// A pointer to the commit data, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_remove_members(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    member_public_keys_json: *const c_char,
+    commit_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("remove_members");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let member_keys = match unsafe { CStr::from_ptr(member_public_keys_json) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid member public keys: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.remove_members(group_id, member_keys) {
+        Ok(commit_data) => {
+            unsafe { *commit_length = commit_data.len() as c_int };
+            let boxed = commit_data.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Get who created a group and when.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+/// On success `info_json` receives `{ "creator", "created_at" }`, as the creator
+/// recorded them in the group, so every member sees the same values. Both are
+/// null for groups created by clients that do not record them.
+/// Free using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_get_group_creation_info(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    info_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("get_group_creation_info");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.group_creation_info(group_id) {
+        Ok(info) => {
+            let info_str = serde_json::to_string(&info).unwrap_or_else(|_| "{}".to_string());
+            unsafe { *info_json = CString::new(info_str).unwrap_or_default().into_raw() };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Get information about a group. See `marmot_get_group_creation_info` for
+/// its creator and creation time.
+///
+/// # Returns
+/// 0 on success, non-zero if group not found.
+#[no_mangle]
+pub extern "C" fn marmot_get_group_info(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    group_name: *mut *mut c_char,
+    epoch: *mut u64,
+    members_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("get_group_info");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.get_group_info(group_id) {
+        Some((name, group_epoch, members, _, _)) => {
+            unsafe {
+                *group_name = group_name_cstring(name).into_raw();
+                *epoch = group_epoch;
+
+                let members_str = client.members_json(group_id, &members);
+                *members_json = CString::new(members_str).unwrap_or_default().into_raw();
+            }
+            0
+        }
+        None => {
+            set_client_error(client, "Group not found");
+            -1
+        }
+    }
+}
+
+/// Get one page of a group's members, for groups too large to list at once.
+/// Members are sorted by public key, so pages are consistent between calls.
+///
+/// # Arguments
+/// * `offset` - Number of members to skip
+/// * `limit` - Maximum number of members to return, at least one
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+/// On success `members_json` receives a JSON array of hex public keys, which
+/// the caller must free using `marmot_free_string`, and `total` receives the
+/// group's member count.
+#[no_mangle]
+pub extern "C" fn marmot_get_members_page(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    offset: u32,
+    limit: u32,
+    members_json: *mut *mut c_char,
+    total: *mut u32,
+) -> c_int {
+    begin_call("get_members_page");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    if members_json.is_null() || total.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Output pointer must not be null".to_string()));
+        return -1;
+    }
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.members_page(group_id, offset as usize, limit as usize) {
+        Ok((members, member_count)) => {
+            let members_str = client.members_json(group_id, &members);
+            unsafe {
+                *members_json = CString::new(members_str).unwrap_or_default().into_raw();
+                *total = member_count as u32;
+            }
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Get a group's members as they were at a given epoch.
+/// Only epochs this client has observed are available; MDK does not retain
+/// historical group states, so other epochs fail rather than reporting the
+/// current roster.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+/// On success `members_json` receives a JSON array of hex public keys, which
+/// the caller must free using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_get_members_at_epoch(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    epoch: u64,
+    members_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("get_members_at_epoch");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.members_at_epoch(group_id, epoch) {
+        Ok(members) => {
+            let members_str = client.members_json(group_id, &members);
+            unsafe { *members_json = CString::new(members_str).unwrap_or_default().into_raw() };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Get the epoch transitions this client has applied to a group, for auditing.
+/// The history starts when this client created or joined the group.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+/// On success `history_json` receives a JSON array of `{ "epoch", "timestamp",
+/// "kind", "actor" }` objects, oldest first, where `kind` is add, remove,
+/// add_remove or update and `actor` is the committer, or null if unknown.
+/// The caller must free it using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_get_epoch_history(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    history_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("get_epoch_history");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.epoch_history(group_id) {
+        Ok(history) => {
+            let history_str = serde_json::to_string(&history).unwrap_or_else(|_| "[]".to_string());
+            unsafe { *history_json = CString::new(history_str).unwrap_or_default().into_raw() };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Get how many commits, including self-updates, this client has applied to
+/// a group since creating or joining it. The count is kept in the state
+/// exported by `marmot_export_group_state` and `marmot_export_all_groups`.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+#[no_mangle]
+pub extern "C" fn marmot_get_rotation_count(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    count: *mut u64,
+) -> c_int {
+    begin_call("get_rotation_count");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.rotation_count(group_id) {
+        Ok(rotations) => {
+            unsafe { *count = rotations };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Get the relays configured for a group.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+/// On success `relays_json` receives a JSON array of relay URLs, which the
+/// caller must free using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_get_group_relays(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    relays_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("get_group_relays");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.group_relays(group_id) {
+        Ok(relays) => {
+            let relays_str = serde_json::to_string(&relays).unwrap_or_else(|_| "[]".to_string());
+            unsafe { *relays_json = CString::new(relays_str).unwrap_or_default().into_raw() };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Get the admins of a group, who may moderate it.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+/// On success `admins_json` receives a JSON array of hex public keys, which
+/// the caller must free using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_get_group_admins(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    admins_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("get_group_admins");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    if admins_json.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Output pointer must not be null".to_string()));
+        return -1;
+    }
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.group_admins(group_id) {
+        Ok(admins) => {
+            let admins_str = serde_json::to_string(&admins).unwrap_or_else(|_| "[]".to_string());
+            unsafe { *admins_json = CString::new(admins_str).unwrap_or_default().into_raw() };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Replace the relays configured for a group.
+///
+/// # Arguments
+/// * `relays_json` - JSON array of relay URLs, must not be empty
+///
+/// # Returns
+/// A pointer to the commit data, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_set_group_relays(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    relays_json: *const c_char,
+    commit_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("set_group_relays");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let relays = match unsafe { CStr::from_ptr(relays_json) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid relays JSON string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.set_group_relays(group_id, relays) {
+        Ok(commit_data) => {
+            unsafe { *commit_length = commit_data.len() as c_int };
+            let boxed = commit_data.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Rename a group. Only the name in the group data is changed.
+///
+/// # Arguments
+/// * `new_name` - The new name, must not be empty
+///
+/// # Returns
+/// A pointer to the commit data, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_rename_group(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    new_name: *const c_char,
+    commit_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("rename_group");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let name = match unsafe { CStr::from_ptr(new_name) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid group name string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.rename_group(group_id, name) {
+        Ok(commit_data) => {
+            unsafe { *commit_length = commit_data.len() as c_int };
+            let boxed = commit_data.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Set a group's description. Only the description in the group data is changed.
+///
+/// # Arguments
+/// * `description` - The new description; empty to clear it
+///
+/// # Returns
+/// A pointer to the commit data, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_set_group_description(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    description: *const c_char,
+    commit_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("set_group_description");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let description = match unsafe { CStr::from_ptr(description) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid description string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.set_group_description(group_id, description) {
+        Ok(commit_data) => {
+            unsafe { *commit_length = commit_data.len() as c_int };
+            let boxed = commit_data.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Set a group's image. The image is encrypted with a fresh key and nonce,
+/// which are stored in the group data via a commit.
+///
+/// # Arguments
+/// * `image` - The plaintext image bytes
+/// * `commit_json` - Receives the commit event JSON to publish; free using `marmot_free_string`
+///
+/// # Returns
+/// A pointer to the encrypted image blob for the host to upload, or null on failure.
+/// The caller must free the buffer using `marmot_free_buffer`.
+#[no_mangle]
+pub extern "C" fn marmot_set_group_image(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    image: *const u8,
+    image_length: c_int,
+    encrypted_length: *mut c_int,
+    commit_json: *mut *mut c_char,
+) -> *mut u8 {
+    begin_call("set_group_image");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let image = match unsafe { ffi_slice(image, image_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.set_group_image(group_id, image) {
+        Ok((encrypted, commit)) => {
+            unsafe {
+                *encrypted_length = encrypted.len() as c_int;
+                *commit_json = CString::new(commit).unwrap_or_default().into_raw();
+            }
+            Box::into_raw(encrypted.into_boxed_slice()) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Get the parameters needed to decrypt a group's image.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+/// On success `image_key_json` receives `{ "hash", "key", "nonce" }` as hex
+/// strings, or null if the group has no image. Free using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_get_group_image_key(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    image_key_json: *mut *mut c_char,
+) -> c_int {
+    begin_call("get_group_image_key");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.group_image_key(group_id) {
+        Ok(image_key) => {
+            let image_key_ptr = match image_key {
+                Some(image_key) => {
+                    let image_key_str = serde_json::to_string(&image_key).unwrap_or_else(|_| "{}".to_string());
+                    CString::new(image_key_str).unwrap_or_default().into_raw()
+                }
+                None => ptr::null_mut(),
+            };
+            unsafe { *image_key_json = image_key_ptr };
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Decrypt a downloaded group image blob. Needs no client.
+///
+/// # Arguments
+/// * `image_key_json` - The JSON from `marmot_get_group_image_key`
+///
+/// # Returns
+/// A pointer to the plaintext image, or null on failure (including a blob
+/// that does not match the stored hash).
+/// The caller must free the buffer using `marmot_free_buffer`.
+#[no_mangle]
+pub extern "C" fn marmot_decrypt_group_image(
+    encrypted: *const u8,
+    encrypted_length: c_int,
+    image_key_json: *const c_char,
+    image_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("decrypt_group_image");
+
+    let encrypted = match unsafe { ffi_slice(encrypted, encrypted_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_last_error(e);
+            return ptr::null_mut();
+        }
+    };
+    let image_key: GroupImageKey = match unsafe { CStr::from_ptr(image_key_json) }.to_str() {
+        Ok(s) => match serde_json::from_str(s) {
+            Ok(image_key) => image_key,
+            Err(e) => {
+                set_last_error(MarmotError::from(e));
+                return ptr::null_mut();
+            }
+        },
+        Err(e) => {
+            set_last_error(format!("Invalid image key string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client::decrypt_group_image(encrypted, &image_key) {
+        Ok(image) => {
+            unsafe { *image_length = image.len() as c_int };
+            Box::into_raw(image.into_boxed_slice()) as *mut u8
+        }
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Get the MLS epoch authenticator for a group, for out-of-band verification
+/// that two members share the same epoch.
+///
+/// # Returns
+/// A pointer to the authenticator bytes, or null on failure.
+/// The caller must free the buffer using `marmot_free_buffer_zeroize`, as it holds key material.
+#[no_mangle]
+pub extern "C" fn marmot_epoch_authenticator(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    authenticator_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("epoch_authenticator");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.epoch_authenticator(group_id) {
+        Ok(authenticator) => {
+            unsafe { *authenticator_length = authenticator.len() as c_int };
+            let boxed = authenticator.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Export a group's ratchet tree for debugging and interop testing.
+/// The output is the standard MLS (RFC 9420) TLS encoding of the ratchet tree.
+/// This does not modify any group state.
+///
+/// # Returns
+/// A pointer to the ratchet tree data, or null on failure.
+/// The caller must free the buffer using `marmot_free_buffer`.
+#[no_mangle]
+pub extern "C" fn marmot_export_ratchet_tree(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    tree_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("export_ratchet_tree");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.export_ratchet_tree(group_id) {
+        Ok(tree) => {
+            unsafe { *tree_length = tree.len() as c_int };
+            let boxed = tree.into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Export a group's MLS GroupInfo, with its ratchet tree, as a TLS-encoded
+/// MLSMessage (RFC 9420). Unlike `marmot_export_group_state`, which is a JSON
+/// snapshot only this library understands, this is the standard format used
+/// for external commits and interop. It holds no secrets.
+///
+/// # Returns
+/// A pointer to the GroupInfo message, or null on failure.
+/// The caller must free the buffer using `marmot_free_buffer`.
+#[no_mangle]
+pub extern "C" fn marmot_export_group_info(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    group_info_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("export_group_info");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.export_group_info_mls(group_id) {
+        Ok(group_info) => {
+            unsafe { *group_info_length = group_info.len() as c_int };
+            Box::into_raw(group_info.into_boxed_slice()) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Set a custom extension in a group's MLS GroupContext, for app data such as a
+/// topic or pinned message ID.
+///
+/// Only the private-use types 0xFF00-0xFF07 may be set; any other type fails
+/// with an invalid state error. The change is a GroupContextExtensions commit,
+/// which fails if a member's client does not support the type.
+///
+/// # Returns
+/// A pointer to the JSON-serialized commit event to publish, or null on failure.
+#[no_mangle]
+pub extern "C" fn marmot_set_group_extension(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    extension_type: u16,
+    data: *const u8,
+    data_length: c_int,
+    commit_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("set_group_extension");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+    let data = match unsafe { ffi_slice(data, data_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.set_group_extension(group_id, extension_type, data) {
+        Ok(commit_data) => {
+            unsafe { *commit_length = commit_data.len() as c_int };
+            Box::into_raw(commit_data.into_boxed_slice()) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Read a custom extension from a group's MLS GroupContext.
+///
+/// # Returns
+/// 1 if the extension is present, 0 if it is not, -1 on failure.
+/// If present, `out_data` receives the extension data and `out_length` its
+/// length; the caller must free the data using `marmot_free_buffer`.
+#[no_mangle]
+pub extern "C" fn marmot_get_group_extension(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    extension_type: u16,
+    out_data: *mut *mut u8,
+    out_length: *mut c_int,
+) -> c_int {
+    begin_call("get_group_extension");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    if out_data.is_null() || out_length.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Out-parameters must not be null".to_string()));
+        return -1;
+    }
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.group_extension(group_id, extension_type) {
+        Ok(Some(data)) => {
+            unsafe {
+                *out_length = data.len() as c_int;
+                *out_data = Box::into_raw(data.into_boxed_slice()) as *mut u8;
+            }
+            1
+        }
+        Ok(None) => {
+            unsafe {
+                *out_length = 0;
+                *out_data = ptr::null_mut();
+            }
+            0
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Export a group's complete state, including its secrets, for
+/// `marmot_import_group_state`. The exporting client must not send in the
+/// group afterwards.
+///
+/// # Returns
+/// A pointer to the state data, or null on failure.
+/// The caller must free the buffer using `marmot_free_buffer_zeroize`, as it holds key material.
+#[no_mangle]
+pub extern "C" fn marmot_export_group_state(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    state_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("export_group_state");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match client.export_group_state(group_id) {
+        Ok(state) => {
+            unsafe { *state_length = state.len() as c_int };
+            let boxed = state.to_vec().into_boxed_slice();
+            Box::into_raw(boxed) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Import group state produced by `marmot_export_group_state` on a client of
+/// the same identity.
+///
+/// # Returns
+/// 0 on success, non-zero on failure (including when the group already exists
+/// or the state belongs to another identity).
+#[no_mangle]
+pub extern "C" fn marmot_import_group_state(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+    state: *const u8,
+    state_length: c_int,
+) -> c_int {
+    begin_call("import_group_state");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &mut *client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+    let state = match unsafe { ffi_slice(state, state_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.import_group_state(group_id, state) {
+        Ok(_) => 0,
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Drop the local state of a group whose epoch has diverged beyond catching
+/// up, so it can be re-joined from a fresh welcome. Other groups are unaffected.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+#[no_mangle]
+pub extern "C" fn marmot_reset_group(
+    client: *mut MarmotClient,
+    group_id: *const u8,
+    group_id_length: c_int,
+) -> c_int {
+    begin_call("reset_group");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.reset_group(group_id) {
+        Ok(()) => 0,
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Export every active group's complete state, including its secrets, as a
+/// single versioned backup blob.
+///
+/// # Returns
+/// A pointer to the backup data, or null on failure.
+/// The caller must free the buffer using `marmot_free_buffer_zeroize`, as it holds key material.
+#[no_mangle]
+pub extern "C" fn marmot_export_all_groups(
+    client: *mut MarmotClient,
+    backup_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("export_all_groups");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    match client.export_all() {
+        Ok(backup) => {
+            unsafe { *backup_length = backup.len() as c_int };
+            Box::into_raw(backup.to_vec().into_boxed_slice()) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Restore the groups in a backup from `marmot_export_all_groups` made by a
+/// client of the same identity. Groups that already exist are skipped.
+///
+/// # Returns
+/// The number of groups restored, or -1 on failure.
+#[no_mangle]
+pub extern "C" fn marmot_import_all_groups(
+    client: *mut MarmotClient,
+    backup: *const u8,
+    backup_length: c_int,
+) -> c_int {
+    begin_call("import_all_groups");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return -1;
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let backup = match unsafe { ffi_slice(backup, backup_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, e);
+            return -1;
+        }
+    };
+
+    match client.import_all(backup) {
+        Ok(restored) => restored as c_int,
+        Err(e) => {
+            set_client_error(client, e);
+            -1
+        }
+    }
+}
+
+/// Export the client's Nostr identity and groups as a backup encrypted with
+/// a passphrase, for moving them to another device.
+///
+/// # Returns
+/// A pointer to the backup data, or null on failure.
+/// The caller must free the buffer using `marmot_free_buffer_zeroize`, as it holds key material.
+#[no_mangle]
+pub extern "C" fn marmot_export_identity_backup(
+    client: *mut MarmotClient,
+    passphrase: *const c_char,
+    backup_length: *mut c_int,
+) -> *mut u8 {
+    begin_call("export_identity_backup");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    let passphrase = match unsafe { CStr::from_ptr(passphrase) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid passphrase string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.export_identity_backup(passphrase) {
+        Ok(backup) => {
+            unsafe { *backup_length = backup.len() as c_int };
+            Box::into_raw(backup.into_boxed_slice()) as *mut u8
+        }
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Create a client from a backup made with `marmot_export_identity_backup`.
+///
+/// # Returns
+/// A pointer to the client, or null on failure. A wrong passphrase fails with
+/// the crypto error code.
+/// The caller must free the client using `marmot_destroy_client`.
+#[no_mangle]
+pub extern "C" fn marmot_import_identity_backup(
+    backup: *const u8,
+    backup_length: c_int,
+    passphrase: *const c_char,
+) -> *mut MarmotClient {
+    begin_call("import_identity_backup");
+
+    let backup = match unsafe { ffi_slice(backup, backup_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_last_error(e);
+            return ptr::null_mut();
+        }
+    };
+
+    let passphrase = match unsafe { CStr::from_ptr(passphrase) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_last_error(format!("Invalid passphrase string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    register_client(MarmotClient::import_identity_backup(backup, passphrase))
+}
+
+/// Sign an app-level Nostr event (e.g. profile metadata or a reaction outside
+/// any group) with the client's key.
+///
+/// # Arguments
+/// * `unsigned_event_json` - JSON unsigned event whose pubkey is the client's
+///
+/// # Returns
+/// A pointer to the signed event JSON, or null on failure.
+/// The caller must free the string using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_sign_event(client: *mut MarmotClient, unsigned_event_json: *const c_char) -> *mut c_char {
+    begin_call("sign_event");
+
+    if client.is_null() {
+        set_last_error("Client is null");
+        return ptr::null_mut();
+    }
+
+    let client = unsafe { &*client };
+    client.clear_last_error();
+
+    if unsigned_event_json.is_null() {
+        set_client_error(client, MarmotError::InvalidState("Event must not be null".into()));
+        return ptr::null_mut();
+    }
+    let unsigned = match unsafe { CStr::from_ptr(unsigned_event_json) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_client_error(client, format!("Invalid event string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match client.sign_event(unsigned) {
+        Ok(event) => CString::new(event).unwrap_or_default().into_raw(),
+        Err(e) => {
+            set_client_error(client, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Verify a Nostr event's id and signature. Needs no client.
+///
+/// # Returns
+/// 1 if the event is valid, 0 if its id or signature is invalid, -1 if it
+/// cannot be parsed.
+#[no_mangle]
+pub extern "C" fn marmot_verify_event(event_json: *const c_char) -> c_int {
+    begin_call("verify_event");
+
+    if event_json.is_null() {
+        set_last_error("Event must not be null");
+        return -1;
+    }
+    let event = match unsafe { CStr::from_ptr(event_json) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_last_error(format!("Invalid event string: {}", e));
+            return -1;
+        }
+    };
+
+    match client::verify_event(event) {
+        Ok(true) => 1,
+        Ok(false) => 0,
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+/// List the MLS ciphersuites this build supports, for choosing a value to pass
+/// to `marmot_create_client_with_ciphersuite`. Needs no client.
+///
+/// # Arguments
+/// * `out_json` - Receives a JSON array of `{ "code", "name" }` objects, where
+///   `code` is the IANA ciphersuite value. Free using `marmot_free_string`.
+///
+/// # Returns
+/// 0 on success, non-zero on failure.
+#[no_mangle]
+pub extern "C" fn marmot_supported_ciphersuites(out_json: *mut *mut c_char) -> c_int {
+    begin_call("supported_ciphersuites");
+
+    if out_json.is_null() {
+        set_last_error("Output pointer must not be null");
+        return -1;
+    }
+
+    let json = serde_json::to_string(&client::supported_ciphersuites()).unwrap_or_else(|_| "[]".to_string());
+    unsafe { *out_json = CString::new(json).unwrap_or_default().into_raw() };
+    0
+}
+
+/// Self-test the library before going live: runs a full local round trip
+/// (group creation, add member, welcome, encrypt and decrypt) between two
+/// throwaway clients with ephemeral keys. Needs no client.
+///
+/// # Returns
+/// 0 if every step succeeded, non-zero on failure, with the failing step
+/// described by `marmot_get_last_error`.
+#[no_mangle]
+pub extern "C" fn marmot_health_check() -> c_int {
+    begin_call("health_check");
+
+    match client::health_check() {
+        Ok(()) => 0,
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+/// Encode a group ID as lowercase hex, the representation used in the
+/// library's JSON outputs. Needs no client.
+///
+/// # Returns
+/// A hex string, or null on failure.
+/// The caller must free the string using `marmot_free_string`.
+#[no_mangle]
+pub extern "C" fn marmot_group_id_to_hex(group_id: *const u8, group_id_length: c_int) -> *mut c_char {
+    begin_call("group_id_to_hex");
+
+    let group_id = match unsafe { ffi_group_id(group_id, group_id_length) } {
+        Ok(s) => s,
+        Err(e) => {
+            set_last_error(e);
+            return ptr::null_mut();
+        }
+    };
+
+    CString::new(hex::encode(group_id)).unwrap_or_default().into_raw()
+}
+
+/// Decode a hex group ID, as produced by `marmot_group_id_to_hex`, into bytes.
+/// Needs no client.
+///
+/// # Returns
+/// A pointer to the group ID, or null on failure.
+/// The caller must free the buffer using `marmot_free_buffer`.
+#[no_mangle]
+pub extern "C" fn marmot_group_id_from_hex(group_id_hex: *const c_char, group_id_length: *mut c_int) -> *mut u8 {
+    begin_call("group_id_from_hex");
+
+    if group_id_hex.is_null() || group_id_length.is_null() {
+        set_last_error("Group ID hex and length must not be null");
+        return ptr::null_mut();
+    }
+
+    let group_id_hex = match unsafe { CStr::from_ptr(group_id_hex) }.to_str() {
+        Ok(s) => s,
+        Err(e) => {
+            set_last_error(format!("Invalid group ID string: {}", e));
+            return ptr::null_mut();
+        }
+    };
+
+    match hex::decode(group_id_hex) {
+        Ok(group_id) => {
+            unsafe { *group_id_length = group_id.len() as c_int };
+            Box::into_raw(group_id.into_boxed_slice()) as *mut u8
+        }
+        Err(e) => {
+            set_last_error(MarmotError::from(e));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a buffer allocated by this library.
+///
+/// # Arguments
+/// * `length` - The length reported when the buffer was returned
+#[no_mangle]
+pub extern "C" fn marmot_free_buffer(buffer: *mut u8, length: c_int) {
+    if buffer.is_null() || length < 0 {
+        return;
+    }
+
+    unsafe {
+        // Buffers are boxed slices, so they are freed with the layout of one
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buffer, length as usize)));
+    }
+}
+
+/// Overwrite a buffer allocated by this library with zeros, then free it.
+/// Use this instead of `marmot_free_buffer` for outputs that may hold key
+/// material: key packages, exported group state and backups, and the epoch
+/// authenticator.
+///
+/// # Arguments
+/// * `length` - The length reported when the buffer was returned
+#[no_mangle]
+pub extern "C" fn marmot_free_buffer_zeroize(buffer: *mut u8, length: c_int) {
+    if buffer.is_null() || length < 0 {
+        return;
+    }
+
+    unsafe {
+        let mut data = Box::from_raw(ptr::slice_from_raw_parts_mut(buffer, length as usize));
+        zeroize::Zeroize::zeroize(&mut *data);
+    }
+}
+
+/// Free a buffer returned by value by this library.
+#[no_mangle]
+pub extern "C" fn marmot_free_owned_buffer(buffer: MarmotBuffer) {
+    if buffer.data.is_null() || buffer.length < 0 {
+        return;
+    }
+
+    unsafe {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.length as usize)));
+    }
+}
+
+/// Free a string allocated by this library.
+#[no_mangle]
+pub extern "C" fn marmot_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        unsafe {
+            drop(CString::from_raw(s));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Address of a buffer whose contents should be checked when it is freed
+    static WATCHED_BUFFER: AtomicUsize = AtomicUsize::new(0);
+    static WATCHED_WAS_ZEROED: AtomicBool = AtomicBool::new(false);
+    static WATCHED_SIZE: AtomicUsize = AtomicUsize::new(0);
+    /// Secret to look for in the watched buffer when it is freed
+    static WATCHED_SECRET: Mutex<Option<[u8; 32]>> = Mutex::new(None);
+    static WATCHED_HELD_SECRET: AtomicBool = AtomicBool::new(false);
+
+    /// Allocator that records the size of the watched buffer, whether it was
+    /// zeroed and whether it still held the watched secret before deallocation.
+    struct WatchingAllocator;
+
+    unsafe impl GlobalAlloc for WatchingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            if ptr as usize == WATCHED_BUFFER.load(Ordering::SeqCst) {
+                let contents = slice::from_raw_parts(ptr, layout.size());
+                WATCHED_WAS_ZEROED.store(contents.iter().all(|&b| b == 0), Ordering::SeqCst);
+                WATCHED_SIZE.store(layout.size(), Ordering::SeqCst);
+                if let Some(secret) = *WATCHED_SECRET.lock().unwrap() {
+                    WATCHED_HELD_SECRET.store(contents.windows(secret.len()).any(|w| w == secret), Ordering::SeqCst);
+                }
+            }
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: WatchingAllocator = WatchingAllocator;
+
+    #[test]
+    fn free_buffer_zeroize_wipes_contents_before_deallocation() {
+        let data = vec![0xAAu8; 64].into_boxed_slice();
+        let length = data.len() as c_int;
+        let buffer = Box::into_raw(data) as *mut u8;
+
+        WATCHED_BUFFER.store(buffer as usize, Ordering::SeqCst);
+        marmot_free_buffer_zeroize(buffer, length);
+        WATCHED_BUFFER.store(0, Ordering::SeqCst);
+
+        assert!(WATCHED_WAS_ZEROED.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn free_buffer_deallocates_the_whole_buffer() {
+        let data = vec![0x55u8; 48].into_boxed_slice();
+        let length = data.len() as c_int;
+        let buffer = Box::into_raw(data) as *mut u8;
+
+        WATCHED_BUFFER.store(buffer as usize, Ordering::SeqCst);
+        marmot_free_buffer(buffer, length);
+        WATCHED_BUFFER.store(0, Ordering::SeqCst);
+
+        assert_eq!(WATCHED_SIZE.load(Ordering::SeqCst), 48);
+    }
+
+    #[test]
+    fn destroyed_clients_are_invalid_and_destroy_is_idempotent() {
+        let keys = nostr::Keys::generate();
+        let private_key = CString::new(keys.secret_key().to_secret_hex()).unwrap();
+        let public_key = CString::new(keys.public_key().to_hex()).unwrap();
+
+        let client = marmot_create_client(private_key.as_ptr(), public_key.as_ptr(), ptr::null());
+        assert!(!client.is_null());
+        assert_eq!(marmot_client_is_valid(client), 1);
+
+        marmot_destroy_client(client);
+        assert_eq!(marmot_client_is_valid(client), 0);
+
+        // A second destroy must not double free
+        marmot_destroy_client(client);
+        assert_eq!(marmot_client_is_valid(ptr::null_mut()), 0);
+    }
+
+    #[test]
+    fn wipe_client_overwrites_the_secret_key_before_freeing() {
+        let keys = nostr::Keys::generate();
+        let private_key = CString::new(keys.secret_key().to_secret_hex()).unwrap();
+        let public_key = CString::new(keys.public_key().to_hex()).unwrap();
+        let secret: [u8; 32] = hex::decode(keys.secret_key().to_secret_hex()).unwrap().try_into().unwrap();
+
+        let client = marmot_create_client(private_key.as_ptr(), public_key.as_ptr(), ptr::null());
+        assert!(!client.is_null());
+
+        *WATCHED_SECRET.lock().unwrap() = Some(secret);
+        WATCHED_HELD_SECRET.store(true, Ordering::SeqCst);
+        WATCHED_BUFFER.store(client as usize, Ordering::SeqCst);
+        marmot_wipe_client(client);
+        WATCHED_BUFFER.store(0, Ordering::SeqCst);
+        *WATCHED_SECRET.lock().unwrap() = None;
+
+        assert!(!WATCHED_HELD_SECRET.load(Ordering::SeqCst));
+        assert_eq!(marmot_client_is_valid(client), 0);
+    }
+
+    #[test]
+    fn group_ids_round_trip_through_hex() {
+        let group_id: Vec<u8> = (0u8..32).collect();
+
+        let hex_ptr = marmot_group_id_to_hex(group_id.as_ptr(), group_id.len() as c_int);
+        assert!(!hex_ptr.is_null());
+        let group_id_hex = unsafe { CStr::from_ptr(hex_ptr) }.to_str().unwrap().to_string();
+        assert_eq!(group_id_hex, hex::encode(&group_id));
+
+        let mut length = 0;
+        let decoded = marmot_group_id_from_hex(hex_ptr, &mut length);
+        assert!(!decoded.is_null());
+        assert_eq!(unsafe { slice::from_raw_parts(decoded, length as usize) }, group_id.as_slice());
+
+        let not_hex = CString::new("not hex").unwrap();
+        assert!(marmot_group_id_from_hex(not_hex.as_ptr(), &mut length).is_null());
+        assert_eq!(marmot_get_last_error_code(), MarmotError::InvalidKey(String::new()).code());
+
+        marmot_free_buffer(decoded, length);
+        marmot_free_string(hex_ptr);
+    }
+
+    #[test]
+    fn process_commit_diff_reports_added_and_removed_members() {
+        use crate::client::tests::{joined_pair, key_package_event, welcome_input};
+
+        let (alice, bob, group_id) = joined_pair();
+        let (carol_keys, dave_keys) = (nostr::Keys::generate(), nostr::Keys::generate());
+        let client_for = |keys: &nostr::Keys| MarmotClient::new(&keys.secret_key().to_secret_hex(), "", None).unwrap();
+        let (carol, dave) = (client_for(&carol_keys), client_for(&dave_keys));
+
+        let added: serde_json::Value =
+            serde_json::from_slice(&alice.add_member(&group_id, &key_package_event(&dave)).unwrap()).unwrap();
+        bob.process_commit(&group_id, added["commit"].to_string().as_bytes()).unwrap();
+        dave.process_welcome(&welcome_input(&serde_json::to_vec(&added).unwrap())).unwrap();
+
+        // Dave's self-removal proposal is held by the other members, so Alice's next
+        // commit both adds Carol and removes Dave
+        let proposal = dave.propose_self_removal(&group_id).unwrap();
+        alice.process_message(&proposal).unwrap();
+        bob.process_message(&proposal).unwrap();
+        let commit: serde_json::Value =
+            serde_json::from_slice(&alice.add_member(&group_id, &key_package_event(&carol)).unwrap()).unwrap();
+        let commit = commit["commit"].to_string();
+
+        let bob = Box::into_raw(Box::new(bob));
+        let (mut added, mut removed) = (ptr::null_mut(), ptr::null_mut());
+        let result = marmot_process_commit_diff(
+            bob,
+            group_id.as_ptr(),
+            group_id.len() as c_int,
+            commit.as_ptr(),
+            commit.len() as c_int,
+            &mut added,
+            &mut removed,
+        );
+        assert_eq!(result, 0);
+        let lists: Vec<Vec<String>> = [added, removed]
+            .iter()
+            .map(|json| serde_json::from_str(unsafe { CStr::from_ptr(*json) }.to_str().unwrap()).unwrap())
+            .collect();
+        assert_eq!(lists[0], vec![carol_keys.public_key().to_hex()]);
+        assert_eq!(lists[1], vec![dave_keys.public_key().to_hex()]);
+        marmot_free_string(added);
+        marmot_free_string(removed);
+
+        // Output pointers are required
+        let result = marmot_process_commit_diff(
+            bob,
+            group_id.as_ptr(),
+            group_id.len() as c_int,
+            commit.as_ptr(),
+            commit.len() as c_int,
+            ptr::null_mut(),
+            &mut removed,
+        );
+        assert_eq!(result, -1);
+
+        drop(unsafe { Box::from_raw(bob) });
+    }
+
+    #[test]
+    fn create_group_rejects_null_epoch_pointer() {
+        let client = Box::into_raw(Box::new(crate::client::tests::new_client()));
+        let name = CString::new("group").unwrap();
+        let mut length = 0;
+
+        let group_id = marmot_create_group(client, name.as_ptr(), &mut length, ptr::null_mut(), ptr::null_mut());
+        assert!(group_id.is_null());
+        assert_eq!(unsafe { &*client }.last_error_code(), MarmotError::InvalidState(String::new()).code());
+
+        let mut epoch = u64::MAX;
+        let group_id = marmot_create_group(client, name.as_ptr(), &mut length, &mut epoch, ptr::null_mut());
+        assert!(!group_id.is_null());
+        assert_eq!(epoch, 1);
+
+        marmot_free_buffer(group_id, length);
+        drop(unsafe { Box::from_raw(client) });
+    }
+
+    #[test]
+    fn create_group_echoes_the_resolved_config() {
+        let keys = nostr::Keys::generate();
+        let public_key = keys.public_key().to_hex();
+        let client = MarmotClient::new(&keys.secret_key().to_secret_hex(), &public_key, None).unwrap();
+        let client = Box::into_raw(Box::new(client));
+        let name = CString::new("echo").unwrap();
+        let mut length = 0;
+        let mut epoch = 0u64;
+        let mut config = ptr::null_mut();
+
+        let group_id = marmot_create_group(client, name.as_ptr(), &mut length, &mut epoch, &mut config);
+        assert!(!group_id.is_null());
+
+        let echoed: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(config) }.to_str().unwrap()).unwrap();
+        assert_eq!(echoed["name"], "echo");
+        assert_eq!(echoed["description"], "");
+        assert_eq!(echoed["admins"], serde_json::json!([public_key]));
+        let mut relays: Vec<String> = serde_json::from_value(echoed["relays"].clone()).unwrap();
+        relays.sort();
+        assert_eq!(relays, vec!["wss://nos.lol", "wss://relay.damus.io"]);
+
+        marmot_free_string(config);
+        marmot_free_buffer(group_id, length);
+        drop(unsafe { Box::from_raw(client) });
+    }
+
+    #[test]
+    fn returned_buffers_carry_their_allocation_length() {
+        let client = Box::into_raw(Box::new(crate::client::tests::new_client()));
+
+        let key_package = marmot_generate_key_package_buffer(client);
+        assert!(!key_package.data.is_null());
+        let data = unsafe { slice::from_raw_parts(key_package.data, key_package.length as usize) };
+        assert!(serde_json::from_slice::<serde_json::Value>(data).is_ok());
+
+        let length = key_package.length as usize;
+        WATCHED_BUFFER.store(key_package.data as usize, Ordering::SeqCst);
+        marmot_free_owned_buffer(key_package);
+        WATCHED_BUFFER.store(0, Ordering::SeqCst);
+        assert_eq!(WATCHED_SIZE.load(Ordering::SeqCst), length);
+
+        let name = CString::new("group").unwrap();
+        let mut epoch = u64::MAX;
+        let group_id = marmot_create_group_buffer(client, name.as_ptr(), &mut epoch);
+        assert!(!group_id.data.is_null());
+        assert_eq!(epoch, 1);
+
+        let plaintext = CString::new("hello").unwrap();
+        let ciphertext = marmot_encrypt_message_buffer(client, group_id.data, group_id.length, plaintext.as_ptr());
+        assert!(!ciphertext.data.is_null());
+        assert!(ciphertext.length > 0);
+
+        marmot_free_owned_buffer(ciphertext);
+        marmot_free_owned_buffer(group_id);
+
+        let failed = marmot_encrypt_message_buffer(client, ptr::null(), 0, plaintext.as_ptr());
+        assert!(failed.data.is_null());
+        assert_eq!(failed.length, 0);
+
+        drop(unsafe { Box::from_raw(client) });
+    }
+
+    #[test]
+    fn applying_a_commit_twice_succeeds_both_times() {
+        let (alice, bob, group_id) = crate::client::tests::joined_pair();
+        let bob = Box::into_raw(Box::new(bob));
+        let commit = alice.update_keys(&group_id).unwrap();
+
+        for _ in 0..2 {
+            let result = marmot_process_commit(
+                bob,
+                group_id.as_ptr(),
+                group_id.len() as c_int,
+                commit.as_ptr(),
+                commit.len() as c_int,
+            );
+            assert_eq!(result, 0);
+        }
+        assert_eq!(unsafe { &*bob }.current_epoch(&group_id), alice.current_epoch(&group_id));
+
+        drop(unsafe { Box::from_raw(bob) });
+    }
+
+    #[test]
+    fn verify_event_checks_id_and_signature() {
+        let keys = nostr::Keys::generate();
+        let event = nostr::EventBuilder::new(nostr::Kind::TextNote, "hello").sign_with_keys(&keys).unwrap();
+        let mut event = serde_json::to_value(&event).unwrap();
+
+        let valid = CString::new(event.to_string()).unwrap();
+        assert_eq!(marmot_verify_event(valid.as_ptr()), 1);
+
+        event["content"] = "tampered".into();
+        let tampered = CString::new(event.to_string()).unwrap();
+        assert_eq!(marmot_verify_event(tampered.as_ptr()), 0);
+
+        let malformed = CString::new("{ not json").unwrap();
+        assert_eq!(marmot_verify_event(malformed.as_ptr()), -1);
+        let error = marmot_get_last_error();
+        assert!(!error.is_null());
+        marmot_free_string(error);
+    }
+
+    #[test]
+    fn members_page_rejects_null_outputs() {
+        let alice = crate::client::tests::new_client();
+        let (group_id, _) = alice.create_group("page").unwrap();
+        let alice = Box::into_raw(Box::new(alice));
+        let mut members = ptr::null_mut();
+        let mut total = 0u32;
+
+        let result = marmot_get_members_page(alice, group_id.as_ptr(), group_id.len() as c_int, 0, 10, &mut members, ptr::null_mut());
+        assert_eq!(result, -1);
+        assert!(members.is_null());
+        let result = marmot_get_members_page(alice, group_id.as_ptr(), group_id.len() as c_int, 0, 10, ptr::null_mut(), &mut total);
+        assert_eq!(result, -1);
+
+        assert_eq!(marmot_get_members_page(alice, group_id.as_ptr(), group_id.len() as c_int, 0, 10, &mut members, &mut total), 0);
+        assert_eq!(total, 1);
+
+        marmot_free_string(members);
+        drop(unsafe { Box::from_raw(alice) });
+    }
+
+    #[test]
+    fn group_admins_rejects_a_null_output() {
+        let alice = crate::client::tests::new_client();
+        let (group_id, _) = alice.create_group("admins").unwrap();
+        let alice = Box::into_raw(Box::new(alice));
+
+        assert_eq!(marmot_get_group_admins(alice, group_id.as_ptr(), group_id.len() as c_int, ptr::null_mut()), -1);
+        assert_eq!(unsafe { &*alice }.last_error_code(), MarmotError::InvalidState(String::new()).code());
+
+        drop(unsafe { Box::from_raw(alice) });
+    }
+
+    #[test]
+    fn delete_key_package_rejects_a_null_reference() {
+        let client = Box::into_raw(Box::new(crate::client::tests::new_client()));
+
+        assert_eq!(marmot_delete_key_package(client, ptr::null()), -1);
+        assert_eq!(unsafe { &*client }.last_error_code(), MarmotError::InvalidState(String::new()).code());
+
+        drop(unsafe { Box::from_raw(client) });
+    }
+
+    #[test]
+    fn group_exists_fails_when_storage_fails() {
+        let alice = crate::client::tests::new_client();
+        let (group_id, _) = alice.create_group("exists").unwrap();
+        crate::client::tests::fail_storage_reads(&alice, 1);
+        let alice = Box::into_raw(Box::new(alice));
+
+        assert_eq!(marmot_group_exists(alice, group_id.as_ptr(), group_id.len() as c_int), -1);
+        assert_eq!(unsafe { &*alice }.last_error_code(), MarmotError::StorageError(String::new()).code());
+        assert_eq!(marmot_group_exists(alice, group_id.as_ptr(), group_id.len() as c_int), 1);
+
+        drop(unsafe { Box::from_raw(alice) });
+    }
+
+    #[test]
+    fn health_check_passes_on_a_working_build() {
+        assert_eq!(marmot_health_check(), 0);
+        assert!(marmot_get_last_error().is_null());
+    }
+
+    #[test]
+    fn decrypt_reports_the_rumor_timestamp() {
+        let (alice, bob, group_id) = crate::client::tests::joined_pair();
+        let bob = Box::into_raw(Box::new(bob));
+        let mut sender = ptr::null_mut();
+        let mut epoch = 0u64;
+        let mut timestamp = 0u64;
+
+        let before = nostr::Timestamp::now().as_u64();
+        let event = alice.encrypt_message(&group_id, "hello").unwrap();
+        let after = nostr::Timestamp::now().as_u64();
+
+        let plaintext = marmot_decrypt_message(
+            bob,
+            group_id.as_ptr(),
+            group_id.len() as c_int,
+            event.as_ptr(),
+            event.len() as c_int,
+            &mut sender,
+            &mut epoch,
+            ptr::null_mut(),
+        );
+        assert!(plaintext.is_null());
+        assert_eq!(unsafe { &*bob }.last_error_code(), MarmotError::InvalidState(String::new()).code());
+
+        let plaintext = marmot_decrypt_message(
+            bob,
+            group_id.as_ptr(),
+            group_id.len() as c_int,
+            event.as_ptr(),
+            event.len() as c_int,
+            &mut sender,
+            &mut epoch,
+            &mut timestamp,
+        );
+        assert!(!plaintext.is_null());
+        assert!((before..=after).contains(&timestamp));
+
+        marmot_free_string(plaintext);
+        marmot_free_string(sender);
+        drop(unsafe { Box::from_raw(bob) });
+    }
+
+    #[test]
+    fn nul_in_decrypted_content_errors_on_string_path_and_survives_raw_path() {
+        let (alice, bob, group_id) = crate::client::tests::joined_pair();
+        let bob = Box::into_raw(Box::new(bob));
+        let mut sender = ptr::null_mut();
+        let mut epoch = 0u64;
+
+        let event = alice.encrypt_message(&group_id, "a\0b").unwrap();
+        let plaintext = marmot_decrypt_message(
+            bob,
+            group_id.as_ptr(),
+            group_id.len() as c_int,
+            event.as_ptr(),
+            event.len() as c_int,
+            &mut sender,
+            &mut epoch,
+            &mut 0,
+        );
+        assert!(plaintext.is_null());
+        assert_eq!(unsafe { &*bob }.last_error_code(), MarmotError::InvalidState(String::new()).code());
+
+        // The rejected event was consumed, but its content is still available
+        let mut length = 0;
+        let raw = marmot_decrypt_message_raw(
+            bob,
+            group_id.as_ptr(),
+            group_id.len() as c_int,
+            event.as_ptr(),
+            event.len() as c_int,
+            &mut sender,
+            &mut epoch,
+            &mut length,
+        );
+        assert!(!raw.is_null());
+        assert_eq!(unsafe { slice::from_raw_parts(raw, length as usize) }, b"a\0b");
+        marmot_free_buffer(raw, length);
+        marmot_free_string(sender);
+
+        // A fresh event goes straight through the raw path
+        let event = alice.encrypt_message(&group_id, "c\0d").unwrap();
+        let raw = marmot_decrypt_message_raw(
+            bob,
+            group_id.as_ptr(),
+            group_id.len() as c_int,
+            event.as_ptr(),
+            event.len() as c_int,
+            &mut sender,
+            &mut epoch,
+            &mut length,
+        );
+        assert!(!raw.is_null());
+        assert_eq!(unsafe { slice::from_raw_parts(raw, length as usize) }, b"c\0d");
+
+        marmot_free_buffer(raw, length);
+        marmot_free_string(sender);
+        drop(unsafe { Box::from_raw(bob) });
+    }
+
+    #[test]
+    fn negative_lengths_are_rejected() {
+        let (_alice, bob, group_id) = crate::client::tests::joined_pair();
+        let bob = Box::into_raw(Box::new(bob));
+        let invalid_state = MarmotError::InvalidState(String::new()).code();
+
+        let mut commit_length = 0;
+        let commit = marmot_update_keys(bob, group_id.as_ptr(), -1, &mut commit_length);
+        assert!(commit.is_null());
+        assert_eq!(unsafe { &*bob }.last_error_code(), invalid_state);
+
+        let result = marmot_process_commit(bob, group_id.as_ptr(), group_id.len() as c_int, ptr::null(), 5);
+        assert_eq!(result, -1);
+        assert_eq!(unsafe { &*bob }.last_error_code(), invalid_state);
+
+        let mut state_length = 0;
+        let state = marmot_export_group_state(bob, group_id.as_ptr(), c_int::MIN, &mut state_length);
+        assert!(state.is_null());
+        assert_eq!(unsafe { &*bob }.last_error_code(), invalid_state);
+
+        drop(unsafe { Box::from_raw(bob) });
+    }
+
+    #[test]
+    fn client_errors_do_not_leak_across_threads() {
+        let first = register_client(Ok(crate::client::tests::new_client())) as usize;
+        let second = register_client(Ok(crate::client::tests::new_client())) as usize;
+
+        let last_error = |client: usize| {
+            let error = marmot_client_get_last_error(client as *mut MarmotClient);
+            let message = unsafe { CStr::from_ptr(error) }.to_str().unwrap().to_string();
+            marmot_free_string(error);
+            message
+        };
+
+        let not_found = std::thread::spawn(move || {
+            for _ in 0..200 {
+                let mut epoch = 0u64;
+                let group_id = [0u8; 32];
+                marmot_get_epoch(first as *mut MarmotClient, group_id.as_ptr(), 32, &mut epoch);
+                assert_eq!(last_error(first), "Group not found");
+            }
+        });
+        let negative_length = std::thread::spawn(move || {
+            for _ in 0..200 {
+                let mut epoch = 0u64;
+                marmot_get_epoch(second as *mut MarmotClient, ptr::null(), -1, &mut epoch);
+                assert!(last_error(second).contains("Negative buffer length"));
+            }
+        });
+
+        not_found.join().unwrap();
+        negative_length.join().unwrap();
+
+        marmot_destroy_client(first as *mut MarmotClient);
+        marmot_destroy_client(second as *mut MarmotClient);
+    }
+
+    #[test]
+    fn client_last_error_ignores_destroyed_and_foreign_clients() {
+        let client = register_client(Ok(crate::client::tests::new_client()));
+        let mut epoch = 0u64;
+        marmot_get_epoch(client, [0u8; 32].as_ptr(), 32, &mut epoch);
+        let error = marmot_client_get_last_error(client);
+        assert!(!error.is_null());
+        marmot_free_string(error);
+
+        marmot_destroy_client(client);
+        assert!(marmot_client_get_last_error(client).is_null());
+
+        let foreign = Box::into_raw(Box::new(crate::client::tests::new_client()));
+        marmot_get_epoch(foreign, [0u8; 32].as_ptr(), 32, &mut epoch);
+        assert!(marmot_client_get_last_error(foreign).is_null());
+        drop(unsafe { Box::from_raw(foreign) });
+    }
+
+    #[test]
+    fn last_error_is_per_thread() {
+        let failed = std::thread::spawn(|| {
+            let mut epoch = 0u64;
+            marmot_get_epoch(ptr::null_mut(), ptr::null(), 0, &mut epoch);
+            assert_eq!(marmot_get_last_error_code(), GENERIC_ERROR_CODE);
+        });
+        failed.join().unwrap();
+
+        let untouched = std::thread::spawn(|| {
+            assert!(marmot_get_last_error().is_null());
+            assert_eq!(marmot_get_last_error_code(), 0);
+        });
+        untouched.join().unwrap();
+    }
+
+    #[test]
+    fn empty_group_ids_are_rejected_up_front() {
+        let client = Box::into_raw(Box::new(crate::client::tests::new_client()));
+        let plaintext = CString::new("hello").unwrap();
+        let mut length = 0;
+
+        let ciphertext = marmot_encrypt_message(client, ptr::null(), 0, plaintext.as_ptr(), &mut length);
+        assert!(ciphertext.is_null());
+        assert_eq!(unsafe { &*client }.last_error().as_deref(), Some("Invalid state: empty group id"));
+
+        drop(unsafe { Box::from_raw(client) });
+    }
+
+    static PUBLISHED: Mutex<Vec<serde_json::Value>> = Mutex::new(Vec::new());
+
+    extern "C" fn capture_published(event: *const c_char) {
+        let event = unsafe { CStr::from_ptr(event) }.to_str().unwrap();
+        PUBLISHED.lock().unwrap().push(serde_json::from_str(event).unwrap());
+    }
+
+    /// Replay a captured event into `client` and return the parsed result.
+    fn replay(client: *mut MarmotClient, event: &serde_json::Value) -> serde_json::Value {
+        let event = event.to_string();
+        let mut result = ptr::null_mut();
+        assert_eq!(
+            marmot_replay_published_event(client, event.as_ptr(), event.len() as c_int, &mut result),
+            0
+        );
+        let json = serde_json::from_str(unsafe { CStr::from_ptr(result) }.to_str().unwrap()).unwrap();
+        marmot_free_string(result);
+        json
+    }
+
+    #[test]
+    fn nostr_proxy_round_trips_a_welcome_without_relays() {
+        use crate::client::tests::{key_package_event, new_client};
+
+        let alice = Box::into_raw(Box::new(new_client()));
+        let bob = Box::into_raw(Box::new(new_client()));
+        assert_eq!(marmot_set_nostr_proxy(alice, Some(capture_published)), 0);
+
+        let (group_id, _) = unsafe { &*alice }.create_group("offline").unwrap();
+        unsafe { &*alice }.add_member(&group_id, &key_package_event(unsafe { &*bob })).unwrap();
+
+        // Events are published in order, so the last of a kind is the newest
+        let take = |kind: u64| {
+            let published = std::mem::take(&mut *PUBLISHED.lock().unwrap());
+            published.into_iter().filter(|event| event["kind"] == kind).last().unwrap()
+        };
+
+        let joined = replay(bob, &take(444));
+        assert_eq!(joined["type"], "joined");
+        assert_eq!(joined["group_id"], hex::encode(&group_id));
+        assert_eq!(joined["epoch"], unsafe { &*alice }.current_epoch(&group_id).unwrap());
+
+        unsafe { &*alice }.encrypt_message(&group_id, "no network").unwrap();
+        let message = replay(bob, &take(445));
+        assert_eq!(message["type"], "message");
+        assert_eq!(message["content"], "no network");
+
+        drop(unsafe { Box::from_raw(alice) });
+        drop(unsafe { Box::from_raw(bob) });
+    }
+
+    #[test]
+    fn clear_last_error_resets_the_thread_error() {
+        let mut epoch = 0u64;
+        marmot_get_epoch(ptr::null_mut(), ptr::null(), 0, &mut epoch);
+        let error = marmot_get_last_error();
+        assert!(!error.is_null());
+        marmot_free_string(error);
+
+        marmot_clear_last_error();
+        assert!(marmot_get_last_error().is_null());
+        assert_eq!(marmot_get_last_error_code(), 0);
+    }
+
+    #[test]
+    fn last_error_json_records_the_failed_operation() {
+        let client = Box::into_raw(Box::new(crate::client::tests::new_client()));
+        let (group_id, _) = unsafe { &*client }.create_group("errors").unwrap();
+        let key_package = b"not a key package";
+        let mut welcome_length = 0;
+
+        let welcome = marmot_add_member(
+            client,
+            group_id.as_ptr(),
+            group_id.len() as c_int,
+            key_package.as_ptr(),
+            key_package.len() as c_int,
+            &mut welcome_length,
+        );
+        assert!(welcome.is_null());
+
+        let json = marmot_get_last_error_json();
+        assert!(!json.is_null());
+        let error: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(json) }.to_str().unwrap()).unwrap();
+        marmot_free_string(json);
+
+        assert_eq!(error["operation"], "add_member");
+        assert_eq!(error["group_id"], hex::encode(&group_id));
+        assert_eq!(error["code"], marmot_get_last_error_code());
+        assert!(!error["message"].as_str().unwrap().is_empty());
+
+        marmot_clear_last_error();
+        assert!(marmot_get_last_error_json().is_null());
+
+        drop(unsafe { Box::from_raw(client) });
+    }
+
+    #[test]
+    fn nul_bytes_are_stripped_from_group_names() {
+        use crate::client::tests::{key_package_event, new_client, welcome_input};
+
+        let alice = Box::into_raw(Box::new(new_client()));
+        let bob = Box::into_raw(Box::new(new_client()));
+        let (group_id, _) = unsafe { &*alice }.create_group("team\0chat").unwrap();
+        let added = unsafe { &*alice }.add_member(&group_id, &key_package_event(unsafe { &*bob })).unwrap();
+
+        let read_name = |name: *mut c_char| {
+            let value = unsafe { CStr::from_ptr(name) }.to_str().unwrap().to_string();
+            marmot_free_string(name);
+            value
+        };
+
+        let welcome = welcome_input(&added);
+        let mut joined_length = 0;
+        let mut name = ptr::null_mut();
+        let mut epoch = 0u64;
+        let mut members = ptr::null_mut();
+        let joined = marmot_process_welcome(
+            bob,
+            welcome.as_ptr(),
+            welcome.len() as c_int,
+            &mut joined_length,
+            &mut epoch,
+            &mut name,
+            &mut members,
+        );
+        assert!(!joined.is_null());
+        assert_eq!(read_name(name), "teamchat");
+        marmot_free_string(members);
+        marmot_free_buffer(joined, joined_length);
+
+        let mut name = ptr::null_mut();
+        let mut members = ptr::null_mut();
+        let result = marmot_get_group_info(
+            alice,
+            group_id.as_ptr(),
+            group_id.len() as c_int,
+            &mut name,
+            &mut epoch,
+            &mut members,
+        );
+        assert_eq!(result, 0);
+        assert_eq!(read_name(name), "teamchat");
+        marmot_free_string(members);
+
+        drop(unsafe { Box::from_raw(alice) });
+        drop(unsafe { Box::from_raw(bob) });
+    }
+
+    #[test]
+    fn encrypt_message_ex_reports_the_event_id() {
+        let client = Box::into_raw(Box::new(crate::client::tests::new_client()));
+        let (group_id, _) = unsafe { &*client }.create_group("ids").unwrap();
+        let plaintext = CString::new("hello").unwrap();
+        let mut length = 0;
+        let mut event_id = ptr::null_mut();
+
+        let ciphertext = marmot_encrypt_message_ex(
+            client,
+            group_id.as_ptr(),
+            group_id.len() as c_int,
+            plaintext.as_ptr(),
+            &mut length,
+            &mut event_id,
+        );
+        assert!(!ciphertext.is_null());
+
+        let reported = unsafe { CStr::from_ptr(event_id) }.to_str().unwrap().to_string();
+        marmot_free_string(event_id);
+        let event: serde_json::Value =
+            serde_json::from_slice(unsafe { slice::from_raw_parts(ciphertext, length as usize) }).unwrap();
+        marmot_free_buffer(ciphertext, length);
+
+        // NIP-01: the ID is the SHA-256 of [0, pubkey, created_at, kind, tags, content]
+        use nostr::hashes::{sha256, Hash};
+        let canonical = serde_json::json!([
+            0,
+            event["pubkey"],
+            event["created_at"],
+            event["kind"],
+            event["tags"],
+            event["content"],
+        ]);
+        let computed = sha256::Hash::hash(canonical.to_string().as_bytes()).to_string();
+        assert_eq!(reported, computed);
+        assert_eq!(event["id"], computed);
+
+        drop(unsafe { Box::from_raw(client) });
+    }
+
+    #[test]
+    fn destroying_a_pointer_the_library_did_not_hand_out_is_a_no_op() {
+        // Not created by `marmot_create_client`, so never tracked as live
+        let foreign = Box::into_raw(Box::new(crate::client::tests::new_client()));
+        assert_eq!(marmot_client_is_valid(foreign), 0);
+
+        marmot_destroy_client(foreign);
+        marmot_wipe_client(foreign);
+
+        // Still allocated and intact: neither call freed it
+        let (group_id, _) = unsafe { &*foreign }.create_group("still alive").unwrap();
+        assert!(unsafe { &*foreign }.group_exists(&group_id).unwrap());
+
+        drop(unsafe { Box::from_raw(foreign) });
+    }
+}
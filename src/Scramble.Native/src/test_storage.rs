@@ -0,0 +1,628 @@
+//! A storage backend for tests that fails on demand.
+//!
+//! `FaultyStorage` wraps MDK's in-memory storage and delegates every call to it,
+//! except that the next reads or writes of MDK's group storage can be made to
+//! fail as a database would. MDK reports such failures as `Error::Group`.
+
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use mdk_core::GroupId;
+use mdk_memory_storage::MdkMemoryStorage;
+use mdk_storage_traits::groups::error::GroupError;
+use mdk_storage_traits::groups::types::{Group, GroupExporterSecret, GroupRelay};
+use mdk_storage_traits::groups::GroupStorage;
+use mdk_storage_traits::messages::error::MessageError;
+use mdk_storage_traits::messages::types::{Message, ProcessedMessage};
+use mdk_storage_traits::messages::MessageStorage;
+use mdk_storage_traits::welcomes::error::WelcomeError;
+use mdk_storage_traits::welcomes::types::{ProcessedWelcome, Welcome};
+use mdk_storage_traits::welcomes::WelcomeStorage;
+use mdk_storage_traits::{Backend, MdkStorageProvider};
+use nostr::{EventId, PublicKey, RelayUrl};
+use openmls_traits::storage::{traits, StorageProvider, CURRENT_VERSION};
+
+/// MDK's in-memory storage with injectable read and write failures.
+pub(crate) struct FaultyStorage {
+    inner: MdkMemoryStorage,
+    read_faults: AtomicU32,
+    write_faults: AtomicU32,
+}
+
+impl FaultyStorage {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: MdkMemoryStorage::new(),
+            read_faults: AtomicU32::new(0),
+            write_faults: AtomicU32::new(0),
+        }
+    }
+
+    /// Make the next `count` group storage reads fail.
+    pub(crate) fn fail_reads(&self, count: u32) {
+        self.read_faults.store(count, Ordering::SeqCst);
+    }
+
+    /// Make the next `count` group storage writes fail.
+    pub(crate) fn fail_writes(&self, count: u32) {
+        self.write_faults.store(count, Ordering::SeqCst);
+    }
+
+    /// Number of injected write failures not yet hit.
+    pub(crate) fn pending_write_faults(&self) -> u32 {
+        self.write_faults.load(Ordering::SeqCst)
+    }
+
+    fn read(&self) -> Result<(), GroupError> {
+        take_fault(&self.read_faults)
+    }
+
+    fn write(&self) -> Result<(), GroupError> {
+        take_fault(&self.write_faults)
+    }
+}
+
+/// Use up one of `faults`, failing if there was one.
+fn take_fault(faults: &AtomicU32) -> Result<(), GroupError> {
+    match faults.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)) {
+        Ok(_) => Err(GroupError::DatabaseError("injected storage fault".into())),
+        Err(_) => Ok(()),
+    }
+}
+
+impl MdkStorageProvider for FaultyStorage {
+    fn backend(&self) -> Backend {
+        self.inner.backend()
+    }
+}
+
+impl GroupStorage for FaultyStorage {
+    fn all_groups(&self) -> Result<Vec<Group>, GroupError> {
+        self.read()?;
+        self.inner.all_groups()
+    }
+
+    fn find_group_by_mls_group_id(&self, mls_group_id: &GroupId) -> Result<Option<Group>, GroupError> {
+        self.read()?;
+        self.inner.find_group_by_mls_group_id(mls_group_id)
+    }
+
+    fn find_group_by_nostr_group_id(&self, nostr_group_id: &[u8; 32]) -> Result<Option<Group>, GroupError> {
+        self.read()?;
+        self.inner.find_group_by_nostr_group_id(nostr_group_id)
+    }
+
+    fn save_group(&self, group: Group) -> Result<(), GroupError> {
+        self.write()?;
+        self.inner.save_group(group)
+    }
+
+    fn messages(&self, mls_group_id: &GroupId) -> Result<Vec<Message>, GroupError> {
+        self.read()?;
+        self.inner.messages(mls_group_id)
+    }
+
+    fn admins(&self, mls_group_id: &GroupId) -> Result<BTreeSet<PublicKey>, GroupError> {
+        self.read()?;
+        self.inner.admins(mls_group_id)
+    }
+
+    fn group_relays(&self, mls_group_id: &GroupId) -> Result<BTreeSet<GroupRelay>, GroupError> {
+        self.read()?;
+        self.inner.group_relays(mls_group_id)
+    }
+
+    fn replace_group_relays(&self, mls_group_id: &GroupId, relays: BTreeSet<RelayUrl>) -> Result<(), GroupError> {
+        self.write()?;
+        self.inner.replace_group_relays(mls_group_id, relays)
+    }
+
+    fn get_group_exporter_secret(
+        &self,
+        mls_group_id: &GroupId,
+        epoch: u64,
+    ) -> Result<Option<GroupExporterSecret>, GroupError> {
+        self.read()?;
+        self.inner.get_group_exporter_secret(mls_group_id, epoch)
+    }
+
+    fn save_group_exporter_secret(&self, group_exporter_secret: GroupExporterSecret) -> Result<(), GroupError> {
+        self.write()?;
+        self.inner.save_group_exporter_secret(group_exporter_secret)
+    }
+}
+
+impl MessageStorage for FaultyStorage {
+    fn save_message(&self, message: Message) -> Result<(), MessageError> {
+        self.inner.save_message(message)
+    }
+
+    fn find_message_by_event_id(&self, event_id: &EventId) -> Result<Option<Message>, MessageError> {
+        self.inner.find_message_by_event_id(event_id)
+    }
+
+    fn save_processed_message(&self, processed_message: ProcessedMessage) -> Result<(), MessageError> {
+        self.inner.save_processed_message(processed_message)
+    }
+
+    fn find_processed_message_by_event_id(&self, event_id: &EventId) -> Result<Option<ProcessedMessage>, MessageError> {
+        self.inner.find_processed_message_by_event_id(event_id)
+    }
+}
+
+impl WelcomeStorage for FaultyStorage {
+    fn save_welcome(&self, welcome: Welcome) -> Result<(), WelcomeError> {
+        self.inner.save_welcome(welcome)
+    }
+
+    fn find_welcome_by_event_id(&self, event_id: &EventId) -> Result<Option<Welcome>, WelcomeError> {
+        self.inner.find_welcome_by_event_id(event_id)
+    }
+
+    fn pending_welcomes(&self) -> Result<Vec<Welcome>, WelcomeError> {
+        self.inner.pending_welcomes()
+    }
+
+    fn save_processed_welcome(&self, processed_welcome: ProcessedWelcome) -> Result<(), WelcomeError> {
+        self.inner.save_processed_welcome(processed_welcome)
+    }
+
+    fn find_processed_welcome_by_event_id(&self, event_id: &EventId) -> Result<Option<ProcessedWelcome>, WelcomeError> {
+        self.inner.find_processed_welcome_by_event_id(event_id)
+    }
+}
+
+impl StorageProvider<CURRENT_VERSION> for FaultyStorage {
+    type Error = <MdkMemoryStorage as StorageProvider<CURRENT_VERSION>>::Error;
+
+    fn write_mls_join_config<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        MlsGroupJoinConfig: traits::MlsGroupJoinConfig<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        config: &MlsGroupJoinConfig,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_mls_join_config(group_id, config)
+    }
+
+    fn append_own_leaf_node<GroupId: traits::GroupId<CURRENT_VERSION>, LeafNode: traits::LeafNode<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        leaf_node: &LeafNode,
+    ) -> Result<(), Self::Error> {
+        self.inner.append_own_leaf_node(group_id, leaf_node)
+    }
+
+    fn queue_proposal<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+        QueuedProposal: traits::QueuedProposal<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+        proposal: &QueuedProposal,
+    ) -> Result<(), Self::Error> {
+        self.inner.queue_proposal(group_id, proposal_ref, proposal)
+    }
+
+    fn write_tree<GroupId: traits::GroupId<CURRENT_VERSION>, TreeSync: traits::TreeSync<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        tree: &TreeSync,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_tree(group_id, tree)
+    }
+
+    fn write_interim_transcript_hash<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        InterimTranscriptHash: traits::InterimTranscriptHash<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        interim_transcript_hash: &InterimTranscriptHash,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_interim_transcript_hash(group_id, interim_transcript_hash)
+    }
+
+    fn write_context<GroupId: traits::GroupId<CURRENT_VERSION>, GroupContext: traits::GroupContext<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        group_context: &GroupContext,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_context(group_id, group_context)
+    }
+
+    fn write_confirmation_tag<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ConfirmationTag: traits::ConfirmationTag<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        confirmation_tag: &ConfirmationTag,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_confirmation_tag(group_id, confirmation_tag)
+    }
+
+    fn write_group_state<GroupState: traits::GroupState<CURRENT_VERSION>, GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        group_state: &GroupState,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_group_state(group_id, group_state)
+    }
+
+    fn write_message_secrets<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        MessageSecrets: traits::MessageSecrets<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        message_secrets: &MessageSecrets,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_message_secrets(group_id, message_secrets)
+    }
+
+    fn write_resumption_psk_store<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ResumptionPskStore: traits::ResumptionPskStore<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        resumption_psk_store: &ResumptionPskStore,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_resumption_psk_store(group_id, resumption_psk_store)
+    }
+
+    fn write_own_leaf_index<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        LeafNodeIndex: traits::LeafNodeIndex<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        own_leaf_index: &LeafNodeIndex,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_own_leaf_index(group_id, own_leaf_index)
+    }
+
+    fn write_group_epoch_secrets<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        GroupEpochSecrets: traits::GroupEpochSecrets<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        group_epoch_secrets: &GroupEpochSecrets,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_group_epoch_secrets(group_id, group_epoch_secrets)
+    }
+
+    fn write_signature_key_pair<
+        SignaturePublicKey: traits::SignaturePublicKey<CURRENT_VERSION>,
+        SignatureKeyPair: traits::SignatureKeyPair<CURRENT_VERSION>,
+    >(
+        &self,
+        public_key: &SignaturePublicKey,
+        signature_key_pair: &SignatureKeyPair,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_signature_key_pair(public_key, signature_key_pair)
+    }
+
+    fn write_encryption_key_pair<
+        EncryptionKey: traits::EncryptionKey<CURRENT_VERSION>,
+        HpkeKeyPair: traits::HpkeKeyPair<CURRENT_VERSION>,
+    >(
+        &self,
+        public_key: &EncryptionKey,
+        key_pair: &HpkeKeyPair,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_encryption_key_pair(public_key, key_pair)
+    }
+
+    fn write_encryption_epoch_key_pairs<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        EpochKey: traits::EpochKey<CURRENT_VERSION>,
+        HpkeKeyPair: traits::HpkeKeyPair<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        epoch: &EpochKey,
+        leaf_index: u32,
+        key_pairs: &[HpkeKeyPair],
+    ) -> Result<(), Self::Error> {
+        self.inner.write_encryption_epoch_key_pairs(group_id, epoch, leaf_index, key_pairs)
+    }
+
+    fn write_key_package<
+        HashReference: traits::HashReference<CURRENT_VERSION>,
+        KeyPackage: traits::KeyPackage<CURRENT_VERSION>,
+    >(
+        &self,
+        hash_ref: &HashReference,
+        key_package: &KeyPackage,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_key_package(hash_ref, key_package)
+    }
+
+    fn write_psk<PskId: traits::PskId<CURRENT_VERSION>, PskBundle: traits::PskBundle<CURRENT_VERSION>>(
+        &self,
+        psk_id: &PskId,
+        psk: &PskBundle,
+    ) -> Result<(), Self::Error> {
+        self.inner.write_psk(psk_id, psk)
+    }
+
+    fn mls_group_join_config<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        MlsGroupJoinConfig: traits::MlsGroupJoinConfig<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<MlsGroupJoinConfig>, Self::Error> {
+        self.inner.mls_group_join_config(group_id)
+    }
+
+    fn own_leaf_nodes<GroupId: traits::GroupId<CURRENT_VERSION>, LeafNode: traits::LeafNode<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Vec<LeafNode>, Self::Error> {
+        self.inner.own_leaf_nodes(group_id)
+    }
+
+    fn queued_proposal_refs<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Vec<ProposalRef>, Self::Error> {
+        self.inner.queued_proposal_refs(group_id)
+    }
+
+    fn queued_proposals<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+        QueuedProposal: traits::QueuedProposal<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Vec<(ProposalRef, QueuedProposal)>, Self::Error> {
+        self.inner.queued_proposals(group_id)
+    }
+
+    fn tree<GroupId: traits::GroupId<CURRENT_VERSION>, TreeSync: traits::TreeSync<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<TreeSync>, Self::Error> {
+        self.inner.tree(group_id)
+    }
+
+    fn group_context<GroupId: traits::GroupId<CURRENT_VERSION>, GroupContext: traits::GroupContext<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<GroupContext>, Self::Error> {
+        self.inner.group_context(group_id)
+    }
+
+    fn interim_transcript_hash<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        InterimTranscriptHash: traits::InterimTranscriptHash<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<InterimTranscriptHash>, Self::Error> {
+        self.inner.interim_transcript_hash(group_id)
+    }
+
+    fn confirmation_tag<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ConfirmationTag: traits::ConfirmationTag<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<ConfirmationTag>, Self::Error> {
+        self.inner.confirmation_tag(group_id)
+    }
+
+    fn group_state<GroupState: traits::GroupState<CURRENT_VERSION>, GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<GroupState>, Self::Error> {
+        self.inner.group_state(group_id)
+    }
+
+    fn message_secrets<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        MessageSecrets: traits::MessageSecrets<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<MessageSecrets>, Self::Error> {
+        self.inner.message_secrets(group_id)
+    }
+
+    fn resumption_psk_store<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ResumptionPskStore: traits::ResumptionPskStore<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<ResumptionPskStore>, Self::Error> {
+        self.inner.resumption_psk_store(group_id)
+    }
+
+    fn own_leaf_index<GroupId: traits::GroupId<CURRENT_VERSION>, LeafNodeIndex: traits::LeafNodeIndex<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<LeafNodeIndex>, Self::Error> {
+        self.inner.own_leaf_index(group_id)
+    }
+
+    fn group_epoch_secrets<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        GroupEpochSecrets: traits::GroupEpochSecrets<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<GroupEpochSecrets>, Self::Error> {
+        self.inner.group_epoch_secrets(group_id)
+    }
+
+    fn signature_key_pair<
+        SignaturePublicKey: traits::SignaturePublicKey<CURRENT_VERSION>,
+        SignatureKeyPair: traits::SignatureKeyPair<CURRENT_VERSION>,
+    >(
+        &self,
+        public_key: &SignaturePublicKey,
+    ) -> Result<Option<SignatureKeyPair>, Self::Error> {
+        self.inner.signature_key_pair(public_key)
+    }
+
+    fn encryption_key_pair<
+        HpkeKeyPair: traits::HpkeKeyPair<CURRENT_VERSION>,
+        EncryptionKey: traits::EncryptionKey<CURRENT_VERSION>,
+    >(
+        &self,
+        public_key: &EncryptionKey,
+    ) -> Result<Option<HpkeKeyPair>, Self::Error> {
+        self.inner.encryption_key_pair(public_key)
+    }
+
+    fn encryption_epoch_key_pairs<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        EpochKey: traits::EpochKey<CURRENT_VERSION>,
+        HpkeKeyPair: traits::HpkeKeyPair<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        epoch: &EpochKey,
+        leaf_index: u32,
+    ) -> Result<Vec<HpkeKeyPair>, Self::Error> {
+        self.inner.encryption_epoch_key_pairs(group_id, epoch, leaf_index)
+    }
+
+    fn key_package<
+        KeyPackageRef: traits::HashReference<CURRENT_VERSION>,
+        KeyPackage: traits::KeyPackage<CURRENT_VERSION>,
+    >(
+        &self,
+        hash_ref: &KeyPackageRef,
+    ) -> Result<Option<KeyPackage>, Self::Error> {
+        self.inner.key_package(hash_ref)
+    }
+
+    fn psk<PskBundle: traits::PskBundle<CURRENT_VERSION>, PskId: traits::PskId<CURRENT_VERSION>>(
+        &self,
+        psk_id: &PskId,
+    ) -> Result<Option<PskBundle>, Self::Error> {
+        self.inner.psk(psk_id)
+    }
+
+    fn remove_proposal<GroupId: traits::GroupId<CURRENT_VERSION>, ProposalRef: traits::ProposalRef<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+    ) -> Result<(), Self::Error> {
+        self.inner.remove_proposal(group_id, proposal_ref)
+    }
+
+    fn delete_own_leaf_nodes<GroupId: traits::GroupId<CURRENT_VERSION>>(&self, group_id: &GroupId) -> Result<(), Self::Error> {
+        self.inner.delete_own_leaf_nodes(group_id)
+    }
+
+    fn delete_group_config<GroupId: traits::GroupId<CURRENT_VERSION>>(&self, group_id: &GroupId) -> Result<(), Self::Error> {
+        self.inner.delete_group_config(group_id)
+    }
+
+    fn delete_tree<GroupId: traits::GroupId<CURRENT_VERSION>>(&self, group_id: &GroupId) -> Result<(), Self::Error> {
+        self.inner.delete_tree(group_id)
+    }
+
+    fn delete_confirmation_tag<GroupId: traits::GroupId<CURRENT_VERSION>>(&self, group_id: &GroupId) -> Result<(), Self::Error> {
+        self.inner.delete_confirmation_tag(group_id)
+    }
+
+    fn delete_group_state<GroupId: traits::GroupId<CURRENT_VERSION>>(&self, group_id: &GroupId) -> Result<(), Self::Error> {
+        self.inner.delete_group_state(group_id)
+    }
+
+    fn delete_context<GroupId: traits::GroupId<CURRENT_VERSION>>(&self, group_id: &GroupId) -> Result<(), Self::Error> {
+        self.inner.delete_context(group_id)
+    }
+
+    fn delete_interim_transcript_hash<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::Error> {
+        self.inner.delete_interim_transcript_hash(group_id)
+    }
+
+    fn delete_message_secrets<GroupId: traits::GroupId<CURRENT_VERSION>>(&self, group_id: &GroupId) -> Result<(), Self::Error> {
+        self.inner.delete_message_secrets(group_id)
+    }
+
+    fn delete_all_resumption_psk_secrets<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::Error> {
+        self.inner.delete_all_resumption_psk_secrets(group_id)
+    }
+
+    fn delete_own_leaf_index<GroupId: traits::GroupId<CURRENT_VERSION>>(&self, group_id: &GroupId) -> Result<(), Self::Error> {
+        self.inner.delete_own_leaf_index(group_id)
+    }
+
+    fn delete_group_epoch_secrets<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::Error> {
+        self.inner.delete_group_epoch_secrets(group_id)
+    }
+
+    fn clear_proposal_queue<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::Error> {
+        self.inner.clear_proposal_queue::<GroupId, ProposalRef>(group_id)
+    }
+
+    fn delete_signature_key_pair<SignaturePublicKey: traits::SignaturePublicKey<CURRENT_VERSION>>(
+        &self,
+        public_key: &SignaturePublicKey,
+    ) -> Result<(), Self::Error> {
+        self.inner.delete_signature_key_pair(public_key)
+    }
+
+    fn delete_encryption_key_pair<EncryptionKey: traits::EncryptionKey<CURRENT_VERSION>>(
+        &self,
+        public_key: &EncryptionKey,
+    ) -> Result<(), Self::Error> {
+        self.inner.delete_encryption_key_pair(public_key)
+    }
+
+    fn delete_encryption_epoch_key_pairs<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        EpochKey: traits::EpochKey<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        epoch: &EpochKey,
+        leaf_index: u32,
+    ) -> Result<(), Self::Error> {
+        self.inner.delete_encryption_epoch_key_pairs(group_id, epoch, leaf_index)
+    }
+
+    fn delete_key_package<KeyPackageRef: traits::HashReference<CURRENT_VERSION>>(
+        &self,
+        hash_ref: &KeyPackageRef,
+    ) -> Result<(), Self::Error> {
+        self.inner.delete_key_package(hash_ref)
+    }
+
+    fn delete_psk<PskKey: traits::PskId<CURRENT_VERSION>>(&self, psk_id: &PskKey) -> Result<(), Self::Error> {
+        self.inner.delete_psk(psk_id)
+    }
+}